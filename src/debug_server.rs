@@ -0,0 +1,332 @@
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use anyhow::Result;
+use log::{info, warn};
+use serde::Serialize;
+
+use crate::vm::debugger::DebuggerCommand;
+use crate::vm::profiler::REPORT_SIZE;
+use crate::vm::symbols::{self, Symbols};
+
+// how long the server blocks before it checks whether the VM is still running
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// a halted VM answers within a frame, a running one does not process debugger commands
+const REPLY_TIMEOUT: Duration = Duration::from_secs(1);
+
+// addresses can also be labels of the symbol file
+const USAGE: &str = "break, continue, reverse-continue, next, previous, goto <frame>, first, last, run-to <address>, run-until-return, breakpoint <address>, \
+                     watch <expression>, clear-watches, registers, diff [<from> <to>], profile [<n>], coverage, memory <address> <len>, set-register <x> <value>, set-i <address>, set-pc <address>, \
+                     write <address> <bytes...>";
+
+// the debugger of the runner the clients control
+pub struct DebugTarget {
+    pub debug_break: Arc<AtomicBool>,
+    pub commands: Sender<DebuggerCommand>,
+    pub symbols: Arc<Symbols>,
+}
+
+#[derive(Debug)]
+enum Request {
+    Break,
+    Command(DebuggerCommand),
+    Registers,
+    Memory { address: u16, len: u16 },
+}
+
+// one JSON object per line, clients can tell the responses apart by their fields
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Response {
+    Ok {
+        ok: bool,
+    },
+    Error {
+        error: String,
+    },
+    Registers {
+        registers: Vec<u8>,
+        i: u16,
+        pc: u16,
+        stack: Vec<u16>,
+    },
+    Memory {
+        address: u16,
+        data: Vec<u8>,
+    },
+}
+
+// frame indices and counts, hex with 0x or decimal, long runs record more frames than fit into 16 bits
+fn parse_index(word: Option<&str>) -> std::result::Result<usize, String> {
+    let word = word.ok_or_else(|| String::from("missing argument"))?;
+    symbols::parse_number(word).ok_or_else(|| format!("{} is not a number", word))
+}
+
+// addresses and values are hex with 0x or decimal
+fn parse_number(word: Option<&str>) -> std::result::Result<u16, String> {
+    let value = parse_index(word)?;
+    match value <= u16::MAX as usize {
+        true => Ok(value as u16),
+        false => Err(format!("{:#X} does not fit into 16 bits", value)),
+    }
+}
+
+// a number or a label
+fn parse_address(word: Option<&str>, symbols: &Symbols) -> std::result::Result<u16, String> {
+    let word = word.ok_or_else(|| String::from("missing argument"))?;
+    symbols.resolve(word).ok_or_else(|| format!("{} is neither a number nor a label", word))
+}
+
+fn parse_byte(word: Option<&str>) -> std::result::Result<u8, String> {
+    let value = parse_number(word)?;
+    match value <= 0xFF {
+        true => Ok(value as u8),
+        false => Err(format!("{:#X} does not fit into a byte", value)),
+    }
+}
+
+fn parse_request(line: &str, symbols: &Symbols) -> std::result::Result<Request, String> {
+    let mut words = line.split_whitespace();
+    let name = words.next().unwrap_or_default();
+
+    let request = match name {
+        "break" => Request::Break,
+        "continue" => Request::Command(DebuggerCommand::Continue),
+        "reverse-continue" => Request::Command(DebuggerCommand::ReverseContinue),
+        "next" => Request::Command(DebuggerCommand::Next),
+        "previous" => Request::Command(DebuggerCommand::Previous),
+        "goto" => Request::Command(DebuggerCommand::GotoFrame(parse_index(words.next())?)),
+        "first" => Request::Command(DebuggerCommand::GotoFirstFrame),
+        "last" => Request::Command(DebuggerCommand::GotoLastFrame),
+        "run-to" => Request::Command(DebuggerCommand::RunTo(parse_address(words.next(), symbols)?)),
+        "run-until-return" => Request::Command(DebuggerCommand::RunUntilReturn),
+        "breakpoint" => Request::Command(DebuggerCommand::AddBreakpoint(parse_address(words.next(), symbols)?)),
+        "registers" => Request::Registers,
+        "watch" => Request::Command(DebuggerCommand::AddWatch(words.collect::<Vec<&str>>().join(" "))),
+        "clear-watches" => Request::Command(DebuggerCommand::ClearWatches),
+        // without frames it compares the previous with the current frame
+        "diff" => match words.next() {
+            Some(from) => Request::Command(DebuggerCommand::DiffFrames {
+                from: parse_index(Some(from))?,
+                to: parse_index(words.next())?,
+            }),
+            None => Request::Command(DebuggerCommand::DiffPrevious),
+        },
+        "profile" => Request::Command(DebuggerCommand::PrintProfile(match words.next() {
+            Some(n) => parse_index(Some(n))?,
+            None => REPORT_SIZE,
+        })),
+        "coverage" => Request::Command(DebuggerCommand::PrintCoverage),
+        "memory" => Request::Memory {
+            address: parse_address(words.next(), symbols)?,
+            len: parse_number(words.next())?,
+        },
+        "set-register" => Request::Command(DebuggerCommand::SetRegister {
+            register: parse_number(words.next())? as usize,
+            value: parse_byte(words.next())?,
+        }),
+        "set-i" => Request::Command(DebuggerCommand::SetI(parse_address(words.next(), symbols)?)),
+        "set-pc" => Request::Command(DebuggerCommand::SetPC(parse_address(words.next(), symbols)?)),
+        "write" => {
+            let address = parse_address(words.next(), symbols)?;
+            let data = words.map(|x| parse_byte(Some(x))).collect::<std::result::Result<Vec<u8>, String>>()?;
+
+            Request::Command(DebuggerCommand::WriteMemory { address, data })
+        }
+        _ => return Err(format!("unknown command {}, expected one of {}", name, USAGE)),
+    };
+
+    Ok(request)
+}
+
+fn handle_request(target: &DebugTarget, request: Request) -> Response {
+    let halted = target.debug_break.load(Ordering::SeqCst);
+
+    match request {
+        Request::Break => {
+            target.debug_break.store(true, Ordering::SeqCst);
+            Response::Ok { ok: true }
+        }
+        // debugger commands are only processed while the VM is halted, they would pile up otherwise
+        _ if halted == false => Response::Error {
+            error: String::from("the VM is running, send break first"),
+        },
+        Request::Command(command) => match target.commands.send(command) {
+            Ok(..) => Response::Ok { ok: true },
+            Err(..) => Response::Error {
+                error: String::from("the VM is not running anymore"),
+            },
+        },
+        Request::Registers | Request::Memory { .. } => {
+            let (tx, rx) = channel();
+            if target.commands.send(DebuggerCommand::ReadState(tx)).is_err() {
+                return Response::Error {
+                    error: String::from("the VM is not running anymore"),
+                };
+            }
+
+            let state = match rx.recv_timeout(REPLY_TIMEOUT) {
+                Ok(state) => state,
+                Err(..) => {
+                    return Response::Error {
+                        error: String::from("the VM did not answer"),
+                    }
+                }
+            };
+
+            match request {
+                Request::Memory { address, len } => {
+                    let start = (address as usize).min(state.memory.len());
+                    let end = (start + len as usize).min(state.memory.len());
+
+                    Response::Memory {
+                        address,
+                        data: state.memory[start..end].to_vec(),
+                    }
+                }
+                _ => Response::Registers {
+                    registers: state.registers,
+                    i: state.i,
+                    pc: state.pc,
+                    stack: state.stack,
+                },
+            }
+        }
+    }
+}
+
+fn serve_client(target: &DebugTarget, stream: TcpStream, alive: &AtomicBool) -> std::io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(POLL_INTERVAL))?;
+
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+
+    while alive.load(Ordering::SeqCst) {
+        // a timeout keeps the part of the line which was already received
+        match reader.read_line(&mut line) {
+            Ok(0) => break,
+            Ok(..) => (),
+            Err(err) if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut => continue,
+            Err(err) => return Err(err),
+        }
+
+        if line.ends_with('\n') == false {
+            continue;
+        }
+
+        if line.trim().is_empty() == false {
+            let response = match parse_request(line.trim(), &target.symbols) {
+                Ok(request) => handle_request(target, request),
+                Err(error) => Response::Error { error },
+            };
+
+            let json = serde_json::to_string(&response).unwrap_or_default();
+            writeln!(writer, "{}", json)?;
+        }
+        line.clear();
+    }
+
+    Ok(())
+}
+
+// serves one client at a time until alive is cleared
+pub fn start(address: &str, target: DebugTarget, alive: Arc<AtomicBool>) -> Result<JoinHandle<()>> {
+    let listener = TcpListener::bind(address)?;
+    listener.set_nonblocking(true)?;
+    info!("Debug server listening on {}", address);
+
+    let handle = thread::spawn(move || {
+        while alive.load(Ordering::SeqCst) {
+            match listener.accept() {
+                Ok((stream, peer)) => {
+                    info!("Debug client {} connected", peer);
+
+                    if let Err(err) = serve_client(&target, stream, &alive) {
+                        warn!("Debug client {} failed: {}", peer, err);
+                    }
+
+                    info!("Debug client {} disconnected", peer);
+                }
+                Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(err) => {
+                    warn!("Debug server stopped: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_request_without_symbols(line: &str) -> std::result::Result<Request, String> {
+        parse_request(line, &Symbols::default())
+    }
+
+    #[test]
+    fn parse_request_reads_commands_and_arguments() {
+        assert!(matches!(parse_request_without_symbols("break"), Ok(Request::Break)));
+        assert!(matches!(parse_request_without_symbols("memory 0x200 16"), Ok(Request::Memory { address: 0x200, len: 16 })));
+        assert!(matches!(
+            parse_request_without_symbols("run-to 0x2A4"),
+            Ok(Request::Command(DebuggerCommand::RunTo(0x2A4)))
+        ));
+        assert!(matches!(
+            parse_request_without_symbols("goto 70000"),
+            Ok(Request::Command(DebuggerCommand::GotoFrame(70000)))
+        ));
+        assert!(matches!(
+            parse_request_without_symbols("diff 65536 65537"),
+            Ok(Request::Command(DebuggerCommand::DiffFrames { from: 65536, to: 65537 }))
+        ));
+        assert!(matches!(
+            parse_request_without_symbols("set-register 0xF 1"),
+            Ok(Request::Command(DebuggerCommand::SetRegister { register: 0xF, value: 1 }))
+        ));
+
+        match parse_request_without_symbols("write 0x300 0xFF 0") {
+            Ok(Request::Command(DebuggerCommand::WriteMemory { address, data })) => {
+                assert_eq!(address, 0x300);
+                assert_eq!(data, vec![0xFF, 0]);
+            }
+            request => panic!("unexpected {:?}", request),
+        }
+    }
+
+    #[test]
+    fn parse_request_rejects_invalid_requests() {
+        assert!(parse_request_without_symbols("jump").is_err());
+        assert!(parse_request_without_symbols("run-to").is_err());
+        assert!(parse_request_without_symbols("set-pc 0xZZ").is_err());
+        assert!(parse_request_without_symbols("write 0x300 0x100").is_err());
+        assert!(parse_request_without_symbols("memory 0x10000 1").is_err());
+        assert!(parse_request_without_symbols("breakpoint main_loop").is_err());
+    }
+
+    #[test]
+    fn parse_request_resolves_labels() {
+        let mut symbols = Symbols::default();
+        symbols.add("main_loop", 0x2A4);
+
+        assert!(matches!(
+            parse_request("breakpoint main_loop", &symbols),
+            Ok(Request::Command(DebuggerCommand::AddBreakpoint(0x2A4)))
+        ));
+        assert!(matches!(
+            parse_request("run-to 0x200", &symbols),
+            Ok(Request::Command(DebuggerCommand::RunTo(0x200)))
+        ));
+    }
+}
@@ -1,4 +1,21 @@
-mod input;
+pub mod input;
+mod attract_mode;
+mod beep;
+mod breakpoint_panel;
+mod collision_highlight;
+mod debugger_panel;
+mod heatmap_panel;
+mod macros;
+mod magnifier;
+mod movie_session;
+mod overlay;
+pub mod palette;
+mod pause_menu;
+mod speedrun_timer;
+mod sprite_export;
+mod telemetry_panel;
+mod thumbnails;
+mod toasts;
 mod emulator;
 
 pub use emulator::Emulator as Emulator;
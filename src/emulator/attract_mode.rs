@@ -0,0 +1,94 @@
+use std::path::Path;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::vm::config::Config;
+
+const BANNER_HEIGHT: f32 = 34.;
+
+// runs every ROM in the ROM directory for a while without input, any key hands the current game to the player
+pub struct AttractMode {
+    roms: Vec<String>,
+    index: usize,
+    frames_per_rom: u32,
+    frames_left: u32,
+}
+
+impl AttractMode {
+    pub fn new(config: &Config, roms: Vec<String>) -> AttractMode {
+        let frames_per_rom = config.attract_mode.seconds.max(1) * 60;
+
+        AttractMode {
+            index: roms.iter().position(|x| *x == config.rom).unwrap_or(0),
+            roms,
+            frames_per_rom,
+            frames_left: frames_per_rom,
+        }
+    }
+
+    // called once per 60hz frame, returns the ROM to load once the current one ran long enough
+    pub fn update(&mut self) -> Option<String> {
+        self.frames_left = self.frames_left.saturating_sub(1);
+
+        if self.frames_left > 0 || self.roms.len() < 2 {
+            return None;
+        }
+
+        self.index = (self.index + 1) % self.roms.len();
+        self.frames_left = self.frames_per_rom;
+
+        self.roms.get(self.index).cloned()
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let name = self
+            .roms
+            .get(self.index)
+            .and_then(|x| Path::new(x).file_name())
+            .and_then(|x| x.to_str())
+            .unwrap_or("");
+
+        let area = graphics::screen_coordinates(ctx);
+        let y = area.h - BANNER_HEIGHT;
+
+        let background = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(0., y, area.w, BANNER_HEIGHT),
+            Color::new(0., 0., 0., 0.8),
+        )?;
+
+        let text = Text::new(format!(
+            "DEMO  {}  ({} / {})  -  press any key to play",
+            name,
+            self.index + 1,
+            self.roms.len()
+        ));
+
+        graphics::draw(ctx, &background, DrawParam::new())?;
+        graphics::draw(ctx, &text, DrawParam::new().dest([10., y + 9.]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_cycles_through_roms() {
+        let mut config = Config::default();
+        config.rom = String::from("roms/B");
+        config.attract_mode.seconds = 1;
+
+        let roms = vec![String::from("roms/A"), String::from("roms/B")];
+        let mut attract_mode = AttractMode::new(&config, roms);
+
+        for _ in 0..59 {
+            assert_eq!(attract_mode.update(), None);
+        }
+
+        assert_eq!(attract_mode.update(), Some(String::from("roms/A")));
+        assert_eq!(attract_mode.index, 0);
+    }
+}
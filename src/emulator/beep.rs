@@ -0,0 +1,94 @@
+use std::io::Cursor;
+
+use anyhow::Result;
+use log::{info, warn};
+
+use ggez::audio::{self, SoundSource};
+use ggez::Context;
+use rodio::{DeviceTrait, Sink};
+
+use crate::vm::audio as vm_audio;
+use crate::vm::config::Config;
+
+// ggez always plays on the default output device, other devices are played on through rodio directly
+enum Output {
+    Default(audio::Source),
+    Device { device: rodio::Device, sink: Option<Sink> },
+}
+
+pub struct Beep {
+    sound: Vec<u8>,
+    output: Output,
+}
+
+impl Beep {
+    pub fn new(config: &Config, ctx: &mut Context) -> Result<Beep> {
+        let sound = vm_audio::sample(config.beep_frequency)?;
+
+        let output = match config.audio_device.as_ref().and_then(|name| Beep::find_device(name)) {
+            Some(device) => Output::Device { device, sink: None },
+            None => Output::Default(audio::Source::from_data(ctx, audio::SoundData::from_bytes(&sound))?),
+        };
+
+        Ok(Beep { sound, output })
+    }
+
+    pub fn play(&mut self) -> Result<()> {
+        match &mut self.output {
+            Output::Default(source) => source.play()?,
+            Output::Device { device, sink } => {
+                let new_sink = Sink::new(device);
+                new_sink.append(rodio::Decoder::new(Cursor::new(self.sound.clone()))?);
+
+                *sink = Some(new_sink);
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        match &mut self.output {
+            Output::Default(source) => source.stop(),
+            Output::Device { sink, .. } => {
+                if let Some(sink) = sink.take() {
+                    sink.stop();
+                }
+            }
+        }
+    }
+
+    pub fn playing(&self) -> bool {
+        match &self.output {
+            Output::Default(source) => source.playing(),
+            Output::Device { sink, .. } => sink.as_ref().map(|x| x.empty() == false).unwrap_or(false),
+        }
+    }
+
+    fn device_names() -> Vec<String> {
+        match rodio::output_devices() {
+            Ok(devices) => devices.filter_map(|x| x.name().ok()).collect(),
+            Err(err) => {
+                warn!("Cannot list the audio output devices: {}", err);
+                Vec::new()
+            }
+        }
+    }
+
+    fn find_device(name: &str) -> Option<rodio::Device> {
+        let device = rodio::output_devices()
+            .ok()?
+            .find(|x| x.name().map(|x| x == name).unwrap_or(false));
+
+        match &device {
+            Some(_) => info!("Playing sound on audio device {}", name),
+            None => warn!(
+                "Audio device {} not found, using the default device. Available devices: {}",
+                name,
+                Beep::device_names().join(", ")
+            ),
+        }
+
+        device
+    }
+}
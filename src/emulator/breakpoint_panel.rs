@@ -0,0 +1,210 @@
+use ggez::input::keyboard::KeyCode;
+use ggez::{Context, GameResult};
+
+use super::overlay::draw_text_overlay;
+use crate::runner::Runner;
+use crate::vm::breakpoints::Breakpoint;
+use crate::vm::debugger::DebuggerCommand;
+use crate::vm::watchpoints::{Access, Watchpoint};
+
+pub struct BreakpointPanel {
+    visible: bool,
+    selected: usize,
+    // hex numbers separated by spaces, an address or the values of a poke
+    entry: String,
+}
+
+// an address has at most 4 hex digits, XO-CHIP has 64K of memory
+const MAX_ADDRESS_DIGITS: usize = 4;
+
+fn hex_digit(keycode: KeyCode) -> Option<char> {
+    let digit = match keycode {
+        KeyCode::Key0 | KeyCode::Numpad0 => '0',
+        KeyCode::Key1 | KeyCode::Numpad1 => '1',
+        KeyCode::Key2 | KeyCode::Numpad2 => '2',
+        KeyCode::Key3 | KeyCode::Numpad3 => '3',
+        KeyCode::Key4 | KeyCode::Numpad4 => '4',
+        KeyCode::Key5 | KeyCode::Numpad5 => '5',
+        KeyCode::Key6 | KeyCode::Numpad6 => '6',
+        KeyCode::Key7 | KeyCode::Numpad7 => '7',
+        KeyCode::Key8 | KeyCode::Numpad8 => '8',
+        KeyCode::Key9 | KeyCode::Numpad9 => '9',
+        KeyCode::A => 'A',
+        KeyCode::B => 'B',
+        KeyCode::C => 'C',
+        KeyCode::D => 'D',
+        KeyCode::E => 'E',
+        KeyCode::F => 'F',
+        _ => return None,
+    };
+
+    Some(digit)
+}
+
+impl BreakpointPanel {
+    pub fn new() -> BreakpointPanel {
+        BreakpointPanel {
+            visible: false,
+            selected: 0,
+            entry: String::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.visible && self.entry.is_empty() == false
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.entry.clear();
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, runner: &mut Runner) {
+        if let Some(digit) = hex_digit(keycode) {
+            if self.entry.split(' ').last().map_or(0, |x| x.len()) < MAX_ADDRESS_DIGITS {
+                self.entry.push(digit);
+            }
+            return;
+        }
+
+        // while an entry is typed the keys edit it and use it as address or values
+        if self.entry.is_empty() == false {
+            let values: Option<Vec<u16>> = self
+                .entry
+                .split_whitespace()
+                .map(|x| u16::from_str_radix(x, 16).ok())
+                .collect();
+            let values = values.unwrap_or_default();
+            let address = values.first().copied();
+            let watchpoint = |access| Watchpoint::new(address.unwrap_or_default(), None, access);
+
+            let command = match keycode {
+                KeyCode::Return => address.map(DebuggerCommand::AddBreakpoint),
+                KeyCode::W => {
+                    runner.add_watchpoint(watchpoint(Access::Write));
+                    None
+                }
+                KeyCode::X => {
+                    runner.add_watchpoint(watchpoint(Access::ReadWrite));
+                    None
+                }
+                KeyCode::M => address.map(|x| DebuggerCommand::PrintMemory { start: x, len: 0x40 }),
+                // the number of a recorded frame, also in hex
+                KeyCode::G => address.map(|x| DebuggerCommand::GotoFrame(x as usize)),
+                KeyCode::Delete => {
+                    if let Some(address) = address {
+                        runner.remove_watchpoints_at(address);
+                    }
+                    None
+                }
+                KeyCode::P => address.map(DebuggerCommand::SetPC),
+                KeyCode::I => address.map(DebuggerCommand::SetI),
+                // register and value, e.g. 3 1F sets V3 to 0x1F
+                KeyCode::V => match values.as_slice() {
+                    [register, value] if *value <= 0xFF => Some(DebuggerCommand::SetRegister {
+                        register: *register as usize,
+                        value: *value as u8,
+                    }),
+                    _ => None,
+                },
+                // address followed by the bytes to write, e.g. 2A4 FF 00
+                KeyCode::O => match values.split_first() {
+                    Some((address, data)) if data.is_empty() == false && data.iter().all(|x| *x <= 0xFF) => {
+                        Some(DebuggerCommand::WriteMemory {
+                            address: *address,
+                            data: data.iter().map(|x| *x as u8).collect(),
+                        })
+                    }
+                    _ => None,
+                },
+                KeyCode::Space => {
+                    if self.entry.ends_with(' ') == false {
+                        self.entry.push(' ');
+                    }
+                    return;
+                }
+                KeyCode::Back => {
+                    self.entry.pop();
+                    return;
+                }
+                _ => return,
+            };
+
+            if let Some(command) = command {
+                runner.send_debugger_command(command);
+            }
+            self.entry.clear();
+            return;
+        }
+
+        // the watchpoints are listed after the breakpoints
+        let breakpoint_count = runner.get_breakpoints().len();
+        let watchpoint = self.selected.checked_sub(breakpoint_count);
+
+        match (keycode, watchpoint) {
+            (KeyCode::Up, _) => self.selected = self.selected.saturating_sub(1),
+            (KeyCode::Down, _) => self.selected += 1,
+            (KeyCode::Space, None) | (KeyCode::Return, None) => runner.toggle_breakpoint_enabled(self.selected),
+            (KeyCode::Space, Some(index)) | (KeyCode::Return, Some(index)) => runner.toggle_watchpoint_enabled(index),
+            (KeyCode::Delete, None) | (KeyCode::Back, None) => runner.remove_breakpoint(self.selected),
+            (KeyCode::Delete, Some(index)) | (KeyCode::Back, Some(index)) => runner.remove_watchpoint(index),
+            // disabled breakpoints can be used as run to targets
+            (KeyCode::T, None) => {
+                if let Some(breakpoint) = runner.get_breakpoints().get(self.selected) {
+                    runner.send_debugger_command(DebuggerCommand::RunTo(breakpoint.address));
+                }
+            }
+            _ => (),
+        }
+
+        let count = runner.get_breakpoints().len() + runner.get_watchpoints().len();
+        self.selected = self.selected.min(count.saturating_sub(1));
+    }
+
+    pub fn draw(&self, ctx: &mut Context, breakpoints: &[Breakpoint], watchpoints: &[Watchpoint]) -> GameResult<()> {
+        let mut content = String::from("Breakpoints and watchpoints (Up/Down select, Space toggle, Delete remove, T run to)\n");
+        content.push_str(&format!(
+            "Address (hex digits, Return breakpoint, W write / X any access watchpoint, Delete removes watchpoints, M dumps memory, G goes to frame): {}\n",
+            self.entry
+        ));
+        content.push_str("Pokes (Space separates values): P sets PC, I sets I, V register value, O address bytes writes memory\n\n");
+
+        if breakpoints.is_empty() {
+            content.push_str("No breakpoints set");
+        }
+
+        for (i, breakpoint) in breakpoints.iter().enumerate() {
+            content.push_str(&format!(
+                "{} [{}] {:#05X}  hits: {}{}\n",
+                if i == self.selected { ">" } else { " " },
+                if breakpoint.enabled { "x" } else { " " },
+                breakpoint.address,
+                breakpoint.hit_count,
+                match &breakpoint.condition {
+                    Some(condition) => format!("  if {}", condition),
+                    None => String::new(),
+                },
+            ));
+        }
+
+        if watchpoints.is_empty() == false {
+            content.push_str("\nWatchpoints\n");
+        }
+
+        for (i, watchpoint) in watchpoints.iter().enumerate() {
+            content.push_str(&format!(
+                "{} [{}] {}  hits: {}\n",
+                if breakpoints.len() + i == self.selected { ">" } else { " " },
+                if watchpoint.enabled { "x" } else { " " },
+                watchpoint,
+                watchpoint.hit_count,
+            ));
+        }
+
+        draw_text_overlay(ctx, content)
+    }
+}
@@ -0,0 +1,70 @@
+use crate::vm::constants::{HIRES_SCREEN_SIZE, SCREEN_SIZE_X};
+use crate::vm::screen::Screen;
+
+// number of 60hz frames a collided pixel stays highlighted
+const HIGHLIGHT_FRAMES: u8 = 12;
+
+// fades out the pixels which were involved in a collision
+// sized for the hi-res screen, a low-res screen only uses the first SCREEN_SIZE pixels
+pub struct CollisionHighlight {
+    frames_left: [u8; HIRES_SCREEN_SIZE],
+    width: usize,
+}
+
+impl CollisionHighlight {
+    pub fn new() -> CollisionHighlight {
+        CollisionHighlight {
+            frames_left: [0; HIRES_SCREEN_SIZE],
+            width: SCREEN_SIZE_X,
+        }
+    }
+
+    // called once per frame with the pixels which collided since the previous frame
+    pub fn update(&mut self, collided: &Screen) {
+        if collided.width() != self.width {
+            self.clear();
+            self.width = collided.width();
+        }
+
+        for (frames_left, (_, _, is_set)) in self.frames_left.iter_mut().zip(collided.iter_pixels()) {
+            *frames_left = match is_set {
+                false => frames_left.saturating_sub(1),
+                true => HIGHLIGHT_FRAMES,
+            };
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.frames_left = [0; HIRES_SCREEN_SIZE];
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.frames_left.iter().any(|x| *x > 0)
+    }
+
+    pub fn is_highlighted(&self, x: usize, y: usize) -> bool {
+        self.frames_left[x + y * self.width] > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn highlight_fades_after_frames() {
+        let mut highlight = CollisionHighlight::new();
+        let mut collided = Screen::new();
+        collided.set(3, 2, true);
+
+        highlight.update(&collided);
+        assert!(highlight.is_highlighted(3, 2));
+        assert!(!highlight.is_highlighted(4, 2));
+
+        for _ in 0..HIGHLIGHT_FRAMES {
+            highlight.update(&Screen::new());
+        }
+
+        assert!(!highlight.is_active());
+    }
+}
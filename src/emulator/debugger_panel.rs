@@ -0,0 +1,102 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::vm::debugger::DebugView;
+
+// shows the registers, stack, timers and the instructions around PC of the current frame
+pub struct DebuggerPanel {
+    visible: bool,
+}
+
+impl DebuggerPanel {
+    pub fn new() -> DebuggerPanel {
+        DebuggerPanel { visible: false }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    fn format(view: &DebugView, halted: bool) -> String {
+        let mut content = String::from(if halted { "Halted\n" } else { "Running\n" });
+
+        for (i, row) in view.registers.chunks(4).enumerate() {
+            let registers: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(j, x)| format!("V{:X} {:02X}", i * 4 + j, x))
+                .collect();
+            content.push_str(&format!("{}\n", registers.join("  ")));
+        }
+
+        content.push_str(&format!(
+            "I {:03X}  PC {:03X}  DT {:02X}  ST {:02X}\n",
+            view.i, view.pc, view.delay_timer, view.sound_timer
+        ));
+
+        let stack: Vec<String> = view.stack.iter().rev().map(|x| format!("{:03X}", x)).collect();
+        content.push_str(&format!("Stack: {}\n\n", if stack.is_empty() { String::from("empty") } else { stack.join(" ") }));
+
+        for (address, raw_opcode, decoded) in &view.disassembly {
+            content.push_str(&format!(
+                "{} {:03X}  {:04X}  {}\n",
+                if *address == view.pc { ">" } else { " " },
+                address,
+                raw_opcode,
+                decoded
+            ));
+        }
+
+        content
+    }
+
+    pub fn draw(&self, ctx: &mut Context, view: &DebugView, halted: bool) -> GameResult<()> {
+        let text = Text::new(DebuggerPanel::format(view, halted));
+        let (width, height) = (text.width(ctx) as f32, text.height(ctx) as f32);
+
+        let background = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(10., 10., width + 12., height + 8.),
+            Color::new(0., 0., 0., 0.8),
+        )?;
+
+        graphics::draw(ctx, &background, DrawParam::new())?;
+        graphics::draw(ctx, &text, DrawParam::new().dest([16., 14.]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_marks_the_current_instruction() {
+        let view = DebugView {
+            registers: (0..16).collect(),
+            i: 0x2A0,
+            pc: 0x202,
+            stack: vec![0x204, 0x310],
+            delay_timer: 0x3C,
+            sound_timer: 0,
+            disassembly: vec![
+                (0x200, 0x6A02, String::from("Const_Set_Reg { x: 10, nn: 2 }")),
+                (0x202, 0x00EE, String::from("Flow_Return")),
+            ],
+        };
+
+        let content = DebuggerPanel::format(&view, true);
+        let lines: Vec<&str> = content.lines().collect();
+
+        assert_eq!(lines[0], "Halted");
+        assert_eq!(lines[1], "V0 00  V1 01  V2 02  V3 03");
+        assert_eq!(lines[5], "I 2A0  PC 202  DT 3C  ST 00");
+        assert_eq!(lines[6], "Stack: 310 204");
+        assert_eq!(lines[8], "  200  6A02  Const_Set_Reg { x: 10, nn: 2 }");
+        assert_eq!(lines[9], "> 202  00EE  Flow_Return");
+    }
+}
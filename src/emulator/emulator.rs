@@ -1,21 +1,47 @@
+use std::fs;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
-use log::error;
+use log::{error, info, warn};
 use anyhow::Result;
 
-use ggez::audio;
-use ggez::audio::SoundSource;
-use ggez::event::EventHandler;
+use ggez::event::{self, EventHandler};
 use ggez::input::keyboard;
 use ggez::input::keyboard::{KeyCode, KeyMods};
-use ggez::{graphics, Context, GameResult};
+use ggez::{graphics, timer, Context, GameResult};
 
+use super::attract_mode::AttractMode;
+use super::beep::Beep;
+use super::breakpoint_panel::BreakpointPanel;
+use super::collision_highlight::CollisionHighlight;
+use super::debugger_panel::DebuggerPanel;
+use super::heatmap_panel::HeatmapPanel;
 use super::input::GGEZInput;
+use super::magnifier::Magnifier;
+use super::movie_session::MovieSession;
+use super::overlay::{draw_label, draw_text_overlay};
+use super::palette;
+use super::pause_menu::{MenuAction, PauseMenu};
+use super::speedrun_timer::SpeedrunTimer;
+use super::sprite_export;
+use super::telemetry_panel::TelemetryPanel;
+use super::toasts::Toasts;
 use crate::runner::Runner;
-use crate::vm::audio as vm_audio;
 use crate::vm::config::Config;
-use crate::vm::constants::{SCREEN_SIZE_X, SCREEN_SIZE_Y};
+use crate::vm::constants::SCREEN_SIZE_X;
 use crate::vm::debugger::DebuggerCommand;
+use crate::vm::display::{Snapshot, SpriteDraw};
+use crate::vm::movie_input::MovieInput;
+use crate::vm::profiler::REPORT_SIZE;
+use crate::vm::quirks::Quirks;
+
+const UPDATES_PER_SECOND: u32 = 60;
+
+// the screen is considered static once it has not changed for this long
+const IDLE_AFTER: Duration = Duration::from_secs(2);
+// frame time while idle, which renders the overlays at 10 fps
+const IDLE_FRAME_TIME: Duration = Duration::from_millis(100);
 
 pub struct Emulator {
     config: Config,
@@ -23,47 +49,400 @@ pub struct Emulator {
 
     runner: Runner,
     input: Arc<Mutex<GGEZInput>>,
-    beep: audio::Source,
+    beep: Beep,
+
+    breakpoint_panel: BreakpointPanel,
+    pause_menu: PauseMenu,
+    magnifier: Magnifier,
+    collision_highlight: CollisionHighlight,
+    telemetry_panel: TelemetryPanel,
+    debugger_panel: DebuggerPanel,
+    heatmap_panel: HeatmapPanel,
+    speedrun_timer: SpeedrunTimer,
+    movie: Option<MovieSession>,
+    attract_mode: Option<AttractMode>,
+    toasts: Toasts,
+
+    previous_snapshot: Option<Snapshot>,
+    // sprites drawn during the last frame, only collected while outlines are shown
+    sprite_draws: Option<Vec<SpriteDraw>>,
+    // set by events which change what is drawn besides the VM screen
+    redraw: bool,
+    // release of the key which ended the demo
+    ignore_key_up: Option<KeyCode>,
+    // the VM is paused and only runs a frame when the frame advance key is pressed
+    frame_advance: bool,
+    // set while the rewind key is held, true if the VM was paused before
+    rewinding: Option<bool>,
+    // last time the screen changed or a redraw was requested
+    last_change: Instant,
+    // frames not rendered since the last rendered one
+    skipped_frames: u8,
 }
 
 impl Emulator {
-    pub fn new(ctx: &mut Context, config: Config) -> Result<Emulator> {
-        let (input, runner) = Emulator::create_runner(&config)?;
+    pub fn new(ctx: &mut Context, mut config: Config) -> Result<Emulator> {
+        config.apply_rom_settings();
+        let movie = MovieSession::from_config(&mut config)?;
+        let (input, runner) = Emulator::create_runner(&config, movie.as_ref().and_then(|x| x.input()))?;
+        let attract_mode = match config.attract_mode.enable {
+            true => Some(AttractMode::new(&config, PauseMenu::list_roms(&config))),
+            false => None,
+        };
+        let mut pause_menu = PauseMenu::new();
+        pause_menu.set_quirks(runner.get_quirks());
 
-        Ok(Emulator {
-            beep: Emulator::create_beep(&config, ctx)?,
+        let mut emulator = Emulator {
+            beep: Beep::new(&config, ctx)?,
             screen_scaling: config.screen_scaling,
             config,
             input,
             runner,
-        })
-    }
+            breakpoint_panel: BreakpointPanel::new(),
+            pause_menu,
+            magnifier: Magnifier::new(),
+            collision_highlight: CollisionHighlight::new(),
+            telemetry_panel: TelemetryPanel::new(),
+            debugger_panel: DebuggerPanel::new(),
+            heatmap_panel: HeatmapPanel::new(),
+            speedrun_timer: SpeedrunTimer::new(),
+            movie,
+            attract_mode,
+            toasts: Toasts::new(),
+            previous_snapshot: None,
+            sprite_draws: None,
+            redraw: true,
+            ignore_key_up: None,
+            frame_advance: false,
+            rewinding: None,
+            last_change: Instant::now(),
+            skipped_frames: 0,
+        };
+        emulator.resume_session();
 
-    fn create_runner(config: &Config) -> Result<(Arc<Mutex<GGEZInput>>, Runner)> {
-        let input = Arc::new(Mutex::new(GGEZInput::new(&config)));
-        Ok((input.clone(), Runner::new(&config, input.clone())?))
+        Ok(emulator)
     }
 
-    fn create_beep(config: &Config, ctx: &mut Context) -> Result<audio::Source> {
-        let sound_bytes = vm_audio::sample(config.beep_frequency)?;
-        Ok(audio::Source::from_data(ctx, audio::SoundData::from_bytes(sound_bytes.as_slice())).unwrap())
+    fn create_runner(config: &Config, movie_input: Option<MovieInput>) -> Result<(Arc<Mutex<GGEZInput>>, Runner)> {
+        let input = Arc::new(Mutex::new(GGEZInput::new(&config)));
+        Ok((input.clone(), Runner::new(&config, input.clone(), movie_input)?))
     }
 
     fn reset(&mut self, ctx: &mut Context) -> Result<()> {
         let config = Config::load()?;
-        let (input, runner) = Emulator::create_runner(&config)?;
+        self.restart(ctx, config)
+    }
+
+    fn load_rom(&mut self, ctx: &mut Context, rom: String) -> Result<()> {
+        let mut config = Config::load()?;
+        config.rom = rom;
 
-        self.beep = Emulator::create_beep(&config, ctx)?;
+        self.save_session();
+        self.restart(ctx, config)?;
+        self.resume_session();
+
+        Ok(())
+    }
+
+    fn restart(&mut self, ctx: &mut Context, mut config: Config) -> Result<()> {
+        self.update_movie();
+        config.apply_rom_settings();
+        let movie = MovieSession::from_config(&mut config)?;
+        let (input, runner) = Emulator::create_runner(&config, movie.as_ref().and_then(|x| x.input()))?;
+
+        self.beep = Beep::new(&config, ctx)?;
         self.config = config;
         self.input = input;
         self.runner = runner;
+        self.pause_menu.set_quirks(self.runner.get_quirks());
+        self.movie = movie;
+        self.previous_snapshot = None;
+        self.collision_highlight.clear();
+        self.speedrun_timer.reset();
+        if self.heatmap_panel.is_visible() {
+            self.runner.enable_heatmap();
+        }
 
         Ok(())
     }
-}
 
-impl EventHandler for Emulator {
-    fn update(&mut self, _ctx: &mut Context) -> GameResult<()> {
+    // the VM does not see key presses while an overlay is open or a movie is played back
+    fn captures_input(&self) -> bool {
+        let playing_movie = match &self.movie {
+            Some(movie) => movie.is_playing(),
+            None => false,
+        };
+
+        self.breakpoint_panel.is_visible() == false
+            && self.pause_menu.is_visible() == false
+            && playing_movie == false
+            && self.attract_mode.is_none()
+    }
+
+    fn handle_menu_action(&mut self, ctx: &mut Context, action: MenuAction) {
+        let result = match action {
+            MenuAction::Resume => Ok(()),
+            MenuAction::Reset => self.reset(ctx),
+            MenuAction::LoadRom(rom) => self.load_rom(ctx, rom),
+            MenuAction::ToggleMute => {
+                self.config.mute = !self.config.mute;
+                return;
+            }
+            MenuAction::CycleSpeed => {
+                self.config.speed_multiplier = self.config.next_speed_multiplier();
+                self.runner.set_speed_multiplier(self.config.speed_multiplier);
+                self.toasts.push(format!("Speed: {}x", self.config.speed_multiplier));
+                return;
+            }
+            MenuAction::CycleFrameSkip => {
+                self.config.frame_skip = self.config.next_frame_skip();
+                self.skipped_frames = 0;
+                return;
+            }
+            MenuAction::CyclePalette => {
+                self.config.palette = self.config.palette.next();
+                return;
+            }
+            MenuAction::ToggleFrameBlending => {
+                self.config.frame_blending = !self.config.frame_blending;
+                return;
+            }
+            MenuAction::ToggleCollisionHighlight => {
+                self.config.highlight_collisions = !self.config.highlight_collisions;
+                self.collision_highlight.clear();
+                return;
+            }
+            MenuAction::ToggleShiftQuirk => {
+                self.toggle_quirk(|x| x.shift_uses_vy = !x.shift_uses_vy);
+                return;
+            }
+            MenuAction::ToggleLoadStoreQuirk => {
+                self.toggle_quirk(|x| x.load_store_keeps_i = !x.load_store_keeps_i);
+                return;
+            }
+            MenuAction::ToggleJumpQuirk => {
+                self.toggle_quirk(|x| x.jump_uses_vx = !x.jump_uses_vx);
+                return;
+            }
+            MenuAction::ToggleLogicQuirk => {
+                self.toggle_quirk(|x| x.logic_resets_vf = !x.logic_resets_vf);
+                return;
+            }
+            MenuAction::ToggleWrapQuirk => {
+                self.toggle_quirk(|x| x.sprites_wrap = !x.sprites_wrap);
+                return;
+            }
+            MenuAction::ToggleDisplayWaitQuirk => {
+                self.toggle_quirk(|x| x.display_wait = !x.display_wait);
+                return;
+            }
+            MenuAction::ToggleAwaitKeyReleaseQuirk => {
+                self.toggle_quirk(|x| x.await_key_release = !x.await_key_release);
+                return;
+            }
+            MenuAction::ToggleCheat(index) => {
+                if let Some(cheat) = self
+                    .config
+                    .get_rom_settings_mut()
+                    .and_then(|x| x.cheats.get_mut(index))
+                {
+                    cheat.enabled = !cheat.enabled;
+                    self.runner.set_cheat_enabled(index, cheat.enabled);
+                    self.toasts.push(format!(
+                        "Cheat {} {}",
+                        cheat.name,
+                        if cheat.enabled { "enabled" } else { "disabled" }
+                    ));
+                }
+
+                return;
+            }
+            MenuAction::SearchMemory(filter) => {
+                let memory = self.runner.get_memory();
+                self.pause_menu.search_memory(filter, &memory);
+                return;
+            }
+            MenuAction::Quit => {
+                self.save_session();
+                event::quit(ctx);
+                return;
+            }
+        };
+
+        if let Err(msg) = result {
+            error!("ERROR resetting VM: {}", msg);
+            self.toasts.push(format!("ERROR: {}", msg));
+        }
+
+        self.pause_menu.close();
+        self.frame_advance = false;
+        self.runner.resume();
+    }
+
+    fn toggle_quirk(&mut self, toggle: impl Fn(&mut Quirks)) {
+        let mut quirks = self.runner.get_quirks();
+        toggle(&mut quirks);
+
+        self.runner.set_quirks(quirks);
+        self.pause_menu.set_quirks(quirks);
+        self.toasts.push(format!("Quirks: {}", quirks));
+    }
+
+    // None is the quick save state which is not in a numbered slot
+    fn state_path(&self, slot: Option<usize>) -> PathBuf {
+        match slot {
+            Some(slot) => self.config.get_rom_data_path(&format!("{}.state", slot)),
+            None => self.config.get_rom_data_path("state"),
+        }
+    }
+
+    fn state_name(slot: Option<usize>) -> String {
+        match slot {
+            Some(slot) => format!("State {}", slot),
+            None => String::from("State"),
+        }
+    }
+
+    fn save_state(&mut self, slot: Option<usize>) {
+        if self.refuse_during_movie("Save states are") {
+            return;
+        }
+
+        match self.runner.save_state(&self.state_path(slot)) {
+            Ok(..) => self.toasts.push(format!("{} saved", Emulator::state_name(slot))),
+            Err(msg) => {
+                error!("ERROR saving state: {}", msg);
+                self.toasts.push(format!("ERROR: {}", msg));
+            }
+        }
+    }
+
+    fn load_state(&mut self, slot: Option<usize>) {
+        if self.refuse_during_movie("Save states are") {
+            return;
+        }
+
+        match self.runner.load_state(&self.state_path(slot)) {
+            Ok(..) => {
+                self.redraw = true;
+                self.toasts.push(format!("{} loaded", Emulator::state_name(slot)));
+            }
+            Err(msg) => {
+                error!("ERROR loading state: {}", msg);
+                self.toasts.push(format!("ERROR: {}", msg));
+            }
+        }
+    }
+
+    fn session_path(&self) -> PathBuf {
+        self.config.get_rom_data_path("session.state")
+    }
+
+    // demos and movies start from the reset
+    fn uses_session(&self) -> bool {
+        self.config.resume_session && self.movie.is_none() && self.attract_mode.is_none()
+    }
+
+    fn save_session(&mut self) {
+        if self.uses_session() == false {
+            return;
+        }
+
+        let path = self.session_path();
+
+        // a finished program starts from the reset the next time
+        if self.runner.has_exited() || self.runner.has_halted() {
+            let _ = fs::remove_file(&path);
+        } else if let Err(msg) = self.runner.save_state(&path) {
+            error!("ERROR saving session: {}", msg);
+        }
+    }
+
+    fn resume_session(&mut self) {
+        let path = self.session_path();
+        if self.uses_session() == false || path.exists() == false {
+            return;
+        }
+
+        match self.runner.load_state(&path) {
+            Ok(..) => self.toasts.push(String::from("Session resumed")),
+            Err(msg) => {
+                error!("ERROR resuming session: {}", msg);
+                self.toasts.push(format!("ERROR: {}", msg));
+            }
+        }
+    }
+
+    // the first press pauses, the VM only runs one frame or with step a single instruction per press from then on
+    fn handle_frame_advance(&mut self, resume: bool, step: bool) {
+        if resume {
+            if self.frame_advance {
+                self.frame_advance = false;
+                self.runner.resume();
+                self.toasts.push("Frame advance stopped");
+            }
+        } else if self.frame_advance && step {
+            self.runner.step(1);
+        } else if self.frame_advance {
+            self.runner.advance_frame();
+        } else if self.refuse_during_movie("Frame advance is") == false {
+            self.frame_advance = true;
+            self.runner.pause();
+            self.toasts.push("Frame advance, each press runs a single frame");
+        }
+    }
+
+    // hands the frame count and the recorded input of the VM to the movie, also before the runner is replaced
+    fn update_movie(&mut self) {
+        if let Some(movie) = &mut self.movie {
+            movie.update(self.runner.get_frame_count(), self.runner.take_movie_input());
+        }
+    }
+
+    // save states and rewinding would break the input of movies, which starts at the reset
+    fn refuse_during_movie(&mut self, feature: &str) -> bool {
+        let active = self.movie.as_ref().map(|x| x.is_active()).unwrap_or(false);
+        if active {
+            self.toasts.push(format!("{} not available while a movie is recorded or played", feature));
+        }
+
+        active
+    }
+
+    // the screen only has to be drawn if the VM screen, an overlay or a toast changed
+    fn screen_changed(&self, snapshot: &Snapshot) -> bool {
+        match &self.previous_snapshot {
+            Some(previous) => previous.get_generation() != snapshot.get_generation(),
+            None => true,
+        }
+    }
+
+    fn needs_redraw(&self, snapshot: &Snapshot) -> bool {
+        self.screen_changed(snapshot)
+            || self.redraw
+            || self.breakpoint_panel.is_visible()
+            || self.pause_menu.is_visible()
+            || self.telemetry_panel.is_visible()
+            || self.debugger_panel.is_visible()
+            || self.heatmap_panel.is_visible()
+            || self.speedrun_timer.is_visible()
+            || self.collision_highlight.is_active()
+            || self.toasts.is_active()
+            || self.runner.has_exited()
+            || self.runner.has_halted()
+    }
+
+    // only the overlays can change while paused or while the screen is static
+    fn is_idle(&self) -> bool {
+        self.config.power_saving
+            && (self.runner.is_paused() || self.last_change.elapsed() > IDLE_AFTER)
+            && self.redraw == false
+            && self.toasts.is_active() == false
+            && self.runner.is_playing_sound() == false
+            && self.rewinding.is_none()
+    }
+
+    fn update_frame(&mut self, _ctx: &mut Context) {
         let pressed_keys = keyboard::pressed_keys(&_ctx);
 
         if self.config.debugger.enable {
@@ -76,72 +455,499 @@ impl EventHandler for Emulator {
             }
         }
 
-        {
-            let mut input = self.input.lock().unwrap();
-            input.update_keys(pressed_keys);
+        self.input.lock().unwrap().update();
+
+        // one frame per update, the gameplay runs backwards at the normal speed
+        if self.rewinding.is_some() {
+            self.runner.rewind();
         }
 
-        if self.runner.is_playing_sound() && self.beep.playing() == false {
+        self.update_movie();
+        if let Some(movie) = &mut self.movie {
+            if let Err(msg) = movie.add_checksums(self.runner.take_checksums()) {
+                warn!("{}", msg);
+                self.toasts.push(format!("{}", msg));
+            }
+        }
+
+        if let Some(sprite_draws) = &mut self.sprite_draws {
+            let draws = self.runner.take_sprite_draws();
+
+            if draws != *sprite_draws {
+                *sprite_draws = draws;
+                self.redraw = true;
+            }
+        }
+
+        if let Some(rom) = self.attract_mode.as_mut().and_then(|x| x.update()) {
+            if let Err(msg) = self.load_rom(_ctx, rom) {
+                error!("ERROR loading the next demo ROM: {}", msg);
+            }
+        }
+
+        if let Some(err) = self.runner.take_error() {
+            self.runner.pause();
+            self.pause_menu.open();
+            self.toasts.push(format!("ERROR: {}", err));
+        }
+
+        // taken every frame so the statistics always cover a single frame
+        let draw_stats = self.runner.take_draw_stats();
+        self.telemetry_panel.update(draw_stats);
+
+        if self.heatmap_panel.is_visible() {
+            let runner = &self.runner;
+            self.heatmap_panel.update(|| runner.get_heatmap());
+        }
+
+        if self.config.highlight_collisions {
+            self.collision_highlight.update(&self.runner.take_collided_pixels());
+        }
+
+        let play_sound =
+            self.runner.is_playing_sound() && self.runner.is_paused() == false && self.config.mute == false;
+
+        if play_sound && self.beep.playing() == false {
             if let Err(msg) = self.beep.play() {
                 error!("ERROR playing sound: {}", msg);
             }
-        } else if self.beep.playing() && self.runner.is_playing_sound() == false {
+        } else if self.beep.playing() && play_sound == false {
             self.beep.stop();
         }
+    }
+}
+
+impl EventHandler for Emulator {
+    // closing the window quits without the pause menu
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.update_movie();
+        self.save_session();
+        false
+    }
+
+    // runs at a fixed rate independent of the VM speed and the render loop
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        while timer::check_update_time(ctx, UPDATES_PER_SECOND) {
+            self.update_frame(ctx);
+        }
 
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let snapshot = self.runner.get_display_snapshot();
+        if self.screen_changed(&snapshot) || self.redraw {
+            self.last_change = Instant::now();
+        }
+
+        if self.is_idle() {
+            // the sleep makes the loop, and the redraws of the overlays, run at a few fps
+            timer::sleep(IDLE_FRAME_TIME);
+            if self.needs_redraw(&snapshot) == false {
+                return Ok(());
+            }
+        } else if self.needs_redraw(&snapshot) == false {
+            timer::sleep(timer::remaining_update_time(ctx));
+            return Ok(());
+        }
+
+        // requested redraws are never skipped so the menus stay responsive
+        if self.redraw == false && self.skipped_frames < self.config.frame_skip {
+            self.skipped_frames += 1;
+            timer::sleep(timer::remaining_update_time(ctx));
+            return Ok(());
+        }
+
+        self.skipped_frames = 0;
+
+        // a blended frame has to be drawn once more without the previous screen once the screen settles
+        self.redraw = self.config.frame_blending
+            && self.previous_snapshot.as_ref().map(|x| x.get_generation()) != Some(snapshot.get_generation());
+
         let mut has_items = false;
         let mut builder = graphics::MeshBuilder::new();
-        let snapshot = self.runner.get_display_snapshot();
+        let colors = palette::rom_colors(&self.config, self.config.palette);
+        let blended = palette::blend(colors.background, colors.plane_1);
 
-        for y in 0..SCREEN_SIZE_Y {
-            for x in 0..SCREEN_SIZE_X {
-                let mut curr_pixel = 0;
-                let pixel_byte = snapshot.get_pixel(x, y);
-
-                for n in 0..8 {
-                    let mask = 1 << n;
-                    let is_set = pixel_byte & mask > 0;
-
-                    if is_set {
-                        builder.rectangle(
-                            graphics::DrawMode::fill(),
-                            graphics::Rect::new(
-                                (x + curr_pixel) as f32 * self.screen_scaling,
-                                y as f32 * self.screen_scaling,
-                                self.screen_scaling,
-                                self.screen_scaling,
-                            ),
-                            graphics::WHITE,
-                        );
-
-                        has_items = true;
-                    }
+        // the window and the magnifier are laid out in low-res pixels, hi-res pixels are half their size
+        let resolution = snapshot.width() / SCREEN_SIZE_X;
+        let (last_x, last_y) = snapshot.get_last_draw();
+        let viewport = self.magnifier.get_viewport((last_x / resolution, last_y / resolution));
+        let unit_size = self.screen_scaling * viewport.zoom as f32;
+        let pixel_size = unit_size / resolution as f32;
+        let (origin_x, origin_y) = (viewport.x * resolution, viewport.y * resolution);
 
-                    curr_pixel += 1;
-                }
+        let previous = match (&self.previous_snapshot, self.config.frame_blending) {
+            (Some(previous), true) if previous.width() == snapshot.width() => previous,
+            _ => &snapshot,
+        };
+
+        for ((x, y, is_set), (_, _, was_set)) in snapshot.iter_pixels().zip(previous.iter_pixels()) {
+            if viewport.contains(x / resolution, y / resolution) == false {
+                continue;
+            }
+
+            let is_collided = self.config.highlight_collisions && self.collision_highlight.is_highlighted(x, y);
+
+            if is_set || was_set || is_collided {
+                builder.rectangle(
+                    graphics::DrawMode::fill(),
+                    graphics::Rect::new(
+                        (x - origin_x) as f32 * pixel_size,
+                        (y - origin_y) as f32 * pixel_size,
+                        pixel_size,
+                        pixel_size,
+                    ),
+                    match (is_collided, is_set && was_set) {
+                        (true, _) => colors.collision,
+                        (false, true) => colors.plane_1,
+                        (false, false) => blended,
+                    },
+                );
+
+                has_items = true;
+            }
+        }
+
+        if let Some((x, y)) = self.magnifier.get_cursor() {
+            builder.rectangle(
+                graphics::DrawMode::stroke(2.),
+                graphics::Rect::new(
+                    (x - viewport.x) as f32 * unit_size,
+                    (y - viewport.y) as f32 * unit_size,
+                    unit_size,
+                    unit_size,
+                ),
+                colors.plane_2,
+            );
+
+            has_items = true;
+        }
+
+        if let Some(sprite_draws) = &self.sprite_draws {
+            // faint outlines add up where sprites are drawn repeatedly
+            let mut outline = colors.plane_2;
+            outline.a = 0.35;
+
+            for draw in sprite_draws {
+                builder.rectangle(
+                    graphics::DrawMode::stroke(1.),
+                    graphics::Rect::new(
+                        (draw.x as f32 - origin_x as f32) * pixel_size,
+                        (draw.y as f32 - origin_y as f32) * pixel_size,
+                        draw.width as f32 * pixel_size,
+                        draw.height as f32 * pixel_size,
+                    ),
+                    outline,
+                );
+
+                has_items = true;
             }
         }
 
-        graphics::clear(ctx, graphics::BLACK);
+        graphics::clear(ctx, colors.background);
 
         if has_items {
             let result = builder.build(ctx)?;
             graphics::draw(ctx, &result, graphics::DrawParam::new())?;
         }
 
+        if self.breakpoint_panel.is_visible() {
+            self.breakpoint_panel
+                .draw(ctx, &self.runner.get_breakpoints(), &self.runner.get_watchpoints())?;
+        }
+
+        if self.telemetry_panel.is_visible() {
+            self.telemetry_panel.draw(ctx, &self.runner.get_telemetry())?;
+        }
+
+        if self.debugger_panel.is_visible() && self.breakpoint_panel.is_visible() == false {
+            self.debugger_panel
+                .draw(ctx, &self.runner.get_debug_view(), self.runner.is_debugger_halted())?;
+        }
+
+        if self.heatmap_panel.is_visible() {
+            self.heatmap_panel.draw(ctx)?;
+        }
+
+        if self.speedrun_timer.is_visible() {
+            self.speedrun_timer.draw(ctx, self.runner.get_frame_count())?;
+        }
+
+        if self.runner.is_paused() && self.pause_menu.is_visible() == false && self.rewinding.is_none() {
+            draw_label(ctx, if self.frame_advance { "Frame advance" } else { "Paused" })?;
+        }
+
+        let status = match (self.runner.has_exited(), self.runner.has_halted()) {
+            (true, _) => Some("Program finished"),
+            (_, true) => Some("Program halted, it jumps to itself"),
+            _ => None,
+        };
+
+        if let Some(status) = status.filter(|_| self.pause_menu.is_visible() == false) {
+            draw_text_overlay(
+                ctx,
+                format!(
+                    "{}\n\nPress {:?} to restart or {:?} to load another ROM",
+                    status, self.config.general_key_mapping.restart_vm, self.config.general_key_mapping.pause_menu
+                ),
+            )?;
+        }
+
+        if self.pause_menu.is_visible() {
+            self.pause_menu.draw(ctx, &self.config)?;
+        }
+
+        if let Some(attract_mode) = &self.attract_mode {
+            attract_mode.draw(ctx)?;
+        }
+
+        self.toasts.draw(ctx)?;
+        self.previous_snapshot = Some(snapshot);
+
         graphics::present(ctx)
     }
 
+    // key up events are lost while the window is unfocused
+    fn focus_event(&mut self, _ctx: &mut Context, gained: bool) {
+        self.redraw = true;
+
+        if gained == false {
+            self.input.lock().unwrap().release_all();
+        }
+    }
+
+    // overridden so escape opens the pause menu instead of quitting
+    fn key_down_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, _keymods: KeyMods, _repeat: bool) {
+        self.redraw = true;
+
+        // the key which ends the demo is not passed on
+        if self.attract_mode.take().is_some() {
+            self.ignore_key_up = Some(_keycode);
+            self.toasts.push("Demo stopped");
+            return;
+        }
+
+        if (_keymods & KeyMods::SHIFT) == KeyMods::SHIFT {
+            match _keycode {
+                KeyCode::Left => self.magnifier.move_cursor(-1, 0),
+                KeyCode::Right => self.magnifier.move_cursor(1, 0),
+                KeyCode::Up => self.magnifier.move_cursor(0, -1),
+                KeyCode::Down => self.magnifier.move_cursor(0, 1),
+                _ => (),
+            }
+        }
+
+        // the slot keys are usually keypad keys, with a modifier they only select the slot
+        let slot = self.config.general_key_mapping.state_slots.iter().position(|x| *x == _keycode);
+        if let Some(slot) = slot.filter(|_| _repeat == false) {
+            if (_keymods & KeyMods::SHIFT) == KeyMods::SHIFT {
+                self.save_state(Some(slot + 1));
+                return;
+            }
+            if (_keymods & KeyMods::CTRL) == KeyMods::CTRL {
+                self.load_state(Some(slot + 1));
+                return;
+            }
+        }
+
+        if _keycode == self.config.general_key_mapping.frame_advance && self.captures_input() {
+            self.handle_frame_advance(
+                (_keymods & KeyMods::SHIFT) == KeyMods::SHIFT,
+                (_keymods & KeyMods::CTRL) == KeyMods::CTRL,
+            );
+            return;
+        }
+
+        // also available while a movie is played back
+        if _keycode == self.config.general_key_mapping.fast_forward {
+            if _repeat == false {
+                self.runner.set_fast_forward(Some(self.config.fast_forward_multiplier));
+            }
+
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.rewind && self.config.rewind_seconds > 0 && self.captures_input() {
+            if _repeat == false && self.refuse_during_movie("Rewinding is") == false {
+                self.rewinding = Some(self.runner.is_paused());
+                self.runner.pause();
+                self.input.lock().unwrap().release_all();
+            }
+
+            return;
+        }
+
+        if self.captures_input() {
+            let mut input = self.input.lock().unwrap();
+            input.key_down(&_keycode, _repeat);
+        }
+    }
+
     fn key_up_event(&mut self, _ctx: &mut Context, _keycode: KeyCode, _keymods: KeyMods) {
         let no_shift = (_keymods & KeyMods::SHIFT) != KeyMods::SHIFT;
+        self.redraw = true;
+
+        if self.ignore_key_up == Some(_keycode) {
+            self.ignore_key_up = None;
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.fast_forward {
+            self.runner.set_fast_forward(None);
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.rewind {
+            if let Some(was_paused) = self.rewinding.take() {
+                if was_paused == false && self.pause_menu.is_visible() == false {
+                    self.runner.resume();
+                }
+
+                return;
+            }
+        }
+
+        {
+            let mut input = self.input.lock().unwrap();
+            input.key_up(&_keycode);
+        }
+
+        if self.pause_menu.is_visible() {
+            if let Some(action) = self.pause_menu.handle_key(_keycode, &self.config) {
+                self.handle_menu_action(_ctx, action);
+            }
+
+            return;
+        }
+
+        // the letters of a typed entry must not trigger the other key bindings
+        if self.breakpoint_panel.is_typing() {
+            self.breakpoint_panel.handle_key(_keycode, &mut self.runner);
+            return;
+        }
+
+        // the timers stop with the VM, resuming also leaves frame advance
+        if _keycode == self.config.general_key_mapping.pause {
+            if self.runner.is_paused() {
+                self.frame_advance = false;
+                self.runner.resume();
+            } else {
+                self.runner.pause();
+            }
+            self.input.lock().unwrap().release_all();
+
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.pause_menu {
+            self.runner.pause();
+            self.pause_menu.open();
+            self.input.lock().unwrap().release_all();
+
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.cheats {
+            self.runner.pause();
+            self.pause_menu.open_cheats();
+            self.input.lock().unwrap().release_all();
+
+            return;
+        }
+
+        if _keycode == self.config.general_key_mapping.magnifier {
+            self.magnifier.cycle_mode();
+        }
+
+        if _keycode == self.config.general_key_mapping.export_sprite {
+            let snapshot = self.runner.get_display_snapshot();
+            // the cursor is in low-res pixels
+            let resolution = snapshot.width() / SCREEN_SIZE_X;
+            let (x, y) = match self.magnifier.get_cursor() {
+                Some((x, y)) => (x * resolution, y * resolution),
+                None => snapshot.get_last_draw(),
+            };
+
+            match sprite_export::export_sprite(&self.config, &snapshot, x, y) {
+                Ok(path) => {
+                    info!("Exported sprite at ({}, {}) to {}", x, y, path.display());
+                    self.toasts.push(format!("Sprite exported to {}", path.display()));
+                }
+                Err(msg) => {
+                    error!("ERROR exporting sprite: {}", msg);
+                    self.toasts.push(format!("ERROR: {}", msg));
+                }
+            }
+        }
+
+        if _keycode == self.config.general_key_mapping.sprite_outlines {
+            self.sprite_draws = match self.sprite_draws {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            };
+        }
+
+        if _keycode == self.config.general_key_mapping.telemetry {
+            self.telemetry_panel.toggle();
+        }
+
+        if _keycode == self.config.general_key_mapping.speedrun_timer {
+            self.speedrun_timer.toggle();
+        }
+
+        if _keycode == self.config.general_key_mapping.split {
+            let split = self.speedrun_timer.split(self.runner.get_frame_count());
+            self.toasts.push(format!("Split at frame {}", split.frames));
+        }
+
+        if _keycode == self.config.general_key_mapping.trace {
+            if self.runner.is_tracing() {
+                self.runner.stop_trace();
+                self.toasts.push(String::from("Instruction trace stopped"));
+            } else {
+                let path = self.config.get_rom_data_path("trace");
+                match self.runner.start_trace(&path) {
+                    Ok(..) => self.toasts.push(format!("Writing the instruction trace to {}", path.display())),
+                    Err(msg) => {
+                        error!("ERROR starting the instruction trace: {}", msg);
+                        self.toasts.push(format!("ERROR: {}", msg));
+                    }
+                }
+            }
+        }
+
+        if _keycode == self.config.general_key_mapping.heatmap {
+            if no_shift {
+                self.runner.enable_heatmap();
+                self.heatmap_panel.toggle();
+            } else {
+                let path = self.config.get_rom_data_path("heatmap.csv");
+                match self.runner.get_heatmap() {
+                    Some(heatmap) => match heatmap.export(&path) {
+                        Ok(..) => self.toasts.push(format!("Exported the memory heatmap to {}", path.display())),
+                        Err(msg) => {
+                            error!("ERROR exporting the memory heatmap: {}", msg);
+                            self.toasts.push(format!("ERROR: {}", msg));
+                        }
+                    },
+                    None => self.toasts.push(String::from("No memory accesses recorded, open the heatmap first")),
+                }
+            }
+        }
+
+        if _keycode == self.config.general_key_mapping.save_state {
+            self.save_state(None);
+        }
+
+        if _keycode == self.config.general_key_mapping.load_state {
+            self.load_state(None);
+        }
 
         if _keycode == self.config.general_key_mapping.restart_vm {
             if let Err(msg) = self.reset(_ctx) {
                 error!("ERROR resetting VM: {}", msg);
+                self.toasts.push(format!("ERROR: {}", msg));
             }
         }
 
@@ -150,17 +956,28 @@ impl EventHandler for Emulator {
                 self.runner.toggle_debugger_break()
             }
 
+            // shift steps continuously, ctrl jumps to the first and the last recorded frame
+            let ctrl = (_keymods & KeyMods::CTRL) == KeyMods::CTRL;
+
             if _keycode == self.config.debugger.key_mapping.step_previous && no_shift {
-                self.runner.send_debugger_command(DebuggerCommand::Previous)
+                self.runner.send_debugger_command(match ctrl {
+                    true => DebuggerCommand::GotoFirstFrame,
+                    false => DebuggerCommand::Previous,
+                })
             }
 
             if _keycode == self.config.debugger.key_mapping.step_next && no_shift {
-                self.runner.send_debugger_command(DebuggerCommand::Next)
+                self.runner.send_debugger_command(match ctrl {
+                    true => DebuggerCommand::GotoLastFrame,
+                    false => DebuggerCommand::Next,
+                })
             }
 
-            if _keycode == self.config.debugger.key_mapping.print_registers && no_shift {
-                self.runner
-                    .send_debugger_command(DebuggerCommand::PrintRegisters)
+            if _keycode == self.config.debugger.key_mapping.print_registers {
+                self.runner.send_debugger_command(match no_shift {
+                    true => DebuggerCommand::PrintRegisters,
+                    false => DebuggerCommand::DiffPrevious,
+                })
             }
 
             if _keycode == self.config.debugger.key_mapping.print_stack && no_shift {
@@ -172,6 +989,62 @@ impl EventHandler for Emulator {
                 self.runner
                     .send_debugger_command(DebuggerCommand::PrintTimers)
             }
+
+            if _keycode == self.config.debugger.key_mapping.print_collisions && no_shift {
+                self.runner
+                    .send_debugger_command(DebuggerCommand::PrintCollisions)
+            }
+
+            if _keycode == self.config.debugger.key_mapping.debugger_panel && no_shift {
+                self.debugger_panel.toggle();
+            }
+
+            if _keycode == self.config.debugger.key_mapping.print_memory && no_shift {
+                self.runner
+                    .send_debugger_command(DebuggerCommand::PrintMemoryAroundI)
+            }
+
+            if _keycode == self.config.debugger.key_mapping.print_profile {
+                self.runner.send_debugger_command(match no_shift {
+                    true => DebuggerCommand::PrintProfile(REPORT_SIZE),
+                    false => DebuggerCommand::PrintCoverage,
+                })
+            }
+
+            if _keycode == self.config.debugger.key_mapping.continue_execution {
+                self.runner.send_debugger_command(match no_shift {
+                    true => DebuggerCommand::Continue,
+                    false => DebuggerCommand::ReverseContinue,
+                })
+            }
+
+            if _keycode == self.config.debugger.key_mapping.toggle_breakpoint && no_shift {
+                self.runner
+                    .send_debugger_command(DebuggerCommand::ToggleBreakpoint)
+            }
+
+            if _keycode == self.config.debugger.key_mapping.run_until_return && no_shift {
+                self.runner.send_debugger_command(DebuggerCommand::RunUntilReturn)
+            }
+
+            if _keycode == self.config.debugger.key_mapping.export_history && no_shift {
+                let path = self.config.get_rom_data_path("history");
+                self.runner.enter_debugger_break();
+                self.runner.send_debugger_command(DebuggerCommand::ExportHistory(path))
+            }
+
+            if _keycode == self.config.debugger.key_mapping.import_history && no_shift {
+                let path = self.config.get_rom_data_path("history");
+                self.runner.enter_debugger_break();
+                self.runner.send_debugger_command(DebuggerCommand::ImportHistory(path))
+            }
+
+            if _keycode == self.config.debugger.key_mapping.breakpoint_panel && no_shift {
+                self.breakpoint_panel.toggle();
+                self.input.lock().unwrap().release_all();
+            } else if self.breakpoint_panel.is_visible() {
+                self.breakpoint_panel.handle_key(_keycode, &mut self.runner)
+            }
         }
     }
 }
@@ -0,0 +1,153 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::vm::heatmap::MemoryHeatmap;
+
+const COLUMNS: usize = 64;
+// larger memories are grouped into this many cells, CHIP-8 memory gets a cell per byte
+const MAX_CELLS: usize = 4096;
+const CELL_SIZE: f32 = 4.;
+// copying the counts every frame is not needed to see where a ROM works
+const REFRESH_FRAMES: u32 = 10;
+
+// the summed counts of the addresses of a cell
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Cell {
+    reads: u64,
+    writes: u64,
+    executes: u64,
+}
+
+// shows the memory as a grid of cells colored by how often they were read (blue), written (red) and executed (green)
+pub struct HeatmapPanel {
+    visible: bool,
+    heatmap: Option<MemoryHeatmap>,
+    frames_until_refresh: u32,
+}
+
+impl HeatmapPanel {
+    pub fn new() -> HeatmapPanel {
+        HeatmapPanel {
+            visible: false,
+            heatmap: None,
+            frames_until_refresh: 0,
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.frames_until_refresh = 0;
+    }
+
+    // called once per frame while the panel is visible, read returns the current counts of the VM
+    pub fn update<F: FnOnce() -> Option<MemoryHeatmap>>(&mut self, read: F) {
+        if self.frames_until_refresh == 0 {
+            self.heatmap = read();
+            self.frames_until_refresh = REFRESH_FRAMES;
+        }
+
+        self.frames_until_refresh -= 1;
+    }
+
+    fn cells(heatmap: &MemoryHeatmap) -> Vec<Cell> {
+        let bytes_per_cell = ((heatmap.len() + MAX_CELLS - 1) / MAX_CELLS).max(1);
+
+        (0..heatmap.len())
+            .step_by(bytes_per_cell)
+            .map(|start| {
+                let end = (start + bytes_per_cell).min(heatmap.len());
+                Cell {
+                    reads: heatmap.reads[start..end].iter().sum(),
+                    writes: heatmap.writes[start..end].iter().sum(),
+                    executes: heatmap.executes[start..end].iter().sum(),
+                }
+            })
+            .collect()
+    }
+
+    // logarithmic, so rarely accessed cells are still visible next to a hot loop
+    fn intensity(count: u64, max: u64) -> f32 {
+        match count {
+            0 => 0.,
+            _ => (0.3 + 0.7 * (count as f32).ln_1p() / (max as f32).ln_1p()).min(1.),
+        }
+    }
+
+    fn color(cell: &Cell, max: &Cell) -> Color {
+        if *cell == Cell::default() {
+            return Color::new(0.15, 0.15, 0.15, 1.);
+        }
+
+        Color::new(
+            HeatmapPanel::intensity(cell.writes, max.writes),
+            HeatmapPanel::intensity(cell.executes, max.executes),
+            HeatmapPanel::intensity(cell.reads, max.reads),
+            1.,
+        )
+    }
+
+    pub fn draw(&self, ctx: &mut Context) -> GameResult<()> {
+        let cells = match &self.heatmap {
+            Some(heatmap) => HeatmapPanel::cells(heatmap),
+            None => Vec::new(),
+        };
+
+        let max = cells.iter().fold(Cell::default(), |max, x| Cell {
+            reads: max.reads.max(x.reads),
+            writes: max.writes.max(x.writes),
+            executes: max.executes.max(x.executes),
+        });
+
+        let rows = (cells.len() + COLUMNS - 1) / COLUMNS;
+        let legend = Text::new(match self.heatmap {
+            Some(..) => "Memory: read blue, write red, executed green",
+            None => "Memory: no accesses recorded yet",
+        });
+
+        let area = graphics::screen_coordinates(ctx);
+        let width = COLUMNS as f32 * CELL_SIZE;
+        let height = rows as f32 * CELL_SIZE + legend.height(ctx) as f32 + 4.;
+        let (x, y) = (area.w - width - 16., area.h - height - 16.);
+
+        let mut builder = MeshBuilder::new();
+        builder.rectangle(DrawMode::fill(), Rect::new(x - 6., y - 6., width + 12., height + 12.), Color::new(0., 0., 0., 0.8));
+
+        let top = y + legend.height(ctx) as f32 + 4.;
+        for (i, cell) in cells.iter().enumerate() {
+            let (column, row) = ((i % COLUMNS) as f32, (i / COLUMNS) as f32);
+            builder.rectangle(
+                DrawMode::fill(),
+                Rect::new(x + column * CELL_SIZE, top + row * CELL_SIZE, CELL_SIZE, CELL_SIZE),
+                HeatmapPanel::color(cell, &max),
+            );
+        }
+
+        let mesh = builder.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::new())?;
+        graphics::draw(ctx, &legend, DrawParam::new().dest([x, y]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cells_sum_the_addresses_they_cover() {
+        let mut heatmap = MemoryHeatmap::new(MAX_CELLS * 2);
+        heatmap.record_execute(0x200, 2);
+        heatmap.record_read(0x401, 1);
+
+        let cells = HeatmapPanel::cells(&heatmap);
+        assert_eq!(cells.len(), MAX_CELLS);
+        assert_eq!(cells[0x100], Cell { reads: 0, writes: 0, executes: 2 });
+        assert_eq!(cells[0x200], Cell { reads: 1, writes: 0, executes: 0 });
+
+        assert_eq!(HeatmapPanel::intensity(0, 10), 0.);
+        assert_eq!(HeatmapPanel::intensity(10, 10), 1.);
+    }
+}
@@ -1,10 +1,17 @@
+use super::macros::Macros;
 use crate::vm::{config::Config, input::Input};
 use ggez::input::keyboard::KeyCode;
-use std::collections::{HashMap, HashSet};
+use std::collections::HashMap;
 
 pub struct GGEZInput {
     pressed_keys: Vec<u8>,
+    // keys pressed since the last update, releasing them is deferred so taps shorter than a frame are not lost
+    pressed_edges: Vec<u8>,
+    pending_releases: Vec<u8>,
+    // presses not yet taken by the VM, presses which are not taken within a frame are dropped
+    unread_presses: Vec<u8>,
     mapping: HashMap<KeyCode, u8>,
+    macros: Macros,
 }
 
 impl GGEZInput {
@@ -23,19 +30,70 @@ impl GGEZInput {
 
         GGEZInput {
             pressed_keys: Vec::with_capacity(16),
+            pressed_edges: Vec::with_capacity(16),
+            pending_releases: Vec::with_capacity(16),
+            unread_presses: Vec::with_capacity(16),
             mapping: mapping,
+            macros: Macros::new(config.get_macros()),
         }
     }
 
-    pub fn update_keys(&mut self, keys: &HashSet<KeyCode>) {
-        self.pressed_keys.clear();
-        for code in keys.iter() {
-            if let Some(key) = self.map_keycode(code) {
+    // auto-repeat events of held keys are no new presses and must not create an edge
+    pub fn key_down(&mut self, code: &KeyCode, repeat: bool) {
+        if repeat {
+            return;
+        }
+
+        // macros take precedence over the key mapping
+        if self.macros.is_bound(code) {
+            let pressed = self.macros.start(code);
+            self.unread_presses.extend(pressed);
+
+            return;
+        }
+
+        if let Some(key) = self.map_keycode(code) {
+            if self.pressed_keys.contains(&key) == false {
                 self.pressed_keys.push(key);
             }
+
+            self.pressed_edges.push(key);
+            self.unread_presses.push(key);
+            self.pending_releases.retain(|x| *x != key);
+        }
+    }
+
+    pub fn key_up(&mut self, code: &KeyCode) {
+        if let Some(key) = self.map_keycode(code) {
+            if self.pressed_edges.contains(&key) {
+                self.pending_releases.push(key);
+            } else {
+                self.pressed_keys.retain(|x| *x != key);
+            }
         }
     }
 
+    // called once per frame, releases the keys which were tapped during the frame
+    pub fn update(&mut self) {
+        for key in self.pending_releases.drain(..) {
+            self.pressed_keys.retain(|x| *x != key);
+        }
+
+        self.pressed_edges.clear();
+        self.unread_presses.clear();
+
+        let pressed = self.macros.update();
+        self.unread_presses.extend(pressed);
+    }
+
+    pub fn release_all(&mut self) {
+        self.pressed_keys.clear();
+        self.pressed_edges.clear();
+        self.pending_releases.clear();
+        self.unread_presses.clear();
+        self.macros.stop();
+    }
+
     fn map_keycode(&self, code: &KeyCode) -> Option<u8> {
         match self.mapping.get(code) {
             Some(key) => Some(*key),
@@ -46,14 +104,107 @@ impl GGEZInput {
 
 impl Input for GGEZInput {
     fn is_pressed(&self, key: u8) -> bool {
-        self.pressed_keys.contains(&key)
+        self.pressed_keys.contains(&key) || self.macros.keys().contains(&key)
     }
 
-    fn get_pressed_key(&self) -> Option<u8> {
-        if self.pressed_keys.len() == 0 {
+    fn take_pressed_key(&mut self) -> Option<u8> {
+        if self.unread_presses.len() == 0 {
             None
         } else {
-            Some(self.pressed_keys[0])
+            Some(self.unread_presses.remove(0))
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::config::MacroStep;
+
+    fn new() -> GGEZInput {
+        GGEZInput::new(&Config::default())
+    }
+
+    #[test]
+    fn key_down_is_visible_immediately() {
+        let mut input = new();
+
+        input.key_down(&KeyCode::Key5, false);
+
+        assert!(input.is_pressed(5));
+
+        input.update();
+        input.key_up(&KeyCode::Key5);
+
+        assert!(!input.is_pressed(5));
+    }
+
+    #[test]
+    fn tap_within_frame_is_released_on_update() {
+        let mut input = new();
+
+        input.key_down(&KeyCode::Key5, false);
+        input.key_up(&KeyCode::Key5);
+
+        assert!(input.is_pressed(5));
+
+        input.update();
+
+        assert!(!input.is_pressed(5));
+    }
+
+    #[test]
+    fn take_pressed_key_reports_held_key_once() {
+        let mut input = new();
+
+        input.key_down(&KeyCode::Key5, false);
+        input.key_down(&KeyCode::Key7, false);
+
+        assert_eq!(input.take_pressed_key(), Some(5));
+        assert_eq!(input.take_pressed_key(), Some(7));
+        assert_eq!(input.take_pressed_key(), None);
+        assert!(input.is_pressed(5));
+    }
+
+    #[test]
+    fn untaken_presses_are_dropped_on_update() {
+        let mut input = new();
+
+        input.key_down(&KeyCode::Key5, false);
+        input.update();
+
+        assert_eq!(input.take_pressed_key(), None);
+    }
+
+    #[test]
+    fn macro_presses_keys_while_running() {
+        let mut config = Config::default();
+        config.macros.insert(
+            KeyCode::Space,
+            vec![MacroStep { keys: vec![5], frames: 1 }, MacroStep { keys: vec![7], frames: 1 }],
+        );
+
+        let mut input = GGEZInput::new(&config);
+
+        input.key_down(&KeyCode::Space, false);
+        assert!(input.is_pressed(5));
+        assert_eq!(input.take_pressed_key(), Some(5));
+
+        input.update();
+        assert!(!input.is_pressed(5));
+        assert!(input.is_pressed(7));
+
+        input.update();
+        assert!(!input.is_pressed(7));
+    }
+
+    #[test]
+    fn repeat_does_not_create_edge() {
+        let mut input = new();
+
+        input.key_down(&KeyCode::Key5, true);
+
+        assert!(!input.is_pressed(5));
+        assert_eq!(input.take_pressed_key(), None);
+    }
+}
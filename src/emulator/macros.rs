@@ -0,0 +1,141 @@
+use ggez::input::keyboard::KeyCode;
+use log::warn;
+
+use crate::vm::config::{MacroMapping, MacroStep};
+
+struct RunningMacro {
+    steps: Vec<MacroStep>,
+    step: usize,
+    frames_left: u32,
+}
+
+// plays back the key sequences bound to host keys, one step after another
+pub struct Macros {
+    bindings: MacroMapping,
+    running: Option<RunningMacro>,
+}
+
+impl Macros {
+    pub fn new(bindings: MacroMapping) -> Macros {
+        let mut bindings = bindings;
+
+        for (keycode, steps) in bindings.iter_mut() {
+            for step in steps.iter_mut() {
+                if step.keys.iter().any(|x| *x > 0xF) {
+                    warn!("Macro bound to {:?} contains keys above 0xF, ignoring them", keycode);
+                    step.keys.retain(|x| *x <= 0xF);
+                }
+            }
+        }
+
+        Macros {
+            bindings,
+            running: None,
+        }
+    }
+
+    pub fn is_bound(&self, code: &KeyCode) -> bool {
+        self.bindings.contains_key(code)
+    }
+
+    // restarts the macro if another one is running, returns the keys pressed by the first step
+    pub fn start(&mut self, code: &KeyCode) -> Vec<u8> {
+        self.running = match self.bindings.get(code) {
+            Some(steps) if steps.is_empty() == false => Some(RunningMacro {
+                steps: steps.clone(),
+                step: 0,
+                frames_left: steps[0].frames.max(1),
+            }),
+            _ => None,
+        };
+
+        self.keys().to_vec()
+    }
+
+    pub fn stop(&mut self) {
+        self.running = None;
+    }
+
+    // keys held by the current step
+    pub fn keys(&self) -> &[u8] {
+        match &self.running {
+            Some(running) => &running.steps[running.step].keys,
+            None => &[],
+        }
+    }
+
+    // called once per frame, returns the keys pressed by the step which starts
+    pub fn update(&mut self) -> Vec<u8> {
+        let mut finished = false;
+        let mut pressed = Vec::new();
+
+        if let Some(running) = &mut self.running {
+            running.frames_left -= 1;
+
+            if running.frames_left == 0 {
+                running.step += 1;
+
+                match running.steps.get(running.step) {
+                    Some(step) => {
+                        running.frames_left = step.frames.max(1);
+                        pressed = step.keys.clone();
+                    }
+                    None => finished = true,
+                }
+            }
+        }
+
+        if finished {
+            self.running = None;
+        }
+
+        pressed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(keys: &[u8], frames: u32) -> MacroStep {
+        MacroStep {
+            keys: keys.to_vec(),
+            frames,
+        }
+    }
+
+    fn new() -> Macros {
+        let mut bindings = MacroMapping::new();
+        bindings.insert(KeyCode::Space, vec![step(&[5], 2), step(&[], 1), step(&[7], 1)]);
+
+        Macros::new(bindings)
+    }
+
+    #[test]
+    fn steps_are_held_for_their_frames() {
+        let mut macros = new();
+
+        assert_eq!(macros.start(&KeyCode::Space), vec![5]);
+        assert_eq!(macros.update(), vec![]);
+        assert_eq!(macros.keys(), &[5]);
+
+        assert_eq!(macros.update(), vec![]);
+        assert!(macros.keys().is_empty());
+
+        assert_eq!(macros.update(), vec![7]);
+        assert_eq!(macros.keys(), &[7]);
+
+        macros.update();
+        assert!(macros.keys().is_empty());
+    }
+
+    #[test]
+    fn keys_above_f_are_ignored() {
+        let mut bindings = MacroMapping::new();
+        bindings.insert(KeyCode::Space, vec![step(&[5, 0x10], 1)]);
+
+        let mut macros = Macros::new(bindings);
+
+        assert_eq!(macros.start(&KeyCode::Space), vec![5]);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::vm::constants::{SCREEN_SIZE_X, SCREEN_SIZE_Y};
+
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Off,
+    Cursor,
+    FollowDraw,
+}
+
+// region of the CHIP-8 screen which is rendered to the window
+pub struct Viewport {
+    pub x: usize,
+    pub y: usize,
+    pub zoom: usize,
+}
+
+impl Viewport {
+    pub fn width(&self) -> usize {
+        SCREEN_SIZE_X / self.zoom
+    }
+
+    pub fn height(&self) -> usize {
+        SCREEN_SIZE_Y / self.zoom
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width()).contains(&x) && (self.y..self.y + self.height()).contains(&y)
+    }
+}
+
+pub struct Magnifier {
+    mode: Mode,
+    cursor: (usize, usize),
+}
+
+impl Magnifier {
+    const ZOOM: usize = 4;
+
+    pub fn new() -> Magnifier {
+        Magnifier {
+            mode: Mode::Off,
+            cursor: (SCREEN_SIZE_X / 2, SCREEN_SIZE_Y / 2),
+        }
+    }
+
+    // off -> follow cursor -> follow last sprite draw -> off
+    pub fn cycle_mode(&mut self) {
+        self.mode = match self.mode {
+            Mode::Off => Mode::Cursor,
+            Mode::Cursor => Mode::FollowDraw,
+            Mode::FollowDraw => Mode::Off,
+        };
+    }
+
+    pub fn get_cursor(&self) -> Option<(usize, usize)> {
+        match self.mode {
+            Mode::Cursor => Some(self.cursor),
+            _ => None,
+        }
+    }
+
+    pub fn move_cursor(&mut self, dx: isize, dy: isize) {
+        let (x, y) = self.cursor;
+
+        self.cursor = (
+            (x as isize + dx).max(0).min(SCREEN_SIZE_X as isize - 1) as usize,
+            (y as isize + dy).max(0).min(SCREEN_SIZE_Y as isize - 1) as usize,
+        );
+    }
+
+    pub fn get_viewport(&self, last_draw: (usize, usize)) -> Viewport {
+        let (x, y) = match self.mode {
+            Mode::Off => return Viewport { x: 0, y: 0, zoom: 1 },
+            Mode::Cursor => self.cursor,
+            // sprites are 8 pixels wide, center on the sprite instead of its origin
+            Mode::FollowDraw => (last_draw.0 + 4, last_draw.1 + 4),
+        };
+
+        let width = SCREEN_SIZE_X / Magnifier::ZOOM;
+        let height = SCREEN_SIZE_Y / Magnifier::ZOOM;
+
+        Viewport {
+            x: x.saturating_sub(width / 2).min(SCREEN_SIZE_X - width),
+            y: y.saturating_sub(height / 2).min(SCREEN_SIZE_Y - height),
+            zoom: Magnifier::ZOOM,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn viewport_off_shows_full_screen() {
+        let m = Magnifier::new();
+        let v = m.get_viewport((0, 0));
+
+        assert_eq!((v.x, v.y, v.zoom), (0, 0, 1));
+        assert_eq!((v.width(), v.height()), (SCREEN_SIZE_X, SCREEN_SIZE_Y));
+    }
+
+    #[test]
+    fn viewport_is_clamped_to_screen() {
+        let mut m = Magnifier::new();
+        m.cycle_mode();
+        m.move_cursor(SCREEN_SIZE_X as isize, SCREEN_SIZE_Y as isize);
+
+        let v = m.get_viewport((0, 0));
+
+        assert_eq!(m.get_cursor(), Some((SCREEN_SIZE_X - 1, SCREEN_SIZE_Y - 1)));
+        assert_eq!(v.x + v.width(), SCREEN_SIZE_X);
+        assert_eq!(v.y + v.height(), SCREEN_SIZE_Y);
+    }
+}
@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+use log::{info, warn};
+
+use crate::vm::config::Config;
+use crate::vm::movie::{Checksum, Movie, MovieHeader};
+use crate::vm::movie_input::MovieInput;
+use crate::vm::rom;
+
+// the VM records and plays back the input itself, the session loads and saves the movie and verifies the checksums
+pub enum MovieSession {
+    Recording { movie: Movie, path: PathBuf },
+    Playing { movie: Movie, diverged: bool },
+    Finished,
+}
+
+impl MovieSession {
+    // sets the seed of the VM, so the config has to be passed to the runner afterwards
+    pub fn from_config(config: &mut Config) -> Result<Option<MovieSession>> {
+        let rom = rom::load(config)?;
+
+        if let Some(path) = config.movie.play.clone() {
+            let movie = Movie::load(&PathBuf::from(&path))?;
+            movie.apply_seed(config);
+            movie.validate(&MovieHeader::new(config, &rom))?;
+
+            info!("Playing movie {} by {}: {}", path, movie.header.author, movie.header.comment);
+            return Ok(Some(MovieSession::Playing { movie, diverged: false }));
+        }
+
+        if let Some(path) = config.movie.record.clone() {
+            if config.seed.is_none() {
+                config.seed = Some(rand::random());
+            }
+
+            info!("Recording movie {}", path);
+            return Ok(Some(MovieSession::Recording {
+                movie: Movie::new(MovieHeader::new(config, &rom)),
+                path: PathBuf::from(path),
+            }));
+        }
+
+        Ok(None)
+    }
+
+    // the keyboard is ignored while a movie is played back
+    pub fn is_playing(&self) -> bool {
+        matches!(self, MovieSession::Playing { .. })
+    }
+
+    pub fn is_active(&self) -> bool {
+        matches!(self, MovieSession::Recording { .. } | MovieSession::Playing { .. })
+    }
+
+    // passed to the runner, which replaces the keyboard of the VM with it
+    pub fn input(&self) -> Option<MovieInput> {
+        match self {
+            MovieSession::Recording { .. } => Some(MovieInput::recording()),
+            MovieSession::Playing { movie, .. } => Some(MovieInput::playing(movie.frames.clone())),
+            MovieSession::Finished => None,
+        }
+    }
+
+    // called once per update with the frame count of the VM and the input it recorded since the last call
+    pub fn update(&mut self, frame_count: u64, recorded: Vec<u16>) {
+        match self {
+            MovieSession::Recording { movie, .. } => movie.frames.extend(recorded),
+            MovieSession::Playing { movie, .. } if frame_count >= movie.frames.len() as u64 => {
+                info!("Movie playback finished after {} frames", movie.frames.len());
+                *self = MovieSession::Finished;
+            }
+            _ => {}
+        }
+    }
+
+    // stores the checksums while recording and compares them while playing,
+    // only the first divergence is reported as everything after it differs too
+    pub fn add_checksums(&mut self, checksums: Vec<Checksum>) -> Result<()> {
+        match self {
+            MovieSession::Recording { movie, .. } => movie.checksums.extend(checksums),
+            MovieSession::Playing { movie, diverged, .. } if *diverged == false => {
+                for checksum in &checksums {
+                    if let Err(err) = movie.verify(checksum) {
+                        *diverged = true;
+                        return Err(err.into());
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for MovieSession {
+    fn drop(&mut self) {
+        if let MovieSession::Recording { movie, path } = self {
+            match movie.save(path) {
+                Ok(..) => info!("Saved movie with {} frames to {}", movie.frames.len(), path.display()),
+                Err(err) => warn!("Failed to save movie to {}: {}", path.display(), err),
+            }
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+pub fn draw_text_overlay(ctx: &mut Context, content: String) -> GameResult<()> {
+    let background = Mesh::new_rectangle(
+        ctx,
+        DrawMode::fill(),
+        graphics::screen_coordinates(ctx),
+        Color::new(0., 0., 0., 0.8),
+    )?;
+
+    graphics::draw(ctx, &background, DrawParam::new())?;
+    graphics::draw(ctx, &Text::new(content), DrawParam::new().dest([10., 10.]))
+}
+
+// a single line in the top right corner which leaves the screen visible
+pub fn draw_label(ctx: &mut Context, content: &str) -> GameResult<()> {
+    let text = Text::new(content);
+    let (width, height) = (text.width(ctx) as f32, text.height(ctx) as f32);
+    let x = graphics::screen_coordinates(ctx).w - width - 10.;
+
+    let background = Mesh::new_rectangle(
+        ctx,
+        DrawMode::fill(),
+        Rect::new(x - 4., 8., width + 8., height + 4.),
+        Color::new(0., 0., 0., 0.8),
+    )?;
+
+    graphics::draw(ctx, &background, DrawParam::new())?;
+    graphics::draw(ctx, &text, DrawParam::new().dest([x, 10.]))
+}
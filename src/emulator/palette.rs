@@ -0,0 +1,67 @@
+use ggez::graphics::Color;
+
+use crate::vm::config::{Config, Palette};
+
+pub struct Colors {
+    pub background: Color,
+    pub plane_1: Color,
+    pub plane_2: Color,
+    pub collision: Color,
+}
+
+// the colorblind palettes use the Okabe-Ito colors which stay distinguishable for red-green deficiencies
+pub fn colors(palette: Palette) -> Colors {
+    match palette {
+        Palette::Classic => Colors {
+            background: Color::from_rgb(0x00, 0x00, 0x00),
+            plane_1: Color::from_rgb(0xFF, 0xFF, 0xFF),
+            plane_2: Color::from_rgb(0xAA, 0xAA, 0xAA),
+            collision: Color::from_rgb(0xFF, 0x00, 0x00),
+        },
+        Palette::HighContrast => Colors {
+            background: Color::from_rgb(0x00, 0x00, 0x00),
+            plane_1: Color::from_rgb(0xFF, 0xFF, 0x00),
+            plane_2: Color::from_rgb(0x00, 0xFF, 0xFF),
+            collision: Color::from_rgb(0xFF, 0x00, 0xFF),
+        },
+        Palette::Deuteranopia => Colors {
+            background: Color::from_rgb(0x00, 0x00, 0x00),
+            plane_1: Color::from_rgb(0xE6, 0x9F, 0x00),
+            plane_2: Color::from_rgb(0x56, 0xB4, 0xE9),
+            collision: Color::from_rgb(0xD5, 0x5E, 0x00),
+        },
+        Palette::Protanopia => Colors {
+            background: Color::from_rgb(0x00, 0x00, 0x00),
+            plane_1: Color::from_rgb(0xF0, 0xE4, 0x42),
+            plane_2: Color::from_rgb(0x56, 0xB4, 0xE9),
+            collision: Color::from_rgb(0xCC, 0x79, 0xA7),
+        },
+    }
+}
+
+// the colors of the configured palette with the colors of the current ROM on top
+pub fn rom_colors(config: &Config, palette: Palette) -> Colors {
+    let mut colors = colors(palette);
+
+    if let Some(rom_colors) = config.get_rom_settings().and_then(|x| x.colors.as_ref()) {
+        colors.background = parse_color(&rom_colors.background).unwrap_or(colors.background);
+        colors.plane_1 = parse_color(&rom_colors.foreground).unwrap_or(colors.plane_1);
+    }
+
+    colors
+}
+
+// #RRGGBB
+fn parse_color(hex: &str) -> Option<Color> {
+    let value = u32::from_str_radix(hex.trim_start_matches('#'), 16).ok()?;
+
+    match hex.trim_start_matches('#').len() {
+        6 => Some(Color::from_rgb((value >> 16) as u8, (value >> 8) as u8, value as u8)),
+        _ => None,
+    }
+}
+
+// mixes both colors 50/50
+pub fn blend(a: Color, b: Color) -> Color {
+    Color::new((a.r + b.r) / 2., (a.g + b.g) / 2., (a.b + b.b) / 2., (a.a + b.a) / 2.)
+}
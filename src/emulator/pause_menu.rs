@@ -0,0 +1,338 @@
+use std::fs;
+use std::path::Path;
+
+use ggez::input::keyboard::KeyCode;
+use ggez::{Context, GameResult};
+use log::warn;
+
+use super::overlay::draw_text_overlay;
+use super::thumbnails::Thumbnails;
+use crate::vm::cheat_search::{CheatSearch, SearchFilter};
+use crate::vm::config::Config;
+use crate::vm::quirks::Quirks;
+
+const MAX_SEARCH_CANDIDATES: usize = 12;
+
+#[derive(Clone)]
+pub enum MenuAction {
+    Resume,
+    Reset,
+    LoadRom(String),
+    ToggleMute,
+    CyclePalette,
+    ToggleFrameBlending,
+    ToggleCollisionHighlight,
+    ToggleShiftQuirk,
+    ToggleLoadStoreQuirk,
+    ToggleJumpQuirk,
+    ToggleLogicQuirk,
+    ToggleWrapQuirk,
+    ToggleDisplayWaitQuirk,
+    ToggleAwaitKeyReleaseQuirk,
+    CycleSpeed,
+    CycleFrameSkip,
+    ToggleCheat(usize),
+    // None starts a new search
+    SearchMemory(Option<SearchFilter>),
+    Quit,
+}
+
+#[derive(Clone, PartialEq)]
+enum Page {
+    Main,
+    Roms,
+    Settings,
+    Cheats,
+    CheatSearch,
+}
+
+#[derive(Clone)]
+enum Entry {
+    Action(MenuAction),
+    Open(Page),
+    Back,
+    Info,
+}
+
+pub struct PauseMenu {
+    visible: bool,
+    page: Page,
+    selected: usize,
+    roms: Vec<String>,
+    thumbnails: Thumbnails,
+    cheat_search: CheatSearch,
+    search_value: u8,
+    // quirks of the running VM, they are not part of the config
+    quirks: Quirks,
+}
+
+impl PauseMenu {
+    pub fn new() -> PauseMenu {
+        PauseMenu {
+            visible: false,
+            page: Page::Main,
+            selected: 0,
+            roms: Vec::new(),
+            thumbnails: Thumbnails::new(),
+            cheat_search: CheatSearch::new(),
+            search_value: 0,
+            quirks: Quirks::default(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn open(&mut self) {
+        self.visible = true;
+        self.page = Page::Main;
+        self.selected = 0;
+    }
+
+    pub fn open_cheats(&mut self) {
+        self.visible = true;
+        self.page = Page::Cheats;
+        self.selected = 0;
+    }
+
+    pub fn close(&mut self) {
+        self.visible = false;
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+    }
+
+    pub fn handle_key(&mut self, keycode: KeyCode, config: &Config) -> Option<MenuAction> {
+        let entries = self.entries(config);
+
+        match keycode {
+            KeyCode::Up => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down => self.selected = (self.selected + 1).min(entries.len() - 1),
+            KeyCode::Return | KeyCode::Space => match entries[self.selected].1.clone() {
+                Entry::Action(action) => return Some(action),
+                Entry::Open(page) => self.open_page(page, config),
+                Entry::Back => self.open_page(Page::Main, config),
+                Entry::Info => (),
+            },
+            KeyCode::Left if self.page == Page::CheatSearch => {
+                self.search_value = self.search_value.wrapping_sub(1)
+            }
+            KeyCode::Right if self.page == Page::CheatSearch => {
+                self.search_value = self.search_value.wrapping_add(1)
+            }
+            KeyCode::Escape | KeyCode::Back => {
+                if self.page == Page::Main {
+                    return Some(MenuAction::Resume);
+                }
+
+                self.open_page(Page::Main, config)
+            }
+            _ => (),
+        }
+
+        None
+    }
+
+    pub fn draw(&self, ctx: &mut Context, config: &Config) -> GameResult<()> {
+        let mut content = String::from(match self.page {
+            Page::Main => "Paused",
+            Page::Roms => "Load ROM",
+            Page::Settings => "Settings",
+            Page::Cheats => "Cheats",
+            Page::CheatSearch => "Cheat search",
+        });
+        content.push_str("\n\n");
+
+        for (i, (label, _)) in self.entries(config).iter().enumerate() {
+            content.push_str(&format!(
+                "{} {}\n",
+                if i == self.selected { ">" } else { " " },
+                label
+            ));
+        }
+
+        draw_text_overlay(ctx, content)?;
+
+        match (&self.page, self.roms.get(self.selected)) {
+            (Page::Roms, Some(rom)) => self.thumbnails.draw(ctx, config, rom),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn search_memory(&mut self, filter: Option<SearchFilter>, memory: &[u8]) {
+        if memory.len() == 0 {
+            return;
+        }
+
+        match filter {
+            Some(filter) => self.cheat_search.filter(memory, filter),
+            None => self.cheat_search.start(memory),
+        }
+    }
+
+    fn open_page(&mut self, page: Page, config: &Config) {
+        if page == Page::Roms {
+            self.roms = PauseMenu::list_roms(config);
+            self.thumbnails.request(config, &self.roms);
+        }
+
+        self.page = page;
+        self.selected = 0;
+    }
+
+    fn entries(&self, config: &Config) -> Vec<(String, Entry)> {
+        let mut entries = match self.page {
+            Page::Main => vec![
+                (String::from("Resume"), Entry::Action(MenuAction::Resume)),
+                (String::from("Reset"), Entry::Action(MenuAction::Reset)),
+                (String::from("Load ROM"), Entry::Open(Page::Roms)),
+                (String::from("Settings"), Entry::Open(Page::Settings)),
+                (String::from("Cheats"), Entry::Open(Page::Cheats)),
+                (String::from("Quit"), Entry::Action(MenuAction::Quit)),
+            ],
+            Page::Roms => self
+                .roms
+                .iter()
+                .map(|rom| {
+                    let name = Path::new(rom)
+                        .file_name()
+                        .and_then(|x| x.to_str())
+                        .unwrap_or(rom);
+
+                    (String::from(name), Entry::Action(MenuAction::LoadRom(rom.clone())))
+                })
+                .collect(),
+            Page::Settings => vec![
+                (
+                    format!("Sound: {}", if config.mute { "Off" } else { "On" }),
+                    Entry::Action(MenuAction::ToggleMute),
+                ),
+                (
+                    format!("Speed: {}x", config.speed_multiplier),
+                    Entry::Action(MenuAction::CycleSpeed),
+                ),
+                (
+                    format!("Frame skip: {}", config.frame_skip),
+                    Entry::Action(MenuAction::CycleFrameSkip),
+                ),
+                (
+                    format!("Palette: {}", config.palette),
+                    Entry::Action(MenuAction::CyclePalette),
+                ),
+                (
+                    format!("Frame blending: {}", if config.frame_blending { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleFrameBlending),
+                ),
+                (
+                    format!("Collision highlight: {}", if config.highlight_collisions { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleCollisionHighlight),
+                ),
+                (
+                    format!("Shift uses VY: {}", if self.quirks.shift_uses_vy { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleShiftQuirk),
+                ),
+                (
+                    format!("Load / store keeps I: {}", if self.quirks.load_store_keeps_i { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleLoadStoreQuirk),
+                ),
+                (
+                    format!("Jump uses VX: {}", if self.quirks.jump_uses_vx { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleJumpQuirk),
+                ),
+                (
+                    format!("Logic resets VF: {}", if self.quirks.logic_resets_vf { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleLogicQuirk),
+                ),
+                (
+                    format!("Sprites wrap: {}", if self.quirks.sprites_wrap { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleWrapQuirk),
+                ),
+                (
+                    format!("Display wait: {}", if self.quirks.display_wait { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleDisplayWaitQuirk),
+                ),
+                (
+                    format!("Await key release: {}", if self.quirks.await_key_release { "On" } else { "Off" }),
+                    Entry::Action(MenuAction::ToggleAwaitKeyReleaseQuirk),
+                ),
+            ],
+            Page::Cheats => {
+                let mut entries = vec![(String::from("Cheat search"), Entry::Open(Page::CheatSearch))];
+                entries.extend(config.get_rom_cheats().iter().enumerate().map(|(i, cheat)| {
+                    (
+                        format!("[{}] {}", if cheat.enabled { "x" } else { " " }, cheat.name),
+                        Entry::Action(MenuAction::ToggleCheat(i)),
+                    )
+                }));
+
+                entries
+            }
+            Page::CheatSearch => self.search_entries(),
+        };
+
+        if self.page != Page::Main {
+            entries.push((String::from("Back"), Entry::Back));
+        }
+
+        entries
+    }
+
+    // snapshot memory, play, then filter by how the values changed since the previous snapshot
+    fn search_entries(&self) -> Vec<(String, Entry)> {
+        let mut entries = vec![(String::from("New search"), Entry::Action(MenuAction::SearchMemory(None)))];
+
+        if self.cheat_search.is_started() == false {
+            return entries;
+        }
+
+        let filters = vec![
+            (String::from("Increased"), SearchFilter::Increased),
+            (String::from("Decreased"), SearchFilter::Decreased),
+            (String::from("Unchanged"), SearchFilter::Unchanged),
+            (String::from("Changed"), SearchFilter::Changed),
+            (format!("Equals < {} >", self.search_value), SearchFilter::Equals(self.search_value)),
+        ];
+
+        for (label, filter) in filters {
+            entries.push((label, Entry::Action(MenuAction::SearchMemory(Some(filter)))));
+        }
+
+        let candidates = self.cheat_search.candidates();
+        entries.push((format!("{} candidates", candidates.len()), Entry::Info));
+
+        for address in candidates.iter().take(MAX_SEARCH_CANDIDATES) {
+            entries.push((
+                format!("  {:#05X} = {}", address, self.cheat_search.value(*address)),
+                Entry::Info,
+            ));
+        }
+
+        entries
+    }
+
+    // lists all files next to the currently loaded ROM except for IPS patches
+    pub fn list_roms(config: &Config) -> Vec<String> {
+        let dir = Path::new(&config.rom).parent().unwrap_or(Path::new("."));
+
+        match fs::read_dir(dir) {
+            Ok(entries) => {
+                let mut roms = entries
+                    .filter_map(|x| x.ok())
+                    .map(|x| x.path())
+                    .filter(|x| x.is_file())
+                    .filter(|x| x.extension().and_then(|x| x.to_str()) != Some("ips"))
+                    .filter_map(|x| x.to_str().map(String::from))
+                    .collect::<Vec<String>>();
+                roms.sort();
+
+                roms
+            }
+            Err(err) => {
+                warn!("Failed to list ROMs in {}: {}", dir.display(), err);
+                Vec::new()
+            }
+        }
+    }
+}
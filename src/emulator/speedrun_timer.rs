@@ -0,0 +1,104 @@
+use std::time::{Duration, Instant};
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+use log::info;
+
+const WIDTH: f32 = 240.;
+const LINE_HEIGHT: f32 = 20.;
+const SHOWN_SPLITS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Split {
+    pub time: Duration,
+    pub frames: u64,
+}
+
+// real time and emulated 60hz frames since the last reset, the real time includes pauses
+pub struct SpeedrunTimer {
+    visible: bool,
+    started: Instant,
+    splits: Vec<Split>,
+}
+
+impl SpeedrunTimer {
+    pub fn new() -> SpeedrunTimer {
+        SpeedrunTimer {
+            visible: false,
+            started: Instant::now(),
+            splits: Vec::new(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    pub fn reset(&mut self) {
+        self.started = Instant::now();
+        self.splits.clear();
+    }
+
+    pub fn split(&mut self, frames: u64) -> Split {
+        let split = Split {
+            time: self.started.elapsed(),
+            frames,
+        };
+
+        info!("Split {}: {} ({} frames)", self.splits.len() + 1, format_time(split.time), split.frames);
+        self.splits.push(split);
+
+        split
+    }
+
+    pub fn draw(&self, ctx: &mut Context, frames: u64) -> GameResult<()> {
+        let mut content = format!("Time   {}\nFrames {}", format_time(self.started.elapsed()), frames);
+
+        let skipped = self.splits.len().saturating_sub(SHOWN_SPLITS);
+        for (i, split) in self.splits.iter().enumerate().skip(skipped) {
+            content.push_str(&format!("\n{:>2}  {}  {}", i + 1, format_time(split.time), split.frames));
+        }
+
+        let lines = 2 + self.splits.len() - skipped;
+        let background = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(10., 10., WIDTH, lines as f32 * LINE_HEIGHT + 8.),
+            Color::new(0., 0., 0., 0.8),
+        )?;
+
+        graphics::draw(ctx, &background, DrawParam::new())?;
+        graphics::draw(ctx, &Text::new(content), DrawParam::new().dest([16., 14.]))
+    }
+}
+
+// mm:ss.cc, minutes keep counting past an hour
+fn format_time(time: Duration) -> String {
+    let centis = time.as_millis() / 10;
+    format!("{:02}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_time_pads_fields() {
+        assert_eq!(format_time(Duration::from_millis(83_456)), "01:23.45");
+        assert_eq!(format_time(Duration::from_secs(3_605)), "60:05.00");
+    }
+
+    #[test]
+    fn reset_clears_splits() {
+        let mut timer = SpeedrunTimer::new();
+
+        assert_eq!(timer.split(120).frames, 120);
+        timer.reset();
+
+        assert!(timer.splits.is_empty());
+    }
+}
@@ -0,0 +1,91 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use crate::vm::config::Config;
+use crate::vm::display::Snapshot;
+
+// DXYN draws at most 15 rows of 8 pixels
+const SPRITE_WIDTH: usize = 8;
+const MAX_SPRITE_HEIGHT: usize = 15;
+
+// rows of the sprite sized region starting at x, y, pixels outside of the screen are unset
+pub fn sprite_rows(snapshot: &Snapshot, x: usize, y: usize) -> Vec<u8> {
+    let mut rows: Vec<u8> = (y..y + MAX_SPRITE_HEIGHT)
+        .map(|row_y| {
+            (0..SPRITE_WIDTH).fold(0u8, |row, n| {
+                let (pixel_x, pixel_y) = (x + n, row_y);
+                let is_set = pixel_x < snapshot.width() && pixel_y < snapshot.height() && snapshot.is_set(pixel_x, pixel_y);
+
+                match is_set {
+                    true => row | 0x80 >> n,
+                    false => row,
+                }
+            })
+        })
+        .collect();
+
+    while rows.last() == Some(&0) {
+        rows.pop();
+    }
+
+    rows
+}
+
+// byte rows and an Octo snippet which can be pasted into a ROM source
+pub fn format_sprite(x: usize, y: usize, rows: &[u8]) -> String {
+    let mut content = format!("# sprite at ({}, {}), 8x{}\n", x, y, rows.len());
+
+    let bytes: Vec<String> = rows.iter().map(|row| format!("0x{:02X}", row)).collect();
+    content.push_str(&format!("# {}\n", bytes.join(" ")));
+
+    content.push_str(&format!(": sprite_{}_{}\n", x, y));
+    for row in rows {
+        content.push_str(&format!("    0b{:08b}\n", row));
+    }
+
+    content
+}
+
+// appends the sprite at x, y to the sprite file of the current ROM
+pub fn export_sprite(config: &Config, snapshot: &Snapshot, x: usize, y: usize) -> Result<PathBuf> {
+    let rows = sprite_rows(snapshot, x, y);
+    let path = config.get_rom_data_path("sprites.txt");
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    let mut file = OpenOptions::new().create(true).append(true).open(&path)?;
+    writeln!(file, "{}", format_sprite(x, y, &rows))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::display::{Display, VmDisplay};
+
+    #[test]
+    fn sprite_rows_reads_region() {
+        let mut display = VmDisplay::new();
+        display.draw_sprite(3, 2, 3, &[0xF0, 0x90, 0xF0], false);
+
+        let snapshot = display.get_snapshot();
+
+        assert_eq!(sprite_rows(&snapshot, 3, 2), vec![0xF0, 0x90, 0xF0]);
+        assert_eq!(sprite_rows(&snapshot, 4, 3), vec![0x20, 0xE0]);
+        assert!(sprite_rows(&snapshot, 60, 30).is_empty());
+    }
+
+    #[test]
+    fn format_sprite_writes_octo_snippet() {
+        let content = format_sprite(1, 2, &[0xF0, 0x90]);
+
+        assert!(content.contains("# 0xF0 0x90\n"));
+        assert!(content.contains(": sprite_1_2\n    0b11110000\n    0b10010000\n"));
+    }
+}
@@ -0,0 +1,73 @@
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+use crate::vm::display::DrawStats;
+use crate::vm::telemetry::TelemetryReport;
+
+const WIDTH: f32 = 260.;
+const HEIGHT: f32 = 130.;
+
+// shows whether the VM keeps up with the requested hz and how much it draws
+pub struct TelemetryPanel {
+    visible: bool,
+    draw_stats: DrawStats,
+}
+
+impl TelemetryPanel {
+    pub fn new() -> TelemetryPanel {
+        TelemetryPanel {
+            visible: false,
+            draw_stats: DrawStats::default(),
+        }
+    }
+
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn toggle(&mut self) {
+        self.visible = !self.visible;
+    }
+
+    // called once per 60hz frame with the display operations of that frame
+    pub fn update(&mut self, draw_stats: DrawStats) {
+        self.draw_stats = draw_stats;
+    }
+
+    pub fn draw(&self, ctx: &mut Context, report: &TelemetryReport) -> GameResult<()> {
+        let target = match report.target_hz {
+            0 => String::from("unlimited"),
+            hz => format!("{} hz", hz),
+        };
+
+        let mut content = format!(
+            "Cycles: {} / {}\nDropped: {} per second\nJitter: {:.3} ms",
+            report.executed,
+            target,
+            report.dropped,
+            report.average_jitter as f64 / 1_000_000.,
+        );
+
+        content.push_str(&format!(
+            "\nPer frame:\n  Draws: {}  Clears: {}\n  Pixels toggled: {}",
+            self.draw_stats.draws, self.draw_stats.clears, self.draw_stats.pixels_toggled,
+        ));
+
+        if report.dropped > 0 {
+            content.push_str("\nToo slow, try lowering the hz");
+        }
+
+        let area = graphics::screen_coordinates(ctx);
+        let x = area.w - WIDTH - 10.;
+
+        let background = Mesh::new_rectangle(
+            ctx,
+            DrawMode::fill(),
+            Rect::new(x, 10., WIDTH, HEIGHT),
+            Color::new(0., 0., 0., 0.8),
+        )?;
+
+        graphics::draw(ctx, &background, DrawParam::new())?;
+        graphics::draw(ctx, &Text::new(content), DrawParam::new().dest([x + 6., 14.]))
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use anyhow::Result;
+use ggez::graphics::{self, DrawParam, FilterMode, Image};
+use ggez::{Context, GameResult};
+use log::{debug, warn};
+
+use super::palette;
+use crate::headless::Headless;
+use crate::vm::config::Config;
+use crate::vm::hash::rom_hash;
+use crate::vm::rom;
+use crate::vm::screen::Screen;
+
+// most ROMs show their title screen or first level after this many frames
+const FRAMES: usize = 300;
+
+// screenshots of ROMs which are generated in the background and cached in the data directory
+pub struct Thumbnails {
+    // None if the ROM could not be run
+    screens: Arc<Mutex<HashMap<String, Option<Screen>>>>,
+    requested: HashSet<String>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Thumbnails {
+        Thumbnails {
+            screens: Arc::new(Mutex::new(HashMap::new())),
+            requested: HashSet::new(),
+        }
+    }
+
+    // generates the thumbnails of ROMs which have not been requested before on a background thread
+    pub fn request(&mut self, config: &Config, roms: &[String]) {
+        let missing = roms
+            .iter()
+            .filter(|x| self.requested.insert((*x).clone()))
+            .cloned()
+            .collect::<Vec<String>>();
+
+        if missing.len() == 0 {
+            return;
+        }
+
+        let config = config.clone();
+        let screens = self.screens.clone();
+
+        thread::spawn(move || {
+            for rom in missing {
+                let screen = match Thumbnails::generate(&config, &rom) {
+                    Ok(screen) => Some(screen),
+                    Err(err) => {
+                        debug!("No thumbnail for {}: {}", rom, err);
+                        None
+                    }
+                };
+
+                screens.lock().unwrap().insert(rom, screen);
+            }
+        });
+    }
+
+    pub fn draw(&self, ctx: &mut Context, config: &Config, rom: &str) -> GameResult<()> {
+        let screen = match self.screens.lock().unwrap().get(rom) {
+            Some(Some(screen)) => *screen,
+            _ => return Ok(()),
+        };
+
+        let colors = palette::colors(config.palette);
+        let mut rgba = Vec::with_capacity(screen.width() * screen.height() * 4);
+        for (_, _, is_set) in screen.iter_pixels() {
+            let (r, g, b) = match is_set {
+                false => colors.background.to_rgb(),
+                true => colors.plane_1.to_rgb(),
+            };
+
+            rgba.extend_from_slice(&[r, g, b, 255]);
+        }
+
+        let mut image = Image::from_rgba8(ctx, screen.width() as u16, screen.height() as u16, &rgba)?;
+        image.set_filter(FilterMode::Nearest);
+
+        let area = graphics::screen_coordinates(ctx);
+        let scale = (area.w / 2. - 10.) / screen.width() as f32;
+
+        graphics::draw(
+            ctx,
+            &image,
+            DrawParam::new().dest([area.w / 2., 40.]).scale([scale, scale]),
+        )
+    }
+
+    fn generate(config: &Config, rom: &str) -> Result<Screen> {
+        let mut config = config.clone();
+        config.rom = String::from(rom);
+        config.apply_rom_settings();
+
+        let bytes = rom::load(&config)?;
+        let path = Config::get_data_path(rom, &format!("{}.thumbnail", rom_hash(&bytes)));
+
+        // caches of another size were written with an older screen layout and are regenerated
+        if let Some(screen) = fs::read(&path).ok().and_then(|x| Screen::from_bytes(&x)) {
+            return Ok(screen);
+        }
+
+        let mut headless = Headless::new(&config, &bytes)?;
+        for _ in 1..FRAMES {
+            headless.run_frame()?;
+        }
+
+        let screen = *headless.run_frame()?.get_screen();
+
+        if let Err(err) = fs::create_dir_all("data").and_then(|_| fs::write(&path, screen.as_bytes())) {
+            warn!("Failed to cache thumbnail {}: {}", path.display(), err);
+        }
+
+        Ok(screen)
+    }
+}
@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Mesh, Rect, Text};
+use ggez::{Context, GameResult};
+
+const DURATION: Duration = Duration::from_millis(2500);
+const MAX_TOASTS: usize = 4;
+const LINE_HEIGHT: f32 = 24.;
+
+// short lived messages shown in the bottom left corner
+pub struct Toasts {
+    messages: VecDeque<(String, Instant)>,
+}
+
+impl Toasts {
+    pub fn new() -> Toasts {
+        Toasts {
+            messages: VecDeque::with_capacity(MAX_TOASTS),
+        }
+    }
+
+    pub fn push<T: Into<String>>(&mut self, message: T) {
+        if self.messages.len() == MAX_TOASTS {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back((message.into(), Instant::now()));
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.messages.len() > 0
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.messages.retain(|(_, created)| created.elapsed() < DURATION);
+
+        let area = graphics::screen_coordinates(ctx);
+        let count = self.messages.len();
+
+        for (i, (message, _)) in self.messages.iter().enumerate() {
+            let text = Text::new(message.as_str());
+            let y = area.h - (count - i) as f32 * LINE_HEIGHT - 10.;
+            let width = text.width(ctx) as f32;
+
+            let background = Mesh::new_rectangle(
+                ctx,
+                DrawMode::fill(),
+                Rect::new(6., y - 2., width + 8., LINE_HEIGHT - 4.),
+                Color::new(0., 0., 0., 0.8),
+            )?;
+
+            graphics::draw(ctx, &background, DrawParam::new())?;
+            graphics::draw(ctx, &text, DrawParam::new().dest([10., y]))?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn push_drops_oldest_message() {
+        let mut toasts = Toasts::new();
+
+        for i in 0..MAX_TOASTS + 1 {
+            toasts.push(format!("{}", i));
+        }
+
+        assert_eq!(toasts.messages.len(), MAX_TOASTS);
+        assert_eq!(toasts.messages[0].0, "1");
+    }
+}
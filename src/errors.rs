@@ -11,12 +11,160 @@ pub enum Errors {
         max: usize,
     },
 
+    #[error("Load address {address:#05X} is within the reserved area at the end of the memory")]
+    LoadAddressInvalid {
+        address: u16,
+    },
+
+    #[error("Font address {address:#05X} overlaps the ROM or the end of the memory")]
+    FontAddressInvalid {
+        address: u16,
+    },
+
+    #[error("Cannot load font {name} error: {message}")]
+    FontInvalid {
+        name: String,
+        message: String,
+    },
+
+    #[error("Memory size {size} is not between {min} and {max}")]
+    MemorySizeInvalid {
+        size: usize,
+        min: usize,
+        max: usize,
+    },
+
+    #[error("Memory image size {size} does not match the memory size {expected}")]
+    MemoryImageSizeInvalid {
+        size: usize,
+        expected: usize,
+    },
+
     #[error("Cannot load ROM {name} error: {error}")]
     RomLoadFailed {
         name: String,
         error: std::io::Error,
     },
 
+    #[error("Cannot load Octocart {name} error: {message}")]
+    OctocartInvalid {
+        name: String,
+        message: String,
+    },
+
+    #[error("Invalid arguments: {message}\nusage: {usage}")]
+    InvalidArguments {
+        message: String,
+        usage: String,
+    },
+
+    #[error("Invalid IPS patch: {message}")]
+    IpsInvalid {
+        message: String,
+    },
+
+    #[error("Cannot apply IPS patch {name} error: {error}")]
+    IpsApplyFailed {
+        name: String,
+        error: anyhow::Error,
+    },
+
+    #[error("Memory access of {len} bytes at {address:#06X} is out of bounds")]
+    MemoryOutOfBounds {
+        address: usize,
+        len: usize,
+    },
+
+    #[error("Program counter {address:#06X} is outside of the {size} bytes of memory")]
+    PcOutOfRange {
+        address: u16,
+        size: usize,
+    },
+
+    #[error("Write of {len} bytes at {address:#06X} by the instruction at {pc:#06X} into the protected area below {end:#06X}")]
+    WriteProtected {
+        address: usize,
+        len: usize,
+        pc: u16,
+        end: u16,
+    },
+
+    #[error("Peripheral {name} failed: {error}")]
+    PeripheralFailed {
+        name: String,
+        error: anyhow::Error,
+    },
+
     #[error("Stack is empty cannot pop frame")]
     StackEmpty,
+
+    #[error("Stack overflow, calls are nested deeper than {depth}")]
+    StackOverflow {
+        depth: usize,
+    },
+
+    #[error("Cannot load movie {name} error: {error}")]
+    MovieLoadFailed {
+        name: String,
+        error: anyhow::Error,
+    },
+
+    #[error("The VM is not running anymore")]
+    VmStopped,
+
+    #[error("Cannot load save state {name} error: {error}")]
+    SaveStateLoadFailed {
+        name: String,
+        error: anyhow::Error,
+    },
+
+    #[error("Save state format version {version} is not supported, expected {expected}")]
+    SaveStateVersionMismatch {
+        version: u32,
+        expected: u32,
+    },
+
+    #[error("Save state was made with a different ROM")]
+    SaveStateRomMismatch,
+
+    #[error("Save state does not fit the VM: {message}")]
+    SaveStateInvalid {
+        message: String,
+    },
+
+    #[error("Cannot load frame history {name} error: {error}")]
+    FrameHistoryLoadFailed {
+        name: String,
+        error: anyhow::Error,
+    },
+
+    #[error("Cannot export memory heatmap {name} error: {error}")]
+    HeatmapExportFailed {
+        name: String,
+        error: std::io::Error,
+    },
+
+    #[error("Cannot create instruction trace {name} error: {error}")]
+    TraceCreateFailed {
+        name: String,
+        error: std::io::Error,
+    },
+
+    #[error("Movie format version {version} is not supported, expected {expected}")]
+    MovieVersionMismatch {
+        version: u32,
+        expected: u32,
+    },
+
+    #[error("Movie playback diverged from the recording at frame {frame}")]
+    MovieDesync {
+        frame: u64,
+    },
+
+    #[error("Movie was recorded with a different {field}: {recorded}, current: {current}")]
+    MovieMismatch {
+        field: String,
+        recorded: String,
+        current: String,
+    },
 }
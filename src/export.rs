@@ -0,0 +1,247 @@
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::Write;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use gif::{Encoder, Frame, Repeat, SetParameter};
+use log::{info, warn};
+
+use super::emulator::palette;
+use super::headless::Headless;
+use super::vm::config::{Config, Palette};
+use super::vm::constants::{SCREEN_SIZE_X, SCREEN_SIZE_Y};
+use super::vm::display::Snapshot;
+use super::vm::movie::{Movie, MovieHeader};
+use super::vm::rom;
+use crate::errors::Errors;
+
+// gif delays are in 1/100 seconds, most viewers do not honor delays below 2
+const GIF_FRAME_DELAY: u16 = 2;
+const VM_FRAMES_PER_SECOND: u32 = 60;
+// the width of the gif is a u16
+const MAX_SCALE: u16 = u16::MAX / SCREEN_SIZE_X as u16;
+
+pub struct ExportOptions {
+    pub movie: PathBuf,
+    pub output: PathBuf,
+    pub scale: u16,
+    pub palette: Option<Palette>,
+}
+
+impl ExportOptions {
+    pub const USAGE: &'static str = "export <movie> <output.gif> [--scale <n>] [--palette <name>]";
+
+    // parses the arguments following the export command
+    pub fn parse(args: &[String]) -> Result<ExportOptions> {
+        let invalid = |message: &str| -> anyhow::Error {
+            Errors::InvalidArguments {
+                message: String::from(message),
+                usage: String::from(ExportOptions::USAGE),
+            }
+            .into()
+        };
+
+        let mut positional = Vec::new();
+        let mut scale = 4;
+        let mut palette = None;
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--scale" => {
+                    scale = iter
+                        .next()
+                        .and_then(|x| x.parse::<u16>().ok())
+                        .filter(|x| *x > 0 && *x <= MAX_SCALE)
+                        .ok_or_else(|| invalid(&format!("--scale expects a number from 1 to {}", MAX_SCALE)))?
+                }
+                "--palette" => {
+                    palette = Some(
+                        iter.next()
+                            .and_then(|x| x.parse::<Palette>().ok())
+                            .ok_or_else(|| invalid("--palette expects the name of a palette"))?,
+                    )
+                }
+                _ => positional.push(arg),
+            }
+        }
+
+        match positional.as_slice() {
+            [movie, output] => Ok(ExportOptions {
+                movie: PathBuf::from(movie),
+                output: PathBuf::from(output),
+                scale,
+                palette,
+            }),
+            _ => Err(invalid("expected a movie and an output file")),
+        }
+    }
+}
+
+// plays back a movie without a window and writes every frame into a gif
+pub fn export_movie(config: &Config, options: &ExportOptions) -> Result<()> {
+    // the movie was recorded with the settings of the ROM, which includes the options of an Octocart
+    let mut config = config.clone();
+    config.apply_rom_settings();
+
+    let rom = rom::load(&config)?;
+
+    let movie = Movie::load(&options.movie)?;
+    movie.apply_seed(&mut config);
+    movie.validate(&MovieHeader::new(&config, &rom))?;
+    let config = &config;
+
+    encode(config, &rom, &movie, options, File::create(&options.output)?)?;
+
+    info!(
+        "Exported {} frames of {} to {}",
+        movie.frames.len(),
+        options.movie.display(),
+        options.output.display()
+    );
+
+    Ok(())
+}
+
+// plays back the movie and writes the gif to the output
+fn encode<W: Write>(config: &Config, rom: &Vec<u8>, movie: &Movie, options: &ExportOptions, output: W) -> Result<()> {
+    let colors = palette::rom_colors(config, options.palette.unwrap_or(config.palette));
+    let (br, bg, bb) = colors.background.to_rgb();
+    let (fr, fg, fb) = colors.plane_1.to_rgb();
+
+    let width = SCREEN_SIZE_X as u16 * options.scale;
+    let height = SCREEN_SIZE_Y as u16 * options.scale;
+
+    let mut encoder = Encoder::new(output, width, height, &[br, bg, bb, fr, fg, fb])?;
+    encoder.set(Repeat::Infinite)?;
+
+    let mut headless = Headless::new(config, rom)?;
+    headless.play_movie(movie.frames.clone());
+    let mut pending: Option<(Vec<u8>, u16)> = None;
+    let mut written = 0u32;
+    let mut diverged = false;
+
+    for i in 0..movie.frames.len() {
+        let snapshot = headless.run_frame()?;
+
+        // the gif is still written, it just does not show what was recorded from the divergence on
+        for checksum in headless.take_checksums() {
+            if diverged == false {
+                if let Err(err) = movie.verify(&checksum) {
+                    warn!("{}", err);
+                    diverged = true;
+                }
+            }
+        }
+
+        // the VM runs at 60 frames per second, gif frames are sampled from it at a fixed rate
+        let elapsed = (i as u32 + 1) * 100 / VM_FRAMES_PER_SECOND;
+        while (written + 1) * GIF_FRAME_DELAY as u32 <= elapsed {
+            let pixels = render(&snapshot, width as usize, height as usize);
+            written += 1;
+
+            pending = match pending {
+                Some((previous, delay)) if previous == pixels => Some((previous, delay + GIF_FRAME_DELAY)),
+                Some((previous, delay)) => {
+                    write_frame(&mut encoder, &previous, delay, width, height)?;
+                    Some((pixels, GIF_FRAME_DELAY))
+                }
+                None => Some((pixels, GIF_FRAME_DELAY)),
+            };
+        }
+    }
+
+    if let Some((previous, delay)) = pending {
+        write_frame(&mut encoder, &previous, delay, width, height)?;
+    }
+
+    Ok(())
+}
+
+// palette indices of the screen scaled to width x height, 0 is the background and 1 a set pixel
+// hi-res screens are sampled, they keep every pixel from a scale of 2
+fn render(snapshot: &Snapshot, width: usize, height: usize) -> Vec<u8> {
+    let mut pixels = vec![0u8; width * height];
+
+    for (i, pixel) in pixels.iter_mut().enumerate() {
+        let (x, y) = (i % width * snapshot.width() / width, i / width * snapshot.height() / height);
+
+        if snapshot.is_set(x, y) {
+            *pixel = 1;
+        }
+    }
+
+    pixels
+}
+
+fn write_frame<W: Write>(encoder: &mut Encoder<W>, pixels: &[u8], delay: u16, width: u16, height: u16) -> Result<()> {
+    let mut frame = Frame::default();
+    frame.width = width;
+    frame.height = height;
+    frame.delay = delay;
+    frame.buffer = Cow::Borrowed(pixels);
+
+    encoder.write_frame(&frame)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(x: &[&str]) -> Vec<String> {
+        x.iter().map(|x| String::from(*x)).collect()
+    }
+
+    #[test]
+    fn parse_reads_options() {
+        let options = ExportOptions::parse(&args(&["run.yml", "--scale", "8", "out.gif", "--palette", "HighContrast"])).unwrap();
+
+        assert_eq!(options.movie, PathBuf::from("run.yml"));
+        assert_eq!(options.output, PathBuf::from("out.gif"));
+        assert_eq!(options.scale, 8);
+        assert_eq!(options.palette, Some(Palette::HighContrast));
+    }
+
+    #[test]
+    fn parse_rejects_missing_output() {
+        assert!(ExportOptions::parse(&args(&["run.yml"])).is_err());
+        assert!(ExportOptions::parse(&args(&["run.yml", "out.gif", "--scale", "0"])).is_err());
+        assert!(ExportOptions::parse(&args(&["run.yml", "out.gif", "--scale", "1024"])).is_err());
+    }
+
+    #[test]
+    fn encode_writes_the_changed_frames() {
+        let config = Config::default();
+        // draws and erases a pixel whenever the delay timer of 6 frames ran out
+        let rom = vec![
+            0xA2, 0x10, 0xD0, 0x11, 0x62, 0x06, 0xF2, 0x15, 0xF3, 0x07, 0x33, 0x00, 0x12, 0x08, 0x12, 0x02, 0x80,
+        ];
+        let mut movie = Movie::new(MovieHeader::new(&config, &rom));
+        movie.frames = vec![0; VM_FRAMES_PER_SECOND as usize];
+
+        let options = ExportOptions {
+            movie: PathBuf::from("run.yml"),
+            output: PathBuf::from("out.gif"),
+            scale: 2,
+            palette: None,
+        };
+
+        let mut output = Vec::new();
+        encode(&config, &rom, &movie, &options, &mut output).unwrap();
+
+        let mut decoder = gif::Decoder::new(output.as_slice());
+        decoder.set(gif::ColorOutput::Indexed);
+        let mut reader = decoder.read_info().unwrap();
+        assert_eq!((reader.width(), reader.height()), (128, 64));
+
+        let mut delays = Vec::new();
+        while let Some(frame) = reader.read_next_frame().unwrap() {
+            delays.push(frame.delay);
+        }
+
+        assert_eq!(delays.len(), 6);
+        assert_eq!(delays.iter().sum::<u16>(), 100);
+    }
+}
@@ -0,0 +1,96 @@
+use std::sync::atomic::AtomicBool;
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+
+use anyhow::Result;
+
+use super::emulator::input::GGEZInput;
+use super::vm::{
+    audio::Audio,
+    breakpoints::Breakpoints,
+    cheats::Cheats,
+    config::Config,
+    debugger::{Debugger, DebuggerCommand},
+    display::{Display, Snapshot, VmDisplay},
+    movie::Checksum,
+    movie_input::MovieInput,
+    watchpoints::Watchpoints,
+    Vm,
+};
+
+// nanoseconds of a 60hz frame
+const FRAME_DURATION: u128 = 1_000_000_000 / 60;
+const TICK_DURATION: u128 = 100_000;
+
+// runs the VM on the calling thread in emulated time, one frame per call, without a window and without a keyboard
+pub struct Headless {
+    vm: Vm,
+    display: Arc<Mutex<VmDisplay>>,
+}
+
+impl Headless {
+    pub fn new(config: &Config, rom: &Vec<u8>) -> Result<Headless> {
+        let mut config = config.clone();
+        config.debugger.enable = false;
+
+        let display = Arc::new(Mutex::new(VmDisplay::new()));
+        let input = Arc::new(Mutex::new(GGEZInput::new(&config)));
+
+        let (_, rx) = channel::<DebuggerCommand>();
+        let debugger = Debugger::new(
+            &config,
+            Arc::new(AtomicBool::new(false)),
+            rx,
+            Arc::new(Mutex::new(Breakpoints::new())),
+            Arc::new(Mutex::new(Watchpoints::new(Vec::new()))),
+        );
+
+        let mut vm = Vm::new(
+            &config,
+            rom,
+            display.clone(),
+            input,
+            Arc::new(Mutex::new(Audio::new())),
+            debugger,
+            Arc::new(Mutex::new(Cheats::new(config.get_rom_cheats()))),
+        )?;
+        vm.enable_checksums();
+
+        Ok(Headless { vm, display })
+    }
+
+    // the input of a movie, one bitmask of the pressed keys per frame, has to be set before the first frame
+    pub fn play_movie(&mut self, frames: Vec<u16>) {
+        self.vm.set_movie_input(MovieInput::playing(frames));
+    }
+
+    pub fn run_frame(&mut self) -> Result<Snapshot> {
+        let mut elapsed = 0;
+        while elapsed < FRAME_DURATION {
+            self.vm.tick(TICK_DURATION)?;
+            elapsed += TICK_DURATION;
+        }
+
+        Ok(self.display.lock().unwrap().get_snapshot())
+    }
+
+    pub fn take_checksums(&mut self) -> Vec<Checksum> {
+        self.vm.take_checksums()
+    }
+
+    pub fn enable_profiler(&mut self) {
+        self.vm.enable_profiler();
+    }
+
+    pub fn profile_report(&self, n: usize) -> Vec<String> {
+        self.vm.profile_report(n).unwrap_or_default()
+    }
+
+    pub fn enable_coverage(&mut self) {
+        self.vm.enable_coverage();
+    }
+
+    pub fn coverage_report(&self) -> Vec<String> {
+        self.vm.coverage_report().unwrap_or_default()
+    }
+}
@@ -1,11 +1,18 @@
 #![feature(const_int_pow)]
 #![cfg_attr(test, feature(proc_macro_hygiene))]
 
+mod debug_server;
 mod errors;
 mod emulator;
+mod export;
+mod headless;
+mod profile;
 mod runner;
 mod vm;
 
+use std::env;
+use std::process;
+
 use flexi_logger::{LogSpecBuilder, Logger};
 use log::{error, info, LevelFilter};
 
@@ -18,8 +25,11 @@ use ggez::{
 use winit::EventsLoop;
 
 use emulator::Emulator;
+use export::{export_movie, ExportOptions};
+use profile::{profile_rom, ProfileOptions};
 use vm::config::Config;
 use vm::constants::*;
+use vm::symbols::{parse_address, Symbols};
 
 struct ErrorWindow {
     message: String,
@@ -57,7 +67,7 @@ impl EventHandler for ErrorWindow {
 
 fn main() {
     match Config::load() {
-        Ok(config) => {
+        Ok(mut config) => {
             let log_init_result = Logger::with(
                 LogSpecBuilder::new()
                     .default(config.log_level)
@@ -71,6 +81,65 @@ fn main() {
                 println!("ERROR initializing logger: {}", err);
             }
 
+            let args = env::args().skip(1).collect::<Vec<String>>();
+            match args.first().map(|x| x.as_str()) {
+                Some("export") => return run_export(&config, &args[1..]),
+                Some("profile") => return run_profile(&config, &args[1..]),
+                Some("attract") => config.attract_mode.enable = true,
+                _ => (),
+            }
+
+            if args.iter().any(|x| x == "--break-on-start") {
+                config.debugger.enable = true;
+                config.debugger.break_on_start = true;
+            }
+
+            if args.iter().any(|x| x == "--trace") {
+                config.trace = true;
+            }
+
+            if args.iter().any(|x| x == "--profile") {
+                config.profile = true;
+            }
+
+            if args.iter().any(|x| x == "--coverage") {
+                config.coverage = true;
+            }
+
+            if args.iter().any(|x| x == "--heatmap") {
+                config.heatmap = true;
+            }
+
+            if let Some(path) = args.iter().position(|x| x == "--symbols").and_then(|x| args.get(x + 1)) {
+                config.debugger.symbols = Some(path.clone());
+            }
+
+            // may be repeated, every address or label gets a breakpoint
+            let breakpoints: Vec<&String> = args.iter().zip(args.iter().skip(1)).filter(|(x, _)| *x == "--breakpoint").map(|(_, x)| x).collect();
+            let symbols = match breakpoints.iter().any(|x| parse_address(x).is_none()) {
+                true => Symbols::load(&config),
+                false => Symbols::default(),
+            };
+            for address in breakpoints {
+                match symbols.resolve(address) {
+                    Some(address) => config.debugger.breakpoints.push(address),
+                    None => return run_error_window(format!("Invalid breakpoint {}, expected an address like 0x2A0 or a label", address)),
+                }
+                config.debugger.enable = true;
+            }
+
+            if let Some(address) = args.iter().position(|x| x == "--debug-server").and_then(|x| args.get(x + 1)) {
+                config.debugger.server = Some(address.clone());
+                config.debugger.enable = true;
+            }
+
+            if let Some(seed) = args.iter().position(|x| x == "--seed").and_then(|x| args.get(x + 1)) {
+                match seed.parse() {
+                    Ok(seed) => config.seed = Some(seed),
+                    Err(..) => return run_error_window(format!("Invalid seed {}, expected a number", seed)),
+                }
+            }
+
             let (mut ctx, event_loop) = create_context(
                 {
                     let screen_scaling = config.screen_scaling;
@@ -99,6 +168,25 @@ fn main() {
     }
 }
 
+fn run_export(config: &Config, args: &[String]) {
+    let result = ExportOptions::parse(args).and_then(|options| export_movie(config, &options));
+
+    if let Err(err) = result {
+        eprintln!("ERROR exporting movie: {}", err);
+        process::exit(1);
+    }
+}
+
+fn run_profile(config: &Config, args: &[String]) {
+    match ProfileOptions::parse(args).and_then(|options| profile_rom(config, &options)) {
+        Ok(lines) => lines.iter().for_each(|x| println!("{}", x)),
+        Err(err) => {
+            eprintln!("ERROR profiling ROM: {}", err);
+            process::exit(1);
+        }
+    }
+}
+
 fn run_error_window(message: String) {
     let (ctx, event_loop) = create_context(
         {
@@ -0,0 +1,127 @@
+use std::path::PathBuf;
+
+use anyhow::Result;
+
+use super::headless::Headless;
+use super::vm::config::Config;
+use super::vm::movie::{Movie, MovieHeader};
+use super::vm::profiler::REPORT_SIZE;
+use super::vm::rom;
+use crate::errors::Errors;
+
+// 10 seconds of emulated time
+const DEFAULT_FRAMES: usize = 600;
+
+pub struct ProfileOptions {
+    pub frames: Option<usize>,
+    pub top: usize,
+    pub movie: Option<PathBuf>,
+}
+
+impl ProfileOptions {
+    pub const USAGE: &'static str = "profile [--frames <n>] [--top <n>] [--movie <movie>]";
+
+    // parses the arguments following the profile command
+    pub fn parse(args: &[String]) -> Result<ProfileOptions> {
+        let invalid = |message: &str| -> anyhow::Error {
+            Errors::InvalidArguments {
+                message: String::from(message),
+                usage: String::from(ProfileOptions::USAGE),
+            }
+            .into()
+        };
+
+        let mut options = ProfileOptions {
+            frames: None,
+            top: REPORT_SIZE,
+            movie: None,
+        };
+
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--frames" => {
+                    options.frames = Some(
+                        iter.next()
+                            .and_then(|x| x.parse::<usize>().ok())
+                            .filter(|x| *x > 0)
+                            .ok_or_else(|| invalid("--frames expects a positive number"))?,
+                    )
+                }
+                "--top" => {
+                    options.top = iter
+                        .next()
+                        .and_then(|x| x.parse::<usize>().ok())
+                        .filter(|x| *x > 0)
+                        .ok_or_else(|| invalid("--top expects a positive number"))?
+                }
+                "--movie" => {
+                    options.movie = Some(PathBuf::from(
+                        iter.next().ok_or_else(|| invalid("--movie expects a movie file"))?,
+                    ))
+                }
+                _ => return Err(invalid(&format!("unknown argument {}", arg))),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+// runs the ROM without a window, without input or with the input of a movie, and returns the reports of the profiler and the opcode coverage
+pub fn profile_rom(config: &Config, options: &ProfileOptions) -> Result<Vec<String>> {
+    let mut config = config.clone();
+    config.apply_rom_settings();
+
+    let rom = rom::load(&config)?;
+
+    let inputs = match &options.movie {
+        Some(path) => {
+            let movie = Movie::load(path)?;
+            movie.apply_seed(&mut config);
+            movie.validate(&MovieHeader::new(&config, &rom))?;
+            movie.frames
+        }
+        None => Vec::new(),
+    };
+    let config = &config;
+    let frames = options.frames.unwrap_or(match inputs.is_empty() {
+        true => DEFAULT_FRAMES,
+        false => inputs.len(),
+    });
+
+    let mut headless = Headless::new(config, &rom)?;
+    headless.enable_profiler();
+    headless.enable_coverage();
+
+    headless.play_movie(inputs);
+
+    for _ in 0..frames {
+        headless.run_frame()?;
+    }
+
+    let mut lines = headless.profile_report(options.top);
+    lines.extend(headless.coverage_report());
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(x: &[&str]) -> Vec<String> {
+        x.iter().map(|x| String::from(*x)).collect()
+    }
+
+    #[test]
+    fn parse_reads_options() {
+        let options = ProfileOptions::parse(&args(&["--top", "5", "--movie", "run.yml"])).unwrap();
+
+        assert_eq!(options.frames, None);
+        assert_eq!(options.top, 5);
+        assert_eq!(options.movie, Some(PathBuf::from("run.yml")));
+
+        assert!(ProfileOptions::parse(&args(&["--frames", "0"])).is_err());
+        assert!(ProfileOptions::parse(&args(&["run.yml"])).is_err());
+    }
+}
@@ -1,58 +1,186 @@
-use std::fs;
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::mpsc::{channel, Sender};
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use anyhow::Result;
 use log::{error, info, warn};
 
+use crate::debug_server::{self, DebugTarget};
+use crate::errors::Errors;
+
 use super::vm::{
     audio::Audio,
+    breakpoints::{Breakpoint, Breakpoints},
+    cheats::Cheats,
     config::Config,
-    debugger::{Debugger, DebuggerCommand},
-    display::{Display, VmDisplay, Snapshot},
+    debugger::{DebugView, Debugger, DebuggerCommand},
+    display::{Display, DrawStats, SpriteDraw, VmDisplay, Snapshot},
+    heatmap::MemoryHeatmap,
     input::Input,
+    quirks::Quirks,
+    rom,
+    movie::Checksum,
+    movie_input::MovieInput,
+    rpl_flags,
+    save_state::SaveState,
+    screen::Screen,
+    telemetry::TelemetryReport,
+    profiler::REPORT_SIZE,
+    trace::InstructionTrace,
+    watchpoints::{Watchpoint, Watchpoints},
     Vm,
 };
-use crate::errors::Errors;
+
+// requests handled by the VM thread, also while the VM is paused
+pub enum ControlCommand {
+    Pause,
+    Resume,
+    SetSpeedMultiplier(f32),
+    // multiplies the speed multiplier while enabled
+    SetFastForward(Option<f32>),
+    // executes n instructions, intended to be used while paused
+    Step(u32),
+    // executes the instructions of a single 60hz frame, intended to be used while paused
+    AdvanceFrame,
+    ReadMemory(Sender<Vec<u8>>),
+    ReadDebugView(Sender<DebugView>),
+    SaveState(Sender<SaveState>),
+    LoadState(SaveState, Sender<std::result::Result<(), Errors>>),
+    SetQuirks(Quirks),
+    // goes back by one frame, intended to be used while paused
+    Rewind,
+    // None stops writing the instruction trace
+    SetTrace(Option<InstructionTrace>),
+    // starts counting the memory accesses, they are kept until the ROM is reloaded
+    EnableHeatmap,
+    // None until the heatmap was enabled
+    ReadHeatmap(Sender<Option<MemoryHeatmap>>),
+}
+
+// state of the VM thread which is changed through control commands
+struct ControlState {
+    paused: bool,
+    speed_multiplier: f32,
+    fast_forward: Option<f32>,
+}
+
+impl ControlState {
+    // the time which passes for the VM, the tick scheduler and the timers follow it
+    fn scale(&self, delta: u128) -> u128 {
+        let multiplier = self.speed_multiplier * self.fast_forward.unwrap_or(1.);
+        (delta as f64 * multiplier as f64) as u128
+    }
+}
 
 pub struct Runner {
     display: Arc<Mutex<dyn Display>>,
     audio: Arc<Mutex<Audio>>,
     alive: Arc<AtomicBool>,
+    control: Sender<ControlCommand>,
+    paused: bool,
+    tracing: bool,
+    // quirks the VM currently runs with, they can be changed at runtime
+    quirks: Quirks,
+
+    telemetry: Arc<Mutex<TelemetryReport>>,
+    frame_count: Arc<AtomicU64>,
+    // set while the ROM is exited, the VM thread idles then
+    exited: Arc<AtomicBool>,
+    // set while the ROM jumps to itself, the VM thread idles then
+    halted: Arc<AtomicBool>,
+    // checksums of the VM state which were not taken by the movie yet
+    checksums: Arc<Mutex<Vec<Checksum>>>,
+    // recorded input of the movie which was not taken yet, one bitmask per frame
+    movie_input: Arc<Mutex<Vec<u16>>>,
+    // the last execution error, the VM thread pauses itself when it occurs
+    error: Arc<Mutex<Option<Errors>>>,
 
     debug_break: Arc<AtomicBool>,
     debug_sender: Sender<DebuggerCommand>,
+    breakpoints: Arc<Mutex<Breakpoints>>,
+    watchpoints: Arc<Mutex<Watchpoints>>,
+    cheats: Arc<Mutex<Cheats>>,
 
     handle: Option<JoinHandle<()>>,
+    debug_server: Option<JoinHandle<()>>,
 }
 
 impl Runner {
-    pub fn new(config: &Config, input: Arc<Mutex<dyn Input>>) -> Result<Runner> {
-        let rom_bytes = match fs::read(&config.rom) {
-            Ok(bytes) => bytes,
-            Err(err) => {
-                return Err(Errors::RomLoadFailed {
-                    name: config.rom.clone(),
-                    error: err,
-                }
-                .into())
-            }
-        };
+    // the movie input replaces the keyboard from the reset on while a movie is recorded or played
+    pub fn new(config: &Config, input: Arc<Mutex<dyn Input>>, movie_input: Option<MovieInput>) -> Result<Runner> {
+        let rom_bytes = rom::load(config)?;
 
         let display = Arc::new(Mutex::new(VmDisplay::new()));
         let audio = Arc::new(Mutex::new(Audio::new()));
         let alive = Arc::new(AtomicBool::new(true));
+        let telemetry = Arc::new(Mutex::new(TelemetryReport::default()));
+        let frame_count = Arc::new(AtomicU64::new(0));
+        let exited = Arc::new(AtomicBool::new(false));
+        let halted = Arc::new(AtomicBool::new(false));
+        let checksums = Arc::new(Mutex::new(Vec::new()));
+        let recorded_input = Arc::new(Mutex::new(Vec::new()));
+        let error = Arc::new(Mutex::new(None));
 
+        let (control_tx, control_rx) = channel::<ControlCommand>();
         let (tx, rx) = channel::<DebuggerCommand>();
-        let debug_break = Arc::new(AtomicBool::new(false));
+        let break_on_start = config.debugger.enable && config.debugger.break_on_start;
+        let debug_break = Arc::new(AtomicBool::new(break_on_start));
+        if break_on_start {
+            info!("Halted before the first instruction at {:#05X}", config.get_load_address());
+        }
+
+        let breakpoints = Arc::new(Mutex::new(match config.debugger.enable {
+            true => {
+                let mut breakpoints = Breakpoints::load(config.get_rom_data_path("breakpoints.yml"));
+                config.debugger.breakpoints.iter().for_each(|x| breakpoints.add(*x));
+                breakpoints
+            }
+            false => Breakpoints::new(),
+        }));
 
-        let debugger = Debugger::new(config, debug_break.clone(), rx);
+        let watchpoints = Arc::new(Mutex::new(match config.debugger.enable {
+            true => {
+                let mut watchpoints = Watchpoints::load(config.get_rom_data_path("watchpoints.yml"));
+                config.debugger.watchpoints.iter().for_each(|x| watchpoints.add(x.clone()));
+                watchpoints
+            }
+            false => Watchpoints::new(Vec::new()),
+        }));
+
+        let debugger = Debugger::new(config, debug_break.clone(), rx, breakpoints.clone(), watchpoints.clone());
+
+        // stops with the VM thread, a server which cannot be started does not stop the emulator
+        let debug_server = config.debugger.server.as_ref().and_then(|address| {
+            let target = DebugTarget {
+                debug_break: debug_break.clone(),
+                commands: tx.clone(),
+                symbols: debugger.get_symbols(),
+            };
+
+            match debug_server::start(address, target, alive.clone()) {
+                Ok(handle) => Some(handle),
+                Err(err) => {
+                    warn!("Failed to start the debug server on {}: {}", address, err);
+                    None
+                }
+            }
+        });
+        let cheats = Arc::new(Mutex::new(Cheats::new(config.get_rom_cheats())));
 
         let thread_alive = alive.clone();
+        let thread_telemetry = telemetry.clone();
+        let thread_frame_count = frame_count.clone();
+        let thread_exited = exited.clone();
+        let thread_halted = halted.clone();
+        let thread_debug_break = debug_break.clone();
+        let thread_checksums = checksums.clone();
+        let thread_recorded_input = recorded_input.clone();
+        let thread_error = error.clone();
+        let speed_multiplier = config.speed_multiplier;
         match Vm::new(
             config,
             &rom_bytes,
@@ -60,35 +188,256 @@ impl Runner {
             input.clone(),
             audio.clone(),
             debugger,
+            cheats.clone(),
         ) {
             Ok(mut vm) => {
                 info!("Starting VM ...");
+                let quirks = vm.get_quirks();
+
+                // a movie is recorded or played back from the reset on
+                let movie_active = movie_input.is_some();
+
+                // movies start with cleared flags, so they play back the same everywhere
+                let rpl_flags_path = match movie_active {
+                    false => Some(config.get_rom_data_path("flags")),
+                    true => None,
+                };
+                if let Some(path) = &rpl_flags_path {
+                    vm.set_rpl_flags(rpl_flags::load(path));
+                }
+
+                // the checksums are stored while a movie is recorded and verified while it is played
+                if movie_active {
+                    vm.enable_checksums();
+                }
+                if let Some(movie_input) = movie_input {
+                    vm.set_movie_input(movie_input);
+                }
+                vm.enable_rewind(config.rewind_seconds);
+                if config.profile {
+                    vm.enable_profiler();
+                }
+                if config.coverage {
+                    vm.enable_coverage();
+                }
+                if config.heatmap {
+                    vm.enable_heatmap();
+                }
+
+                let trace_path = config.get_rom_data_path("trace");
+                let tracing = config.trace
+                    && match InstructionTrace::create(&trace_path) {
+                        Ok(trace) => {
+                            vm.set_trace(Some(trace));
+                            info!("Writing the instruction trace to {}", trace_path.display());
+                            true
+                        }
+                        Err(err) => {
+                            warn!("Failed to start the instruction trace: {}", err);
+                            false
+                        }
+                    };
 
                 let handle = thread::spawn(move || {
                     let mut delta = 0u128;
+                    let mut saved_rpl_flags = vm.get_rpl_flags();
+                    let mut state = ControlState {
+                        paused: false,
+                        speed_multiplier,
+                        fast_forward: None,
+                    };
+
                     while thread_alive.load(Ordering::SeqCst) {
+                        Runner::process_control(&mut vm, &control_rx, &mut state, &thread_error);
+
+                        // a finished ROM does not do anything anymore, the thread keeps answering the control commands
+                        // and the debugger, a rewind or a loaded state lets the ROM continue
+                        thread_exited.store(vm.has_exited(), Ordering::SeqCst);
+                        thread_halted.store(vm.has_halted(), Ordering::SeqCst);
+                        let finished = (vm.has_exited() || vm.has_halted()) && thread_debug_break.load(Ordering::SeqCst) == false;
+
+                        if state.paused || finished {
+                            thread::sleep(Duration::from_millis(10));
+                            delta = 0;
+                            continue;
+                        }
+
                         let t0 = Instant::now();
 
-                        if let Err(msg) = vm.tick(delta) {
-                            error!("ERROR in VM execution: {}", msg);
+                        let result = vm.tick(state.scale(delta));
+                        Runner::store_error(result, &thread_error, &mut state);
+
+                        let checksums = vm.take_checksums();
+                        if checksums.is_empty() == false {
+                            thread_checksums.lock().unwrap().extend(checksums);
+                        }
+
+                        let input = vm.take_movie_input();
+                        if input.is_empty() == false {
+                            thread_recorded_input.lock().unwrap().extend(input);
+                        }
+
+                        if let Some(report) = vm.take_telemetry_report() {
+                            *thread_telemetry.lock().unwrap() = report;
+                        }
+                        thread_frame_count.store(vm.get_frame_count(), Ordering::SeqCst);
+
+                        let rpl_flags = vm.get_rpl_flags();
+                        if let Some(path) = rpl_flags_path.as_ref().filter(|_| rpl_flags != saved_rpl_flags) {
+                            rpl_flags::save(path, &rpl_flags);
+                            saved_rpl_flags = rpl_flags;
                         }
 
                         let dur = Instant::now() - t0;
                         delta = dur.as_nanos();
                     }
+
+                    if let Some(lines) = vm.profile_report(REPORT_SIZE) {
+                        info!("Execution profile:\n{}", lines.join("\n"));
+                    }
+                    if let Some(lines) = vm.coverage_report() {
+                        info!("{}", lines.join("\n"));
+                    }
                 });
 
                 Ok(Runner {
                     display,
                     audio,
                     alive,
+                    control: control_tx,
+                    paused: false,
+                    tracing,
+                    quirks,
+                    telemetry,
+                    frame_count,
+                    exited,
+                    halted,
+                    checksums,
+                    movie_input: recorded_input,
+                    error,
                     debug_break,
                     debug_sender: tx,
+                    breakpoints,
+                    watchpoints,
+                    cheats,
                     handle: Some(handle),
+                    debug_server,
                 })
             }
-            Err(msg) => Err(msg),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    // the VM pauses itself on errors, the error is shown by the emulator
+    fn store_error(result: std::result::Result<(), Errors>, error: &Mutex<Option<Errors>>, state: &mut ControlState) {
+        if let Err(err) = result {
+            error!("ERROR in VM execution: {}", err);
+            *error.lock().unwrap() = Some(err);
+            state.paused = true;
+        }
+    }
+
+    fn process_control(vm: &mut Vm, control: &Receiver<ControlCommand>, state: &mut ControlState, error: &Mutex<Option<Errors>>) {
+        while let Ok(command) = control.try_recv() {
+            match command {
+                ControlCommand::Pause => state.paused = true,
+                ControlCommand::Resume => state.paused = false,
+                ControlCommand::SetSpeedMultiplier(multiplier) => state.speed_multiplier = multiplier,
+                ControlCommand::SetFastForward(multiplier) => state.fast_forward = multiplier,
+                // stepping stops at the first error
+                ControlCommand::Step(n) => Runner::store_error((0..n).try_for_each(|_| vm.step()), error, state),
+                ControlCommand::AdvanceFrame => Runner::store_error(vm.advance_frame(), error, state),
+                ControlCommand::ReadMemory(reply) => {
+                    let _ = reply.send(vm.get_memory());
+                }
+                ControlCommand::ReadDebugView(reply) => {
+                    let _ = reply.send(vm.get_debug_view());
+                }
+                ControlCommand::SetQuirks(quirks) => vm.set_quirks(quirks),
+                ControlCommand::SaveState(reply) => {
+                    let _ = reply.send(vm.get_state());
+                }
+                ControlCommand::LoadState(state, reply) => {
+                    let _ = reply.send(vm.set_state(&state));
+                }
+                ControlCommand::Rewind => {
+                    vm.rewind();
+                }
+                ControlCommand::SetTrace(trace) => vm.set_trace(trace),
+                ControlCommand::EnableHeatmap => vm.enable_heatmap(),
+                ControlCommand::ReadHeatmap(reply) => {
+                    let _ = reply.send(vm.get_heatmap());
+                }
+            }
+        }
+    }
+
+    fn send_control_command(&self, command: ControlCommand) {
+        if let Err(err) = self.control.send(command) {
+            warn!("Failed to send control command: {}", err);
+        }
+    }
+
+    // copy of the VM memory, empty if the VM thread is not running anymore
+    pub fn get_memory(&self) -> Vec<u8> {
+        let (tx, rx) = channel::<Vec<u8>>();
+
+        if let Err(err) = self.control.send(ControlCommand::ReadMemory(tx)) {
+            warn!("Failed to request memory: {}", err);
+            return Vec::new();
+        }
+
+        rx.recv().unwrap_or_default()
+    }
+
+    // registers, stack, timers and disassembly of the current frame, empty if the VM thread is not running anymore
+    pub fn get_debug_view(&self) -> DebugView {
+        let (tx, rx) = channel::<DebugView>();
+
+        if let Err(err) = self.control.send(ControlCommand::ReadDebugView(tx)) {
+            warn!("Failed to request the debug view: {}", err);
+            return DebugView::default();
+        }
+
+        rx.recv().unwrap_or_default()
+    }
+
+    pub fn enable_heatmap(&self) {
+        self.send_control_command(ControlCommand::EnableHeatmap);
+    }
+
+    // None if the heatmap is not enabled or the VM thread is not running anymore
+    pub fn get_heatmap(&self) -> Option<MemoryHeatmap> {
+        let (tx, rx) = channel::<Option<MemoryHeatmap>>();
+
+        if let Err(err) = self.control.send(ControlCommand::ReadHeatmap(tx)) {
+            warn!("Failed to request the memory heatmap: {}", err);
+            return None;
         }
+
+        rx.recv().unwrap_or_default()
+    }
+
+    pub fn save_state(&self, path: &Path) -> Result<()> {
+        let (tx, rx) = channel::<SaveState>();
+        self.send_control_command(ControlCommand::SaveState(tx));
+
+        let state = rx.recv().map_err(|_| Errors::VmStopped)?;
+        state.save(path)?;
+
+        info!("Saved state to {}", path.display());
+        Ok(())
+    }
+
+    pub fn load_state(&self, path: &Path) -> Result<()> {
+        let state = SaveState::load(path)?;
+        let (tx, rx) = channel();
+        self.send_control_command(ControlCommand::LoadState(state, tx));
+
+        rx.recv().map_err(|_| Errors::VmStopped)??;
+
+        info!("Loaded state from {}", path.display());
+        Ok(())
     }
 
     pub fn get_display_snapshot(&self) -> Snapshot {
@@ -96,21 +445,188 @@ impl Runner {
         display.get_snapshot()
     }
 
+    pub fn take_collided_pixels(&self) -> Screen {
+        let mut display = self.display.lock().unwrap();
+        display.take_collided()
+    }
+
+    pub fn take_sprite_draws(&self) -> Vec<SpriteDraw> {
+        let mut display = self.display.lock().unwrap();
+        display.take_sprite_draws()
+    }
+
+    pub fn take_draw_stats(&self) -> DrawStats {
+        let mut display = self.display.lock().unwrap();
+        display.take_draw_stats()
+    }
+
     pub fn is_playing_sound(&self) -> bool {
         let audio = self.audio.lock().unwrap();
         audio.is_playing()
     }
 
+    // measurements of the last second the VM was running
+    pub fn get_telemetry(&self) -> TelemetryReport {
+        *self.telemetry.lock().unwrap()
+    }
+
+    // emulated 60hz frames since the VM was started
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count.load(Ordering::SeqCst)
+    }
+
+    // the VM thread idles while the program is finished
+    pub fn has_exited(&self) -> bool {
+        self.exited.load(Ordering::SeqCst)
+    }
+
+    // the VM thread idles while the program is stuck in an endless loop
+    pub fn has_halted(&self) -> bool {
+        self.halted.load(Ordering::SeqCst)
+    }
+
+    pub fn take_checksums(&self) -> Vec<Checksum> {
+        self.checksums.lock().unwrap().drain(..).collect()
+    }
+
+    pub fn take_movie_input(&self) -> Vec<u16> {
+        self.movie_input.lock().unwrap().drain(..).collect()
+    }
+
+    // the error which paused the VM, if it was not taken yet
+    pub fn take_error(&self) -> Option<Errors> {
+        self.error.lock().unwrap().take()
+    }
+
+    pub fn pause(&mut self) {
+        self.paused = true;
+        self.send_control_command(ControlCommand::Pause);
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.send_control_command(ControlCommand::Resume);
+    }
+
+    // None runs the VM at the normal speed again
+    pub fn set_fast_forward(&mut self, multiplier: Option<f32>) {
+        self.send_control_command(ControlCommand::SetFastForward(multiplier));
+    }
+
+    pub fn advance_frame(&mut self) {
+        self.send_control_command(ControlCommand::AdvanceFrame);
+    }
+
+    pub fn rewind(&mut self) {
+        self.send_control_command(ControlCommand::Rewind);
+    }
+
+    // replaces the trace at the path, it is written until the trace is stopped
+    pub fn start_trace(&mut self, path: &Path) -> Result<()> {
+        let trace = InstructionTrace::create(path)?;
+        self.tracing = true;
+        self.send_control_command(ControlCommand::SetTrace(Some(trace)));
+
+        info!("Writing the instruction trace to {}", path.display());
+        Ok(())
+    }
+
+    pub fn stop_trace(&mut self) {
+        self.tracing = false;
+        self.send_control_command(ControlCommand::SetTrace(None));
+    }
+
+    pub fn is_tracing(&self) -> bool {
+        self.tracing
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    // scales the elapsed time seen by the VM, 2.0 runs it twice as fast
+    pub fn set_speed_multiplier(&mut self, multiplier: f32) {
+        self.send_control_command(ControlCommand::SetSpeedMultiplier(multiplier));
+    }
+
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.send_control_command(ControlCommand::SetQuirks(quirks));
+    }
+
+    // runs single instructions, the VM has to be paused for stepping to be observable
+    pub fn step(&mut self, n: u32) {
+        self.send_control_command(ControlCommand::Step(n));
+    }
+
     pub fn toggle_debugger_break(&mut self) {
         let x = self.debug_break.load(Ordering::SeqCst);
         self.debug_break.store(!x, Ordering::SeqCst);
     }
 
+    pub fn is_debugger_halted(&self) -> bool {
+        self.debug_break.load(Ordering::SeqCst)
+    }
+
+    // debugger commands are only processed while the VM is halted
+    pub fn enter_debugger_break(&mut self) {
+        self.debug_break.store(true, Ordering::SeqCst);
+    }
+
     pub fn send_debugger_command(&mut self, command: DebuggerCommand) {
         if let Err(err) = self.debug_sender.send(command) {
             warn!("Failed to send debugger command: {}", err);
         }
     }
+
+    pub fn get_breakpoints(&self) -> Vec<Breakpoint> {
+        let breakpoints = self.breakpoints.lock().unwrap();
+        breakpoints.entries().to_vec()
+    }
+
+    pub fn toggle_breakpoint_enabled(&mut self, index: usize) {
+        let mut breakpoints = self.breakpoints.lock().unwrap();
+        breakpoints.toggle_enabled(index);
+    }
+
+    pub fn remove_breakpoint(&mut self, index: usize) {
+        let mut breakpoints = self.breakpoints.lock().unwrap();
+        breakpoints.remove(index);
+    }
+
+    pub fn get_watchpoints(&self) -> Vec<Watchpoint> {
+        let watchpoints = self.watchpoints.lock().unwrap();
+        watchpoints.entries().to_vec()
+    }
+
+    pub fn add_watchpoint(&mut self, watchpoint: Watchpoint) {
+        let mut watchpoints = self.watchpoints.lock().unwrap();
+        watchpoints.add(watchpoint);
+    }
+
+    pub fn toggle_watchpoint_enabled(&mut self, index: usize) {
+        let mut watchpoints = self.watchpoints.lock().unwrap();
+        watchpoints.toggle_enabled(index);
+    }
+
+    pub fn remove_watchpoint(&mut self, index: usize) {
+        let mut watchpoints = self.watchpoints.lock().unwrap();
+        watchpoints.remove(index);
+    }
+
+    pub fn remove_watchpoints_at(&mut self, address: u16) {
+        let mut watchpoints = self.watchpoints.lock().unwrap();
+        watchpoints.remove_at(address);
+    }
+
+    pub fn set_cheat_enabled(&mut self, index: usize, enabled: bool) {
+        let mut cheats = self.cheats.lock().unwrap();
+        cheats.set_enabled(index, enabled);
+    }
 }
 
 impl Drop for Runner {
@@ -121,5 +637,10 @@ impl Drop for Runner {
         if let Some(handle) = self.handle.take() {
             handle.join().unwrap();
         }
+
+        // releases the port before the next runner binds it
+        if let Some(handle) = self.debug_server.take() {
+            handle.join().unwrap();
+        }
     }
 }
@@ -4,6 +4,37 @@ pub mod audio;
 pub mod display;
 pub mod config;
 pub mod debugger;
+pub mod font;
+pub mod frame_history;
+pub mod breakpoints;
+pub mod cheat_search;
+pub mod cheats;
+pub mod data_file;
+pub mod collisions;
+pub mod condition;
+pub mod coverage;
+pub mod hash;
+pub mod heatmap;
+pub mod ips;
+pub mod movie;
+pub mod movie_input;
+pub mod octocart;
+pub mod peripheral;
+pub mod platform;
+pub mod profiler;
+pub mod quirks;
+pub mod rom;
+pub mod rom_database;
+pub mod rpl_flags;
+pub mod save_state;
+pub mod screen;
+pub mod symbols;
+pub mod self_modification;
+pub mod telemetry;
+pub mod trace;
+pub mod variant;
+pub mod watch;
+pub mod watchpoints;
 
 mod timer;
 mod opcodes;
@@ -0,0 +1,231 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use super::condition::{Condition, ConditionContext};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub enabled: bool,
+    // only breaks if it holds, e.g. V3 == 0x1F && I >= 0x300
+    #[serde(default)]
+    pub condition: Option<String>,
+
+    #[serde(skip)]
+    pub hit_count: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct Breakpoints {
+    entries: Vec<Breakpoint>,
+    path: Option<PathBuf>,
+}
+
+impl Breakpoints {
+    pub fn new() -> Breakpoints {
+        Breakpoints::default()
+    }
+
+    // loads the breakpoints persisted for a ROM, changes are written back to the same file
+    pub fn load(path: PathBuf) -> Breakpoints {
+        let entries = match fs::read_to_string(&path) {
+            Ok(yaml) => match serde_yaml::from_str::<Vec<Breakpoint>>(&yaml) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    warn!("Failed to parse breakpoints {}: {}", path.display(), err);
+                    Vec::new()
+                }
+            },
+            Err(..) => Vec::new(),
+        };
+
+        Breakpoints {
+            entries,
+            path: Some(path),
+        }
+    }
+
+    pub fn entries(&self) -> &[Breakpoint] {
+        &self.entries
+    }
+
+    pub fn toggle_at(&mut self, address: u16) {
+        match self.entries.iter().position(|x| x.address == address) {
+            Some(index) => {
+                self.entries.remove(index);
+            }
+            None => {
+                self.entries.push(Breakpoint {
+                    address,
+                    enabled: true,
+                    condition: None,
+                    hit_count: 0,
+                });
+                self.entries.sort_by_key(|x| x.address);
+            }
+        }
+
+        self.save();
+    }
+
+    // unlike toggle_at an existing breakpoint is kept, it is enabled again
+    pub fn add(&mut self, address: u16) {
+        match self.entries.iter_mut().find(|x| x.address == address) {
+            Some(breakpoint) => breakpoint.enabled = true,
+            None => {
+                self.entries.push(Breakpoint {
+                    address,
+                    enabled: true,
+                    condition: None,
+                    hit_count: 0,
+                });
+                self.entries.sort_by_key(|x| x.address);
+            }
+        }
+
+        self.save();
+    }
+
+    pub fn toggle_enabled(&mut self, index: usize) {
+        if let Some(breakpoint) = self.entries.get_mut(index) {
+            breakpoint.enabled = !breakpoint.enabled;
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+            self.save();
+        }
+    }
+
+    // returns true and counts the hit if an enabled breakpoint is set at the address and its condition holds
+    pub(super) fn hit(&mut self, address: u16, context: &ConditionContext) -> bool {
+        match self
+            .entries
+            .iter_mut()
+            .find(|x| x.enabled && x.address == address && Breakpoints::condition_holds(x, context))
+        {
+            Some(breakpoint) => {
+                breakpoint.hit_count += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // like hit but without counting, used to search the recorded frames
+    pub(super) fn matches(&self, address: u16, context: &ConditionContext) -> bool {
+        self.entries
+            .iter()
+            .any(|x| x.enabled && x.address == address && Breakpoints::condition_holds(x, context))
+    }
+
+    // an invalid condition breaks, so the breakpoint is not silently ignored
+    fn condition_holds(breakpoint: &Breakpoint, context: &ConditionContext) -> bool {
+        match breakpoint.condition.as_ref().map(|x| Condition::parse(x)) {
+            None => true,
+            Some(Ok(condition)) => condition.evaluate(context),
+            Some(Err(err)) => {
+                warn!("Invalid condition of the breakpoint at {:#05X}: {}", breakpoint.address, err);
+                true
+            }
+        }
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(err) = self.write(path) {
+                warn!("Failed to save breakpoints to {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_at_adds_and_removes() {
+        let mut b = Breakpoints::new();
+
+        b.toggle_at(0x204);
+        b.toggle_at(0x200);
+
+        assert_eq!(b.entries().len(), 2);
+        assert_eq!(b.entries()[0].address, 0x200);
+
+        b.toggle_at(0x200);
+
+        assert_eq!(b.entries().len(), 1);
+        assert_eq!(b.entries()[0].address, 0x204);
+    }
+
+    #[test]
+    fn add_keeps_existing_breakpoints() {
+        let mut b = Breakpoints::new();
+
+        b.add(0x204);
+        b.toggle_enabled(0);
+        b.add(0x204);
+        b.add(0x200);
+
+        assert_eq!(b.entries().len(), 2);
+        assert_eq!(b.entries()[0].address, 0x200);
+        assert!(b.entries()[1].enabled);
+    }
+
+    fn context(registers: &[u8]) -> ConditionContext<'_> {
+        ConditionContext {
+            registers,
+            i: 0,
+            pc: 0x200,
+            delay_timer: 0,
+            sound_timer: 0,
+        }
+    }
+
+    #[test]
+    fn hit_counts_enabled_only() {
+        let mut b = Breakpoints::new();
+        b.toggle_at(0x200);
+
+        assert!(b.hit(0x200, &context(&[0; 16])));
+        assert!(!b.hit(0x202, &context(&[0; 16])));
+        assert_eq!(b.entries()[0].hit_count, 1);
+
+        b.toggle_enabled(0);
+
+        assert!(!b.hit(0x200, &context(&[0; 16])));
+        assert_eq!(b.entries()[0].hit_count, 1);
+    }
+
+    #[test]
+    fn hit_requires_condition() {
+        let mut b = Breakpoints::new();
+        b.toggle_at(0x200);
+        b.entries[0].condition = Some(String::from("V3 == 0x1F"));
+
+        let mut registers = [0u8; 16];
+        assert!(!b.hit(0x200, &context(&registers)));
+
+        registers[3] = 0x1F;
+        assert!(b.hit(0x200, &context(&registers)));
+        assert_eq!(b.entries()[0].hit_count, 1);
+    }
+}
@@ -0,0 +1,110 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchFilter {
+    Increased,
+    Decreased,
+    Unchanged,
+    Changed,
+    Equals(u8),
+}
+
+// narrows down the addresses of variables like score or lives by comparing memory snapshots
+pub struct CheatSearch {
+    previous: Vec<u8>,
+    candidates: Vec<u16>,
+}
+
+impl CheatSearch {
+    pub fn new() -> CheatSearch {
+        CheatSearch {
+            previous: Vec::new(),
+            candidates: Vec::new(),
+        }
+    }
+
+    pub fn start(&mut self, memory: &[u8]) {
+        self.previous = memory.to_vec();
+        self.candidates = (0..memory.len()).map(|x| x as u16).collect();
+    }
+
+    // keeps the candidates matching the filter compared to the previous snapshot
+    // the search starts over when the memory size changed, e.g. after another ROM was loaded
+    pub fn filter(&mut self, memory: &[u8], filter: SearchFilter) {
+        if memory.len() != self.previous.len() {
+            self.start(memory);
+            return;
+        }
+
+        let previous = &self.previous;
+
+        self.candidates.retain(|address| {
+            let old = previous[*address as usize];
+            let new = memory[*address as usize];
+
+            match filter {
+                SearchFilter::Increased => new > old,
+                SearchFilter::Decreased => new < old,
+                SearchFilter::Unchanged => new == old,
+                SearchFilter::Changed => new != old,
+                SearchFilter::Equals(value) => new == value,
+            }
+        });
+
+        self.previous = memory.to_vec();
+    }
+
+    pub fn is_started(&self) -> bool {
+        self.previous.len() > 0
+    }
+
+    pub fn candidates(&self) -> &[u16] {
+        &self.candidates
+    }
+
+    // value of an address in the most recent snapshot
+    pub fn value(&self, address: u16) -> u8 {
+        self.previous[address as usize]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(SearchFilter::Increased, &[2]    ; "increased")]
+    #[test_case(SearchFilter::Decreased, &[1]    ; "decreased")]
+    #[test_case(SearchFilter::Unchanged, &[0, 3] ; "unchanged")]
+    #[test_case(SearchFilter::Changed,   &[1, 2] ; "changed")]
+    #[test_case(SearchFilter::Equals(5), &[2, 3] ; "equals")]
+    fn filter_keeps_matching_addresses(filter: SearchFilter, expected: &[u16]) {
+        let mut search = CheatSearch::new();
+
+        search.start(&[0, 3, 4, 5]);
+        search.filter(&[0, 2, 5, 5], filter);
+
+        assert_eq!(search.candidates(), expected);
+    }
+
+    #[test]
+    fn filter_compares_against_last_snapshot() {
+        let mut search = CheatSearch::new();
+
+        search.start(&[3, 3]);
+        search.filter(&[2, 3], SearchFilter::Changed);
+        search.filter(&[1, 2], SearchFilter::Decreased);
+
+        assert_eq!(search.candidates(), &[0]);
+        assert_eq!(search.value(0), 1);
+    }
+
+    #[test]
+    fn filter_starts_over_when_the_memory_size_changed() {
+        let mut search = CheatSearch::new();
+
+        search.start(&[0; 8]);
+        search.filter(&[1, 2], SearchFilter::Changed);
+
+        assert_eq!(search.candidates(), &[0, 1]);
+        assert_eq!(search.value(1), 2);
+    }
+}
@@ -0,0 +1,140 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Cheat {
+    #[serde(default)]
+    pub name: String,
+    pub address: u16,
+    pub value: u8,
+    // frozen cheats are written after every cycle, the others are patched once when they are enabled
+    #[serde(default)]
+    pub frozen: bool,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    #[serde(skip)]
+    applied: bool,
+}
+
+impl Cheat {
+    #[cfg(test)]
+    pub fn new(name: &str, address: u16, value: u8, frozen: bool) -> Cheat {
+        Cheat {
+            name: String::from(name),
+            address,
+            value,
+            frozen,
+            enabled: true,
+            applied: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Cheats {
+    entries: Vec<Cheat>,
+}
+
+impl Cheats {
+    pub fn new(entries: Vec<Cheat>) -> Cheats {
+        Cheats { entries }
+    }
+
+    // called by the VM once the size of its memory is known
+    pub(super) fn validate(&self, memory_size: usize) {
+        for cheat in self.entries.iter() {
+            if cheat.address as usize >= memory_size {
+                warn!("Cheat {} address {:#05X} is out of memory and is ignored", cheat.name, cheat.address);
+            }
+        }
+    }
+
+    // enabling a patch applies it again on the next cycle, disabling it does not restore the memory
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(cheat) = self.entries.get_mut(index) {
+            cheat.enabled = enabled;
+            cheat.applied = false;
+        }
+    }
+
+    pub(super) fn apply(&mut self, memory: &mut [u8]) {
+        for cheat in self.entries.iter_mut().filter(|x| x.enabled) {
+            if cheat.frozen || cheat.applied == false {
+                if let Some(byte) = memory.get_mut(cheat.address as usize) {
+                    *byte = cheat.value;
+                }
+
+                cheat.applied = true;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cheats() -> Cheats {
+        Cheats::new(vec![
+            Cheat::new("lives", 0x300, 9, false),
+            Cheat::new("health", 0x301, 3, true),
+            Cheat::new("invalid", 0xFFFF, 1, true),
+        ])
+    }
+
+    #[test]
+    fn patch_is_applied_once() {
+        let mut cheats = cheats();
+        let mut memory = [0u8; 0x1000];
+
+        cheats.apply(&mut memory);
+
+        assert_eq!(memory[0x300], 9);
+        assert_eq!(memory[0x301], 3);
+
+        memory[0x300] = 0;
+        memory[0x301] = 0;
+        cheats.apply(&mut memory);
+
+        assert_eq!(memory[0x300], 0);
+        assert_eq!(memory[0x301], 3);
+    }
+
+    #[test]
+    fn disabled_cheats_are_not_applied() {
+        let mut cheats = cheats();
+        let mut memory = [0u8; 0x1000];
+
+        cheats.set_enabled(0, false);
+        cheats.set_enabled(1, false);
+        cheats.apply(&mut memory);
+
+        assert_eq!(memory[0x300], 0);
+        assert_eq!(memory[0x301], 0);
+
+        cheats.set_enabled(0, true);
+        cheats.apply(&mut memory);
+
+        assert_eq!(memory[0x300], 9);
+    }
+
+    #[test]
+    fn addresses_are_checked_against_the_memory_size() {
+        let mut cheats = Cheats::new(vec![Cheat::new("bank", 0x1000, 5, true)]);
+
+        let mut memory = vec![0u8; 0x10000];
+        cheats.validate(memory.len());
+        cheats.apply(&mut memory);
+        assert_eq!(memory[0x1000], 5);
+
+        let mut memory = vec![0u8; 0x1000];
+        cheats.validate(memory.len());
+        cheats.apply(&mut memory);
+        assert!(memory.iter().all(|x| *x == 0));
+    }
+}
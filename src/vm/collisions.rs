@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+const MAX_COLLISIONS: usize = 256;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Collision {
+    // number of 60hz frames since the VM started
+    pub frame: u64,
+    pub x: u8,
+    pub y: u8,
+    pub sprite: u16,
+    pub address: u16,
+}
+
+impl fmt::Display for Collision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "frame {:>6}  pos ({:>2}, {:>2})  sprite {:#05X}  PC {:#05X}",
+            self.frame, self.x, self.y, self.sprite, self.address
+        )
+    }
+}
+
+// most recent DXYN draws which set VF, the oldest ones are dropped
+pub struct CollisionLog {
+    entries: VecDeque<Collision>,
+}
+
+impl CollisionLog {
+    pub fn new() -> CollisionLog {
+        CollisionLog {
+            entries: VecDeque::with_capacity(MAX_COLLISIONS),
+        }
+    }
+
+    pub fn record(&mut self, collision: Collision) {
+        if self.entries.len() == MAX_COLLISIONS {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(collision);
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &Collision> {
+        self.entries.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn collision(frame: u64) -> Collision {
+        Collision {
+            frame,
+            x: 0,
+            y: 0,
+            sprite: 0x200,
+            address: 0x200,
+        }
+    }
+
+    #[test]
+    fn record_drops_oldest_collision() {
+        let mut log = CollisionLog::new();
+
+        for frame in 0..MAX_COLLISIONS as u64 + 1 {
+            log.record(collision(frame));
+        }
+
+        assert_eq!(log.entries().count(), MAX_COLLISIONS);
+        assert_eq!(log.entries().next(), Some(&collision(1)));
+    }
+}
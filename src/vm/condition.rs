@@ -0,0 +1,260 @@
+// values a condition can refer to, taken from the frame before the instruction at PC executes
+pub struct ConditionContext<'a> {
+    pub registers: &'a [u8],
+    pub i: u16,
+    pub pc: u16,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Operand {
+    Register(usize),
+    I,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+    Value(u16),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Eq,
+    Neq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Compare(Operand, Comparison, Operand),
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+}
+
+// comparisons combined with && and ||, && binds stronger, parentheses group
+#[derive(Debug, Clone, PartialEq)]
+pub struct Condition(Expression);
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Operand(Operand),
+    Comparison(Comparison),
+    And,
+    Or,
+    Open,
+    Close,
+}
+
+impl Operand {
+    fn parse(word: &str) -> Result<Operand, String> {
+        let upper = word.to_uppercase();
+
+        let operand = match upper.as_str() {
+            "I" => Operand::I,
+            "PC" => Operand::Pc,
+            "DT" => Operand::DelayTimer,
+            "ST" => Operand::SoundTimer,
+            _ if upper.starts_with("0X") => {
+                Operand::Value(u16::from_str_radix(&upper[2..], 16).map_err(|_| format!("{} is not a number", word))?)
+            }
+            _ if upper.starts_with('V') && upper.len() == 2 => Operand::Register(
+                usize::from_str_radix(&upper[1..], 16).map_err(|_| format!("{} is not a register", word))?,
+            ),
+            _ => Operand::Value(upper.parse().map_err(|_| format!("{} is not a register or number", word))?),
+        };
+
+        Ok(operand)
+    }
+
+    fn value(self, context: &ConditionContext) -> u16 {
+        match self {
+            Operand::Register(x) => context.registers.get(x).copied().unwrap_or(0) as u16,
+            Operand::I => context.i,
+            Operand::Pc => context.pc,
+            Operand::DelayTimer => context.delay_timer as u16,
+            Operand::SoundTimer => context.sound_timer as u16,
+            Operand::Value(x) => x,
+        }
+    }
+}
+
+impl Comparison {
+    fn matches(self, left: u16, right: u16) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Neq => left != right,
+            Comparison::Lt => left < right,
+            Comparison::Le => left <= right,
+            Comparison::Gt => left > right,
+            Comparison::Ge => left >= right,
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+
+        let (token, len) = match two.as_str() {
+            "&&" => (Token::And, 2),
+            "||" => (Token::Or, 2),
+            "==" => (Token::Comparison(Comparison::Eq), 2),
+            "!=" => (Token::Comparison(Comparison::Neq), 2),
+            "<=" => (Token::Comparison(Comparison::Le), 2),
+            ">=" => (Token::Comparison(Comparison::Ge), 2),
+            _ => match chars[i] {
+                x if x.is_whitespace() => {
+                    i += 1;
+                    continue;
+                }
+                '<' => (Token::Comparison(Comparison::Lt), 1),
+                '>' => (Token::Comparison(Comparison::Gt), 1),
+                '(' => (Token::Open, 1),
+                ')' => (Token::Close, 1),
+                x if x.is_ascii_alphanumeric() => {
+                    let word: String = chars[i..].iter().take_while(|x| x.is_ascii_alphanumeric()).collect();
+                    (Token::Operand(Operand::parse(&word)?), word.len())
+                }
+                x => return Err(format!("unexpected {}", x)),
+            },
+        };
+
+        tokens.push(token);
+        i += len;
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn or(&mut self) -> Result<Expression, String> {
+        let mut condition = self.and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            condition = Expression::Or(Box::new(condition), Box::new(self.and()?));
+        }
+
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Expression, String> {
+        let mut condition = self.comparison()?;
+        while self.peek() == Some(&Token::And) {
+            self.next();
+            condition = Expression::And(Box::new(condition), Box::new(self.comparison()?));
+        }
+
+        Ok(condition)
+    }
+
+    fn comparison(&mut self) -> Result<Expression, String> {
+        match self.next() {
+            Some(Token::Open) => {
+                let condition = self.or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(condition),
+                    _ => Err(String::from("missing )")),
+                }
+            }
+            Some(Token::Operand(left)) => match (self.next(), self.next()) {
+                (Some(Token::Comparison(comparison)), Some(Token::Operand(right))) => {
+                    Ok(Expression::Compare(left, comparison, right))
+                }
+                _ => Err(String::from("expected a comparison like V3 == 0x1F")),
+            },
+            _ => Err(String::from("expected a register, I, PC, DT, ST or (")),
+        }
+    }
+}
+
+impl Condition {
+    // e.g. V3 == 0x1F && (PC == 0x2A4 || I >= 0x300)
+    pub fn parse(text: &str) -> Result<Condition, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+
+        let expression = parser.or()?;
+        match parser.peek() {
+            None => Ok(Condition(expression)),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    pub fn evaluate(&self, context: &ConditionContext) -> bool {
+        self.0.evaluate(context)
+    }
+}
+
+impl Expression {
+    fn evaluate(&self, context: &ConditionContext) -> bool {
+        match self {
+            Expression::Compare(left, comparison, right) => comparison.matches(left.value(context), right.value(context)),
+            Expression::And(left, right) => left.evaluate(context) && right.evaluate(context),
+            Expression::Or(left, right) => left.evaluate(context) || right.evaluate(context),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context(registers: &[u8]) -> ConditionContext<'_> {
+        ConditionContext {
+            registers,
+            i: 0x300,
+            pc: 0x2A4,
+            delay_timer: 0,
+            sound_timer: 5,
+        }
+    }
+
+    #[test]
+    fn evaluate_combines_comparisons() {
+        let mut registers = [0u8; 16];
+        registers[3] = 0x1F;
+
+        let condition = Condition::parse("V3 == 0x1F && PC == 0x2A4").unwrap();
+        assert!(condition.evaluate(&context(&registers)));
+
+        let condition = Condition::parse("v3 != 31 || (I < 0x300 && ST > 0)").unwrap();
+        assert_eq!(condition.evaluate(&context(&registers)), false);
+
+        let condition = Condition::parse("VF == 1 || I >= 0x300 && DT == 0").unwrap();
+        assert!(condition.evaluate(&context(&registers)));
+    }
+
+    #[test]
+    fn parse_rejects_invalid_conditions() {
+        assert!(Condition::parse("").is_err());
+        assert!(Condition::parse("V3 ==").is_err());
+        assert!(Condition::parse("VG == 1").is_err());
+        assert!(Condition::parse("(V3 == 1").is_err());
+        assert!(Condition::parse("V3 == 1 V4").is_err());
+        assert!(Condition::parse("V3 = 1").is_err());
+    }
+}
@@ -4,12 +4,184 @@ use serde::{Deserialize, Serialize};
 
 use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use log::{LevelFilter, warn};
+use log::{LevelFilter, info, warn};
 use anyhow::Result;
+use strum_macros::{Display, EnumString};
+
+use super::cheats::Cheat;
+use super::octocart::{self, Octocart};
+use super::constants::{MEMORY_SIZE, PC_START, STACK_DEPTH, VM_RESERVED_END};
+use super::platform::{self, Platform};
+use super::quirks::{self, Quirks};
+use super::rom_database::RomDatabase;
+use super::variant::Variant;
+use super::watchpoints::Watchpoint;
+
+fn default_toggle_breakpoint_key() -> KeyCode {
+    KeyCode::F9
+}
+
+fn default_breakpoint_panel_key() -> KeyCode {
+    KeyCode::F10
+}
+
+fn default_pause_menu_key() -> KeyCode {
+    KeyCode::Escape
+}
+
+fn default_magnifier_key() -> KeyCode {
+    KeyCode::M
+}
+
+fn default_cheats_key() -> KeyCode {
+    KeyCode::F8
+}
+
+fn default_speed_multiplier() -> f32 {
+    1.
+}
+
+fn default_use_rom_database() -> bool {
+    true
+}
+
+fn default_telemetry_key() -> KeyCode {
+    KeyCode::F11
+}
+
+fn default_print_collisions_key() -> KeyCode {
+    KeyCode::F12
+}
+
+fn default_sprite_outlines_key() -> KeyCode {
+    KeyCode::O
+}
+
+fn default_export_sprite_key() -> KeyCode {
+    KeyCode::P
+}
+
+fn default_load_address() -> u16 {
+    PC_START
+}
+
+fn default_reserved_end() -> u16 {
+    VM_RESERVED_END as u16
+}
+
+fn default_detect_quirks() -> bool {
+    true
+}
+
+fn default_stack_depth() -> usize {
+    STACK_DEPTH
+}
+
+fn default_power_saving() -> bool {
+    true
+}
+
+fn default_continue_execution_key() -> KeyCode {
+    KeyCode::G
+}
+
+fn default_speedrun_timer_key() -> KeyCode {
+    KeyCode::K
+}
+
+fn default_split_key() -> KeyCode {
+    KeyCode::L
+}
+
+fn default_trace_key() -> KeyCode {
+    KeyCode::Y
+}
+
+fn default_heatmap_key() -> KeyCode {
+    KeyCode::Q
+}
+
+fn default_save_state_key() -> KeyCode {
+    KeyCode::Comma
+}
+
+fn default_load_state_key() -> KeyCode {
+    KeyCode::Period
+}
+
+fn default_debugger_panel_key() -> KeyCode {
+    KeyCode::Grave
+}
+
+fn default_print_memory_key() -> KeyCode {
+    KeyCode::I
+}
+
+fn default_run_until_return_key() -> KeyCode {
+    KeyCode::U
+}
+
+fn default_print_profile_key() -> KeyCode {
+    KeyCode::Z
+}
+
+fn default_export_history_key() -> KeyCode {
+    KeyCode::H
+}
+
+fn default_import_history_key() -> KeyCode {
+    KeyCode::J
+}
+
+fn default_state_slot_keys() -> Vec<KeyCode> {
+    vec![
+        KeyCode::Key1,
+        KeyCode::Key2,
+        KeyCode::Key3,
+        KeyCode::Key4,
+        KeyCode::Key5,
+        KeyCode::Key6,
+        KeyCode::Key7,
+        KeyCode::Key8,
+        KeyCode::Key9,
+    ]
+}
+
+fn default_rewind_seconds() -> u32 {
+    5
+}
+
+fn default_rewind_key() -> KeyCode {
+    KeyCode::Back
+}
+
+fn default_fast_forward_multiplier() -> f32 {
+    4.
+}
+
+fn default_fast_forward_key() -> KeyCode {
+    KeyCode::Tab
+}
+
+fn default_frame_advance_key() -> KeyCode {
+    KeyCode::N
+}
+
+fn default_pause_key() -> KeyCode {
+    KeyCode::Pause
+}
 
 pub type KeyMapping = HashMap<KeyCode, u8>;
+pub type MacroMapping = HashMap<KeyCode, Vec<MacroStep>>;
+
+// holds the CHIP-8 keys for a number of frames, no keys releases them
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroStep {
+    pub keys: Vec<u8>,
+    pub frames: u32,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DebuggerKeyMapping {
@@ -19,29 +191,329 @@ pub struct DebuggerKeyMapping {
     pub print_registers: KeyCode,
     pub print_stack: KeyCode,
     pub print_timers: KeyCode,
+    #[serde(default = "default_toggle_breakpoint_key")]
+    pub toggle_breakpoint: KeyCode,
+    #[serde(default = "default_breakpoint_panel_key")]
+    pub breakpoint_panel: KeyCode,
+    #[serde(default = "default_print_collisions_key")]
+    pub print_collisions: KeyCode,
+    #[serde(default = "default_continue_execution_key")]
+    pub continue_execution: KeyCode,
+    #[serde(default = "default_debugger_panel_key")]
+    pub debugger_panel: KeyCode,
+    #[serde(default = "default_print_memory_key")]
+    pub print_memory: KeyCode,
+    #[serde(default = "default_run_until_return_key")]
+    pub run_until_return: KeyCode,
+    #[serde(default = "default_print_profile_key")]
+    pub print_profile: KeyCode,
+    #[serde(default = "default_export_history_key")]
+    pub export_history: KeyCode,
+    #[serde(default = "default_import_history_key")]
+    pub import_history: KeyCode,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DebuggerConfig {
     pub enable: bool,
+    // halts before the first instruction, enables the debugger
+    #[serde(default)]
+    pub break_on_start: bool,
+    // records every DXYN draw which sets VF
+    #[serde(default)]
+    pub log_collisions: bool,
+    // logs writes into addresses which were already executed
+    #[serde(default)]
+    pub log_self_modification: bool,
+    // set in addition to the persisted breakpoints of the ROM, enables the debugger
+    #[serde(default)]
+    pub breakpoints: Vec<u16>,
+    // memory accesses which break execution, only checked while the debugger is enabled
+    #[serde(default)]
+    pub watchpoints: Vec<Watchpoint>,
+    // conditions over the registers, I, PC and the timers, e.g. V3 == 0x1F && PC == 0x2A4, enable the debugger
+    #[serde(default)]
+    pub break_conditions: Vec<String>,
+    // opcode classes, e.g. Disp, Rand or Flow_Call, which break whenever they execute, enable the debugger
+    #[serde(default)]
+    pub break_on_opcodes: Vec<String>,
+    // registers, I, PC, timers or memory like [0x300:4] printed whenever the debugger halts or steps
+    #[serde(default)]
+    pub watches: Vec<String>,
+    // address like 127.0.0.1:6502 external tools connect to, enables the debugger
+    #[serde(default)]
+    pub server: Option<String>,
+    // labels of the ROM, the ROM path with a .sym extension is used if it is not set
+    #[serde(default)]
+    pub symbols: Option<String>,
     pub key_mapping: DebuggerKeyMapping,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneralKeyMapping {
     pub restart_vm: KeyCode,
+    #[serde(default = "default_pause_menu_key")]
+    pub pause_menu: KeyCode,
+    #[serde(default = "default_magnifier_key")]
+    pub magnifier: KeyCode,
+    #[serde(default = "default_cheats_key")]
+    pub cheats: KeyCode,
+    #[serde(default = "default_telemetry_key")]
+    pub telemetry: KeyCode,
+    #[serde(default = "default_sprite_outlines_key")]
+    pub sprite_outlines: KeyCode,
+    #[serde(default = "default_export_sprite_key")]
+    pub export_sprite: KeyCode,
+    #[serde(default = "default_speedrun_timer_key")]
+    pub speedrun_timer: KeyCode,
+    #[serde(default = "default_split_key")]
+    pub split: KeyCode,
+    #[serde(default = "default_save_state_key")]
+    pub save_state: KeyCode,
+    #[serde(default = "default_load_state_key")]
+    pub load_state: KeyCode,
+    // pauses and resumes the VM without opening the pause menu
+    #[serde(default = "default_pause_key")]
+    pub pause: KeyCode,
+    // pauses and then runs a single frame per press, with shift it resumes
+    #[serde(default = "default_frame_advance_key")]
+    pub frame_advance: KeyCode,
+    // held to run faster
+    #[serde(default = "default_fast_forward_key")]
+    pub fast_forward: KeyCode,
+    // held to rewind
+    #[serde(default = "default_rewind_key")]
+    pub rewind: KeyCode,
+    // starts and stops writing the instruction trace
+    #[serde(default = "default_trace_key")]
+    pub trace: KeyCode,
+    // shows the memory heatmap, with shift it is exported as CSV
+    #[serde(default = "default_heatmap_key")]
+    pub heatmap: KeyCode,
+    // one key per numbered save state slot, shift saves and ctrl loads the slot
+    #[serde(default = "default_state_slot_keys")]
+    pub state_slots: Vec<KeyCode>,
+}
+
+// how the instructions are timed, Spread runs them evenly at hz, Frame runs hz / 60 of them at the start of every 60hz frame
+#[derive(Debug, Clone, Copy, PartialEq, Display, EnumString, Serialize, Deserialize)]
+pub enum Scheduler {
+    Spread,
+    Frame,
+}
+
+impl Default for Scheduler {
+    fn default() -> Scheduler {
+        Scheduler::Spread
+    }
+}
+
+// what happens when the ROM writes below its load address where the font and the interpreter live
+#[derive(Debug, Clone, Copy, PartialEq, Display, EnumString, Serialize, Deserialize)]
+pub enum WriteProtection {
+    Off,
+    // stops the VM with an error
+    Error,
+    // logs the write and breaks into the debugger if it is enabled
+    Break,
+}
+
+impl Default for WriteProtection {
+    fn default() -> WriteProtection {
+        WriteProtection::Off
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Display, EnumString, Serialize, Deserialize)]
+pub enum Palette {
+    Classic,
+    HighContrast,
+    Deuteranopia,
+    Protanopia,
+}
+
+impl Default for Palette {
+    fn default() -> Palette {
+        Palette::Classic
+    }
+}
+
+impl Palette {
+    pub fn next(self) -> Palette {
+        match self {
+            Palette::Classic => Palette::HighContrast,
+            Palette::HighContrast => Palette::Deuteranopia,
+            Palette::Deuteranopia => Palette::Protanopia,
+            Palette::Protanopia => Palette::Classic,
+        }
+    }
+}
+
+// per-ROM overrides of the global settings, unset values fall back to the global ones
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RomSettings {
+    pub hz: Option<u128>,
+    // preset of the platform, quirks and hz which are not set
+    #[serde(default)]
+    pub variant: Option<Variant>,
+    // skips the detection of the platform
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    // skips the ROM database and the detection of the quirks
+    #[serde(default)]
+    pub quirks: Option<Quirks>,
+    #[serde(default)]
+    pub load_address: Option<u16>,
+    // the file is a dump of the whole memory including the interpreter area instead of a ROM
+    #[serde(default)]
+    pub memory_image: bool,
+    pub frame_blending: Option<bool>,
+    #[serde(default)]
+    pub colors: Option<RomColors>,
+    // IPS patch applied to the ROM, <rom>.ips is used if it is not set
+    pub ips: Option<String>,
+    #[serde(default)]
+    pub cheats: Vec<Cheat>,
+}
+
+// #RRGGBB colors which replace the background and the first plane of every palette
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RomColors {
+    pub background: String,
+    pub foreground: String,
+}
+
+// runs every ROM in the ROM directory for the given number of seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttractModeConfig {
+    pub enable: bool,
+    pub seconds: u32,
+}
+
+impl Default for AttractModeConfig {
+    fn default() -> AttractModeConfig {
+        AttractModeConfig {
+            enable: false,
+            seconds: 30,
+        }
+    }
+}
+
+// input movies, record and play are paths to movie files, only one of them should be set
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MovieConfig {
+    pub record: Option<String>,
+    pub play: Option<String>,
+    pub author: String,
+    pub comment: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub hz: u128,
+    #[serde(default)]
+    pub scheduler: Scheduler,
+    #[serde(default = "default_use_rom_database")]
+    pub use_rom_database: bool,
+    // platform of ROMs which are neither detected nor in the ROM database
+    #[serde(default)]
+    pub platform: Platform,
+    // preset of the platform, quirks and hz of every ROM, ROM settings and the ROM variant win over it
+    #[serde(default)]
+    pub variant: Option<Variant>,
+    // quirks of ROMs which are not in the ROM database, detected quirks are set on top of them
+    #[serde(default)]
+    pub quirks: Quirks,
+    #[serde(default = "default_detect_quirks")]
+    pub detect_quirks: bool,
+    // address the ROM is loaded to and started at
+    #[serde(default = "default_load_address")]
+    pub load_address: u16,
+    // address of the built-in font which FX29 points I to
+    #[serde(default)]
+    pub font_address: u16,
+    // binary or .hex file which replaces the built-in font
+    #[serde(default)]
+    pub font_file: Option<String>,
+    // size of the memory in bytes, None uses the size of the platform
+    #[serde(default)]
+    pub memory_size: Option<usize>,
+    // nesting depth of subroutine calls, deeper calls stop the VM with a stack overflow
+    #[serde(default = "default_stack_depth")]
+    pub stack_depth: usize,
+    // seed of the random numbers, the same seed generates the same numbers in every run, None seeds randomly
+    #[serde(default)]
+    pub seed: Option<u64>,
+    // traps writes below the load address
+    #[serde(default)]
+    pub write_protection: WriteProtection,
+    // size of the area at the end of the memory which was used by the interpreter
+    #[serde(default = "default_reserved_end")]
+    pub reserved_end: u16,
+    // address of a virtual serial port, the bytes written to it are logged as text
+    #[serde(default)]
+    pub serial_port: Option<u16>,
+    // command which compiles the Octo source of Octocarts, called with the source and the output path
+    #[serde(default)]
+    pub octo_compiler: Option<String>,
+    #[serde(default = "default_speed_multiplier")]
+    pub speed_multiplier: f32,
+    // applied on top of the speed multiplier while the fast forward key is held
+    #[serde(default = "default_fast_forward_multiplier")]
+    pub fast_forward_multiplier: f32,
     pub beep_frequency: f64,
+    // name of the output device the beep plays on, None plays on the default device
+    #[serde(default)]
+    pub audio_device: Option<String>,
     pub screen_scaling: f32,
+    #[serde(default)]
+    pub palette: Palette,
+    #[serde(default)]
+    pub frame_blending: bool,
+    // flashes the pixels erased by a collision
+    #[serde(default)]
+    pub highlight_collisions: bool,
+    // renders at a few fps while nothing on the screen changes
+    #[serde(default = "default_power_saving")]
+    pub power_saving: bool,
+    // number of frames which are not rendered after each rendered frame, the VM keeps running at full speed
+    #[serde(default)]
+    pub frame_skip: u8,
+    // saves the state of the VM when quitting and restores it when the ROM is started again
+    #[serde(default)]
+    pub resume_session: bool,
+    // seconds of gameplay which can be rewound, 0 disables rewinding
+    #[serde(default = "default_rewind_seconds")]
+    pub rewind_seconds: u32,
+    // writes every executed instruction to data/<rom>.trace from the start
+    #[serde(default)]
+    pub trace: bool,
+    // counts the executed instructions per address and opcode, the most executed ones are logged when the emulator quits
+    #[serde(default)]
+    pub profile: bool,
+    // counts the executed opcode variants, the covered and the never executed ones are logged when the emulator quits
+    #[serde(default)]
+    pub coverage: bool,
+    // counts the reads, writes and instruction fetches per memory address from the start instead of when the heatmap is opened
+    #[serde(default)]
+    pub heatmap: bool,
     pub rom: String,
+    #[serde(default)]
+    pub mute: bool,
     pub general_key_mapping: GeneralKeyMapping,
     pub default_key_mapping: KeyMapping,
     pub rom_key_mappings: HashMap<String, KeyMapping>,
+    #[serde(default)]
+    pub macros: MacroMapping,
+    #[serde(default)]
+    pub rom_macros: HashMap<String, MacroMapping>,
+    #[serde(default)]
+    pub rom_settings: HashMap<String, RomSettings>,
     pub debugger: DebuggerConfig,
+    #[serde(default)]
+    pub movie: MovieConfig,
+    #[serde(default)]
+    pub attract_mode: AttractModeConfig,
     pub log_level: LevelFilter,
 }
 
@@ -50,7 +522,16 @@ impl Config {
         const PATH: &'static str = "config.yml";
 
         match fs::read_to_string(PATH) {
-            Ok(yaml) => Ok(serde_yaml::from_str::<Config>(&yaml)?),
+            Ok(yaml) => {
+                let mut config = serde_yaml::from_str::<Config>(&yaml)?;
+                config.debugger.enable |= config.debugger.break_on_start
+                    || config.debugger.breakpoints.is_empty() == false
+                    || config.debugger.break_conditions.is_empty() == false
+                    || config.debugger.break_on_opcodes.is_empty() == false
+                    || config.debugger.server.is_some();
+
+                Ok(config)
+            }
             Err(..) => {
                 let config = Config::default();
                 let yaml = serde_yaml::to_string(&config)?;
@@ -69,6 +550,210 @@ impl Config {
         self.rom_key_mappings.get(filename)
     }
 
+    // macros of the current ROM take precedence over the global ones bound to the same key
+    pub fn get_macros(&self) -> MacroMapping {
+        let mut macros = self.macros.clone();
+
+        let filename = Path::new(&self.rom).file_name().and_then(|x| x.to_str());
+        if let Some(rom_macros) = filename.and_then(|x| self.rom_macros.get(x)) {
+            macros.extend(rom_macros.clone());
+        }
+
+        macros
+    }
+
+    pub fn get_rom_settings(&self) -> Option<&RomSettings> {
+        let filename = Path::new(&self.rom).file_name()?.to_str()?;
+        self.rom_settings.get(filename)
+    }
+
+    // replaces the global settings with the overrides of the current ROM
+    pub fn apply_rom_settings(&mut self) {
+        if octocart::is_octocart(&self.rom) {
+            self.apply_octocart_options();
+        }
+
+        self.apply_variant();
+
+        if let Some(settings) = self.get_rom_settings().cloned() {
+            if let Some(hz) = settings.hz {
+                self.hz = hz;
+            }
+
+            if let Some(frame_blending) = settings.frame_blending {
+                self.frame_blending = frame_blending;
+            }
+        }
+    }
+
+    // the options embedded in an Octocart fill the ROM settings which are not set in the config
+    fn apply_octocart_options(&mut self) {
+        let cart = match Octocart::load(&self.rom) {
+            Ok(cart) => cart,
+            Err(msg) => {
+                warn!("Ignoring the options of {}: {}", self.rom, msg);
+                return;
+            }
+        };
+
+        let filename = match Path::new(&self.rom).file_name().and_then(|x| x.to_str()) {
+            Some(filename) => String::from(filename),
+            None => return,
+        };
+
+        let options = cart.get_rom_settings();
+        let settings = self.rom_settings.entry(filename).or_default();
+        settings.hz = settings.hz.or(options.hz);
+        settings.quirks = settings.quirks.or(options.quirks);
+        settings.colors = settings.colors.take().or(options.colors);
+    }
+
+    // the preset of the variant fills the ROM settings which are not set, so it wins over the ROM database and the detection
+    fn apply_variant(&mut self) {
+        let variant = match self.get_rom_settings().and_then(|x| x.variant).or(self.variant) {
+            Some(variant) => variant,
+            None => return,
+        };
+
+        let filename = match Path::new(&self.rom).file_name().and_then(|x| x.to_str()) {
+            Some(filename) => String::from(filename),
+            None => return,
+        };
+
+        info!("Using the {} preset", variant);
+
+        let settings = self.rom_settings.entry(filename).or_default();
+        settings.hz = settings.hz.or(Some(variant.hz()));
+        settings.platform = settings.platform.or(Some(variant.platform()));
+        settings.quirks = settings.quirks.or(Some(variant.quirks()));
+    }
+
+    // per-ROM state (breakpoints, etc.) is kept out of the ROM directory
+    pub fn get_rom_data_path(&self, extension: &str) -> PathBuf {
+        Config::get_data_path(&self.rom, extension)
+    }
+
+    pub fn get_data_path(rom: &str, extension: &str) -> PathBuf {
+        let filename = Path::new(rom)
+            .file_name()
+            .and_then(|x| x.to_str())
+            .unwrap_or("unknown");
+
+        Path::new("data").join(format!("{}.{}", filename, extension))
+    }
+
+    // the speed recommended by the ROM database is used unless the ROM settings override it
+    pub fn get_hz(&self, rom: &[u8]) -> u128 {
+        let overridden = self.get_rom_settings().and_then(|x| x.hz).is_some();
+
+        if self.use_rom_database && overridden == false {
+            if let Some(entry) = RomDatabase::load().find(rom) {
+                info!("Using {} cycles per frame recommended for {}", entry.cycles_per_frame, entry.name);
+                return entry.cycles_per_frame as u128 * 60;
+            }
+        }
+
+        self.hz
+    }
+
+    pub fn get_load_address(&self) -> u16 {
+        self.get_rom_settings()
+            .and_then(|x| x.load_address)
+            .unwrap_or(self.load_address)
+    }
+
+    // files with the size of the whole memory cannot be ROMs, so they are always loaded as memory images
+    pub fn is_memory_image(&self, rom: &[u8]) -> bool {
+        rom.len() == MEMORY_SIZE || self.get_rom_settings().map(|x| x.memory_image).unwrap_or(false)
+    }
+
+    // the ROM settings override the detection by extension and opcodes, which is preferred over the ROM database
+    pub fn get_platform(&self, rom: &[u8]) -> Platform {
+        if let Some(platform) = self.get_rom_settings().and_then(|x| x.platform) {
+            return platform;
+        }
+
+        let load_address = self.get_load_address();
+        if let Some(platform) = platform::detect(&self.rom, self.get_code(rom), load_address) {
+            info!("Detected {} ROM", platform);
+            return platform;
+        }
+
+        if self.use_rom_database {
+            if let Some(platform) = RomDatabase::load().find(rom).and_then(|x| x.platform) {
+                return platform;
+            }
+        }
+
+        self.platform
+    }
+
+    // the ROM settings win over the ROM database, which wins over the detection
+    pub fn get_quirks(&self, rom: &[u8]) -> Quirks {
+        if let Some(quirks) = self.get_rom_settings().and_then(|x| x.quirks) {
+            return quirks;
+        }
+
+        if self.use_rom_database {
+            if let Some(quirks) = RomDatabase::load().find(rom).and_then(|x| x.quirks) {
+                return quirks;
+            }
+        }
+
+        if self.detect_quirks {
+            let detected = quirks::detect(self.get_code(rom), self.get_load_address(), self.quirks);
+
+            if detected != self.quirks {
+                info!("Guessed quirks {}, they can be set per ROM in rom_settings", detected);
+            }
+
+            return detected;
+        }
+
+        self.quirks
+    }
+
+    // the code of a memory image starts at the load address as well
+    fn get_code<'a>(&self, rom: &'a [u8]) -> &'a [u8] {
+        match self.is_memory_image(rom) {
+            true => rom.get(self.get_load_address() as usize..).unwrap_or(&[]),
+            false => rom,
+        }
+    }
+
+    pub fn get_rom_settings_mut(&mut self) -> Option<&mut RomSettings> {
+        let filename = Path::new(&self.rom).file_name()?.to_str()?;
+        self.rom_settings.get_mut(filename)
+    }
+
+    pub fn get_rom_cheats(&self) -> Vec<Cheat> {
+        match self.get_rom_settings() {
+            Some(settings) => settings.cheats.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    // steps through the speed multipliers offered in the settings menu
+    pub fn next_speed_multiplier(&self) -> f32 {
+        const MULTIPLIERS: [f32; 5] = [0.25, 0.5, 1., 2., 4.];
+
+        MULTIPLIERS
+            .iter()
+            .find(|x| **x > self.speed_multiplier)
+            .copied()
+            .unwrap_or(MULTIPLIERS[0])
+    }
+
+    // steps through the frame skips offered in the settings menu
+    pub fn next_frame_skip(&self) -> u8 {
+        const MAX_FRAME_SKIP: u8 = 4;
+
+        match self.frame_skip {
+            x if x >= MAX_FRAME_SKIP => 0,
+            x => x + 1,
+        }
+    }
+
     pub fn get_default_key_mapping() -> KeyMapping {
         let mut map = HashMap::with_capacity(16);
         map.insert(KeyCode::Key0, 0);
@@ -96,16 +781,77 @@ impl Default for Config {
     fn default() -> Config {
         Config {
             hz: 60,
+            scheduler: Scheduler::Spread,
+            use_rom_database: true,
+            platform: Platform::Chip8,
+            variant: None,
+            quirks: Quirks::default(),
+            detect_quirks: true,
+            load_address: PC_START,
+            font_address: 0,
+            font_file: None,
+            memory_size: None,
+            stack_depth: STACK_DEPTH,
+            seed: None,
+            write_protection: WriteProtection::Off,
+            reserved_end: VM_RESERVED_END as u16,
+            serial_port: None,
+            octo_compiler: None,
+            speed_multiplier: 1.,
+            fast_forward_multiplier: 4.,
             beep_frequency: 440.,
+            audio_device: None,
             screen_scaling: 20.,
+            palette: Palette::Classic,
+            frame_blending: false,
+            highlight_collisions: false,
+            power_saving: true,
+            frame_skip: 0,
+            resume_session: false,
+            rewind_seconds: 5,
+            trace: false,
+            profile: false,
+            coverage: false,
+            heatmap: false,
             rom: String::from("roms/INVADERS"),
+            mute: false,
             general_key_mapping: GeneralKeyMapping {
                 restart_vm: KeyCode::F5,
+                pause_menu: KeyCode::Escape,
+                magnifier: KeyCode::M,
+                cheats: KeyCode::F8,
+                telemetry: KeyCode::F11,
+                sprite_outlines: KeyCode::O,
+                export_sprite: KeyCode::P,
+                speedrun_timer: KeyCode::K,
+                split: KeyCode::L,
+                save_state: KeyCode::Comma,
+                load_state: KeyCode::Period,
+                pause: KeyCode::Pause,
+                frame_advance: KeyCode::N,
+                fast_forward: KeyCode::Tab,
+                rewind: KeyCode::Back,
+                trace: KeyCode::Y,
+                heatmap: KeyCode::Q,
+                state_slots: default_state_slot_keys(),
             },
             default_key_mapping: Config::get_default_key_mapping(),
             rom_key_mappings: HashMap::<String, KeyMapping>::new(),
+            macros: MacroMapping::new(),
+            rom_macros: HashMap::<String, MacroMapping>::new(),
+            rom_settings: HashMap::<String, RomSettings>::new(),
             debugger: DebuggerConfig {
                 enable: false,
+                break_on_start: false,
+                log_collisions: false,
+                log_self_modification: false,
+                breakpoints: Vec::new(),
+                watchpoints: Vec::new(),
+                break_conditions: Vec::new(),
+                break_on_opcodes: Vec::new(),
+                watches: Vec::new(),
+                server: None,
+                symbols: None,
                 key_mapping: DebuggerKeyMapping {
                     toggle_break: KeyCode::F1,
                     step_previous: KeyCode::F2,
@@ -113,8 +859,20 @@ impl Default for Config {
                     print_registers: KeyCode::F4,
                     print_stack: KeyCode::F6,
                     print_timers: KeyCode::F7,
+                    toggle_breakpoint: KeyCode::F9,
+                    breakpoint_panel: KeyCode::F10,
+                    print_collisions: KeyCode::F12,
+                    continue_execution: KeyCode::G,
+                    debugger_panel: KeyCode::Grave,
+                    print_memory: KeyCode::I,
+                    run_until_return: KeyCode::U,
+                    print_profile: KeyCode::Z,
+                    export_history: KeyCode::H,
+                    import_history: KeyCode::J,
                 },
             },
+            movie: MovieConfig::default(),
+            attract_mode: AttractModeConfig::default(),
             log_level: LevelFilter::Trace
         }
     }
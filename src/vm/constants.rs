@@ -2,6 +2,11 @@ pub const SCREEN_SIZE_X: usize = 64;
 pub const SCREEN_SIZE_Y: usize = 32;
 pub const SCREEN_SIZE: usize = SCREEN_SIZE_X * SCREEN_SIZE_Y;
 
+// SUPER-CHIP hi-res mode
+pub const HIRES_SCREEN_SIZE_X: usize = 128;
+pub const HIRES_SCREEN_SIZE_Y: usize = 64;
+pub const HIRES_SCREEN_SIZE: usize = HIRES_SCREEN_SIZE_X * HIRES_SCREEN_SIZE_Y;
+
 pub const FONT_SYMBOL_SIZE: usize = 5;
 pub const FONTS: [u8; 80] = [
     0xF0, 0x90, 0x90, 0x90, 0xF0, // 0
@@ -22,20 +27,43 @@ pub const FONTS: [u8; 80] = [
     0xF0, 0x80, 0xF0, 0x80, 0x80  // F
 ];
 
+// SUPER-CHIP 8x10 digits, stored right after FONTS
+pub const BIG_FONT_SYMBOL_SIZE: usize = 10;
+pub const BIG_FONTS: [u8; 100] = [
+    0x3C, 0x7E, 0xE7, 0xC3, 0xC3, 0xC3, 0xC3, 0xE7, 0x7E, 0x3C, // 0
+    0x18, 0x38, 0x58, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x3C, // 1
+    0x3E, 0x7F, 0xC3, 0x06, 0x0C, 0x18, 0x30, 0x60, 0xFF, 0xFF, // 2
+    0x3C, 0x7E, 0xC3, 0x03, 0x0E, 0x0E, 0x03, 0xC3, 0x7E, 0x3C, // 3
+    0x06, 0x0E, 0x1E, 0x36, 0x66, 0xC6, 0xFF, 0xFF, 0x06, 0x06, // 4
+    0xFF, 0xFF, 0xC0, 0xC0, 0xFC, 0xFE, 0x03, 0xC3, 0x7E, 0x3C, // 5
+    0x3E, 0x7C, 0xC0, 0xC0, 0xFC, 0xFE, 0xC3, 0xC3, 0x7E, 0x3C, // 6
+    0xFF, 0xFF, 0x03, 0x06, 0x0C, 0x18, 0x30, 0x60, 0x60, 0x60, // 7
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7E, 0x7E, 0xC3, 0xC3, 0x7E, 0x3C, // 8
+    0x3C, 0x7E, 0xC3, 0xC3, 0x7F, 0x3F, 0x03, 0x03, 0x3E, 0x7C  // 9
+];
+
+// subroutine nesting of the COSMAC VIP interpreter
+pub(super) const STACK_DEPTH: usize = 16;
+
+// SUPER-CHIP user flags saved by FX75 and restored by FX85
+pub(super) const RPL_FLAG_COUNT: usize = 8;
+
 pub(super) const VM_ORIGINAL_HZ: u128 = 60;
 
 pub(super) const VM_INTERPRETER_SIZE: usize = 512;
 pub(super) const VM_DISPLAY_REFRESH_SIZE: usize = 256;
 pub(super) const VM_INTERNAL_SIZE: usize = 96;
 
-pub(super) const VM_RESERVED_BEGIN: usize = VM_INTERPRETER_SIZE;
+// default size of the area at the end of the memory which ROMs cannot be loaded into
 pub(super) const VM_RESERVED_END: usize = VM_DISPLAY_REFRESH_SIZE + VM_INTERNAL_SIZE;
 
 pub(super) const MEMORY_SIZE: usize = 1024 * 4;
-pub(super) const ROM_SIZE: usize = MEMORY_SIZE - VM_RESERVED_BEGIN - VM_RESERVED_END;
+// XO-CHIP addresses 64K, the area at the end of the 4K memory is not reserved there
+pub(super) const XO_CHIP_MEMORY_SIZE: usize = 1024 * 64;
 pub(super) const REGISTER_COUNT: usize = 16;
 
 pub(super) const PC_INCREMENT: u16 = 2;
+// default load address of ROMs, ETI-660 ROMs for example start at 0x600
 pub(super) const PC_START: u16 = VM_INTERPRETER_SIZE as u16;
 
 pub(super) const TIMER_DURATION_NANO: u128 = u128::pow(10, 9) / VM_ORIGINAL_HZ;
@@ -0,0 +1,68 @@
+// counts how often each opcode variant was executed, variants which never ran are listed in the report
+#[derive(Debug)]
+pub struct OpcodeCoverage {
+    counts: Vec<(&'static str, u64)>,
+}
+
+impl OpcodeCoverage {
+    pub fn new(names: &[&'static str]) -> OpcodeCoverage {
+        OpcodeCoverage {
+            counts: names.iter().map(|x| (*x, 0)).collect(),
+        }
+    }
+
+    // names which are not part of the coverage are ignored
+    pub fn record(&mut self, name: &str) {
+        if let Some((_, count)) = self.counts.iter_mut().find(|(x, _)| *x == name) {
+            *count += 1;
+        }
+    }
+
+    pub fn covered(&self) -> usize {
+        self.counts.iter().filter(|(_, count)| *count > 0).count()
+    }
+
+    pub fn report(&self) -> Vec<String> {
+        let total = self.counts.len();
+        let mut lines = vec![format!(
+            "Opcode coverage: {} of {} executed ({:.1}%)",
+            self.covered(),
+            total,
+            self.covered() as f64 * 100. / total.max(1) as f64
+        )];
+
+        for (name, count) in self.counts.iter().filter(|(_, count)| *count > 0) {
+            lines.push(format!("  {:<24}  {:>10}", name, count));
+        }
+
+        let missing: Vec<&str> = self.counts.iter().filter(|(_, count)| *count == 0).map(|(name, _)| *name).collect();
+        if missing.is_empty() == false {
+            lines.push(format!("Not executed: {}", missing.join(", ")));
+        }
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_lists_executed_and_missing_opcodes() {
+        let mut coverage = OpcodeCoverage::new(&["Disp", "Flow_Jump", "Rand"]);
+        coverage.record("Disp");
+        coverage.record("Disp");
+        coverage.record("Unknown");
+
+        assert_eq!(coverage.covered(), 1);
+        assert_eq!(
+            coverage.report(),
+            vec![
+                "Opcode coverage: 1 of 3 executed (33.3%)",
+                "  Disp                               2",
+                "Not executed: Flow_Jump, Rand",
+            ]
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::errors::Errors;
+
+// reads and parses a file, failed wraps the error together with the path of the file
+pub fn load<T, P, F>(path: &Path, parse: P, failed: F) -> Result<T>
+where
+    P: FnOnce(&[u8]) -> Result<T>,
+    F: FnOnce(String, anyhow::Error) -> Errors,
+{
+    let read = || -> Result<T> { parse(&fs::read(path)?) };
+
+    read().map_err(|error| failed(path.display().to_string(), error).into())
+}
+
+// creates the directory of the file, e.g. data, before writing it
+pub fn save(path: &Path, bytes: &[u8]) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, bytes)?;
+    Ok(())
+}
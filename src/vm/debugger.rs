@@ -1,23 +1,117 @@
+use super::breakpoints::Breakpoints;
+use super::collisions::CollisionLog;
+use super::condition::Condition;
+use super::save_state::SaveState;
+use super::symbols::Symbols;
 use super::config::Config;
+use super::watch::Watch;
+use super::watchpoints::Watchpoints;
+use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::mpsc;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use log::warn;
 use strum_macros::Display;
 
 #[derive(Display, Debug)]
 pub enum DebuggerCommand {
     Next,
     Previous,
+    // moves the frame pointer to a recorded frame, 0 is the first one
+    GotoFrame(usize),
+    GotoFirstFrame,
+    GotoLastFrame,
 
     PrintRegisters,
     PrintStack,
     PrintTimers,
+    PrintCollisions,
+    // prints the registers, stack entries and memory bytes which differ between two recorded frames
+    DiffFrames { from: usize, to: usize },
+    // compares the previous with the current frame
+    DiffPrevious,
+    // hex and ASCII dump of the memory of the current frame
+    PrintMemory { start: u16, len: u16 },
+    // dumps the rows around I
+    PrintMemoryAroundI,
+    // the n most executed addresses and opcodes, starts the profiler if it is not running yet
+    PrintProfile(usize),
+    // the executed and the never executed opcode variants, starts counting if it is not running yet
+    PrintCoverage,
+    // replies with the current frame, used by the debug server
+    ReadState(mpsc::Sender<SaveState>),
+
+    // change the current frame, the frames recorded after it are discarded
+    SetRegister { register: usize, value: u8 },
+    SetI(u16),
+    SetPC(u16),
+    WriteMemory { address: u16, data: Vec<u8> },
+
+    ToggleBreakpoint,
+    AddBreakpoint(u16),
+
+    // leave break mode until the next breakpoint
+    Continue,
+    // walks back through the recorded frames to the last one a breakpoint, write watchpoint or break condition stopped at
+    ReverseContinue,
+    // the expression is printed whenever the debugger halts or steps
+    AddWatch(String),
+    ClearWatches,
+    // continue until the address is reached, like a breakpoint which is removed once it is hit
+    RunTo(u16),
+    // continue until the current subroutine returned
+    RunUntilReturn,
+
+    // writes all frames recorded so far into a file, reading it replaces them
+    ExportHistory(PathBuf),
+    ImportHistory(PathBuf),
+}
+
+// the current frame as the debugger panel shows it
+#[derive(Debug, Clone, Default)]
+pub struct DebugView {
+    pub registers: Vec<u8>,
+    pub i: u16,
+    pub pc: u16,
+    // the stack entries of the calls, the innermost call last
+    pub stack: Vec<u16>,
+    pub delay_timer: u8,
+    pub sound_timer: u8,
+    // address, raw opcode and decoded instruction, starting a few instructions before PC
+    pub disassembly: Vec<(u16, u16, String)>,
+}
+
+pub struct BreakCondition {
+    pub(super) text: String,
+    pub(super) condition: Condition,
+    pub(super) held: bool,
 }
 
 pub struct Debugger {
     pub(super) enabled: bool,
     pub(super) enable_break: Arc<AtomicBool>,
     pub(super) consumer: mpsc::Receiver<DebuggerCommand>,
+    pub(super) breakpoints: Arc<Mutex<Breakpoints>>,
+    pub(super) watchpoints: Arc<Mutex<Watchpoints>>,
+    pub(super) log_collisions: bool,
+    pub(super) log_self_modification: bool,
+    pub(super) collisions: CollisionLog,
+    // checked before every instruction, they break when they start to hold
+    pub(super) break_conditions: Vec<BreakCondition>,
+    // opcode classes like Disp or Flow_Call which break before they execute
+    pub(super) break_on_opcodes: Vec<String>,
+    // expressions and their parsed form
+    pub(super) watches: Vec<(String, Watch)>,
+    // frame the watches were printed for, they are printed again when the debugger shows another frame
+    pub(super) watched_frame: Option<usize>,
+    // labels used in the output instead of plain addresses
+    pub(super) symbols: Arc<Symbols>,
+
+    // address of the breakpoint which halted execution, it is skipped once when resuming
+    pub(super) resume_address: Option<u16>,
+    pub(super) run_to: Option<u16>,
+    // stack depth of the subroutine to leave, it breaks once the stack is shallower
+    pub(super) run_until_depth: Option<usize>,
 }
 
 impl Debugger {
@@ -25,11 +119,116 @@ impl Debugger {
         config: &Config,
         enable_break: Arc<AtomicBool>,
         consumer: mpsc::Receiver<DebuggerCommand>,
+        breakpoints: Arc<Mutex<Breakpoints>>,
+        watchpoints: Arc<Mutex<Watchpoints>>,
     ) -> Debugger {
         Debugger {
             enabled: config.debugger.enable,
             enable_break,
             consumer,
+            breakpoints,
+            watchpoints,
+            log_collisions: config.debugger.log_collisions,
+            log_self_modification: config.debugger.log_self_modification,
+            collisions: CollisionLog::new(),
+            break_conditions: parse_break_conditions(&config.debugger.break_conditions),
+            break_on_opcodes: config.debugger.break_on_opcodes.clone(),
+            watches: parse_watches(&config.debugger.watches),
+            watched_frame: None,
+            symbols: Arc::new(match config.debugger.enable {
+                true => Symbols::load(config),
+                false => Symbols::default(),
+            }),
+            resume_address: None,
+            run_to: None,
+            run_until_depth: None,
         }
     }
+
+    // shared with the debug server, which resolves the labels of its commands
+    pub fn get_symbols(&self) -> Arc<Symbols> {
+        self.symbols.clone()
+    }
+}
+
+const MEMORY_DUMP_ROW: usize = 16;
+
+// one row per 16 bytes, e.g. 0x200: 00 E0 A2 2A 60 0C  |...*`.|, the range is clamped to the memory
+pub(super) fn format_memory(memory: &[u8], start: u16, len: u16) -> Vec<String> {
+    let start = (start as usize).min(memory.len());
+    let end = (start + len as usize).min(memory.len());
+
+    memory[start..end]
+        .chunks(MEMORY_DUMP_ROW)
+        .enumerate()
+        .map(|(i, row)| {
+            let hex: Vec<String> = row.iter().map(|x| format!("{:02X}", x)).collect();
+            let ascii: String = row
+                .iter()
+                .map(|x| if x.is_ascii_graphic() || *x == b' ' { *x as char } else { '.' })
+                .collect();
+
+            format!(
+                "{:#05X}: {:width$}  |{}|",
+                start + i * MEMORY_DUMP_ROW,
+                hex.join(" "),
+                ascii,
+                width = MEMORY_DUMP_ROW * 3 - 1
+            )
+        })
+        .collect()
+}
+
+// invalid expressions are reported and ignored
+fn parse_watches(watches: &[String]) -> Vec<(String, Watch)> {
+    watches
+        .iter()
+        .filter_map(|text| match Watch::parse(text) {
+            Ok(watch) => Some((text.clone(), watch)),
+            Err(err) => {
+                warn!("Ignoring invalid watch {}: {}", text, err);
+                None
+            }
+        })
+        .collect()
+}
+
+// invalid conditions are reported and ignored
+fn parse_break_conditions(conditions: &[String]) -> Vec<BreakCondition> {
+    conditions
+        .iter()
+        .filter_map(|text| match Condition::parse(text) {
+            Ok(condition) => Some(BreakCondition {
+                text: text.clone(),
+                condition,
+                held: false,
+            }),
+            Err(err) => {
+                warn!("Ignoring invalid break condition {}: {}", text, err);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_memory_prints_hex_and_ascii_rows() {
+        let mut memory = vec![0u8; 0x220];
+        memory[0x200..0x204].copy_from_slice(b"AB*\n");
+
+        let rows = format_memory(&memory, 0x200, 0x20);
+        assert_eq!(rows.len(), 2);
+        assert_eq!(
+            rows[0],
+            "0x200: 41 42 2A 0A 00 00 00 00 00 00 00 00 00 00 00 00  |AB*.............|"
+        );
+
+        let rows = format_memory(&memory, 0x218, 0x20);
+        assert_eq!(rows, vec![format!("0x218: {:47}  |........|", "00 00 00 00 00 00 00 00")]);
+        assert!(format_memory(&memory, 0x300, 0x10).is_empty());
+    }
 }
@@ -1,71 +1,147 @@
 #[cfg(test)]
 use mockall::automock;
-use super::constants::{SCREEN_SIZE, SCREEN_SIZE_X};
+use super::screen::Screen;
 
-pub type RawScreen = [u8; SCREEN_SIZE];
+// draws which were not taken are dropped beyond this
+const MAX_SPRITE_DRAWS: usize = 256;
+
+// display operations since the statistics were last taken
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DrawStats {
+    pub draws: u32,
+    pub clears: u32,
+    pub pixels_toggled: u32,
+}
+
+// destination region of a DXYN draw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteDraw {
+    pub x: usize,
+    pub y: usize,
+    pub width: u8,
+    pub height: u8,
+}
 
 pub struct Snapshot {
-    screen: RawScreen,
+    screen: Screen,
+    last_draw: (usize, usize),
+    generation: u64,
 }
 
 impl Snapshot {
-    pub fn get_pixel(&self, x: usize, y: usize) -> u8 {
-        self.screen[x + y * SCREEN_SIZE_X]
+    pub fn width(&self) -> usize {
+        self.screen.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.screen.height()
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        self.screen.is_set(x, y)
+    }
+
+    // all pixels row by row as (x, y, is set)
+    pub fn iter_pixels(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        self.screen.iter_pixels()
+    }
+
+    pub fn get_screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    // changes whenever the screen is modified, equal generations mean equal screens
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
+    // origin of the most recently drawn sprite
+    pub fn get_last_draw(&self) -> (usize, usize) {
+        self.last_draw
     }
 }
 
 #[cfg_attr(test, automock)]
 pub trait Display : Send {
-    fn get_screen(&self) -> &RawScreen;
-    fn set_screen(&mut self, screen: &RawScreen);
+    fn get_screen(&self) -> &Screen;
+    fn set_screen(&mut self, screen: &Screen);
     fn clear(&mut self);
-    fn draw_sprite(&mut self, x: usize, y: usize, height: u8, data: &[u8]) -> DisplayState;
+    // wrap continues the sprite on the opposite side of the screen instead of clipping it
+    fn draw_sprite(&mut self, x: usize, y: usize, height: u8, data: &[u8], wrap: bool) -> DisplayState;
+    // SUPER-CHIP 16x16 sprite with two bytes per row
+    fn draw_large_sprite(&mut self, x: usize, y: usize, data: &[u8], wrap: bool) -> DisplayState;
+    // switches between 64x32 and 128x64 and clears the screen
+    fn set_hires(&mut self, hires: bool);
+    fn scroll(&mut self, dx: isize, dy: isize);
     fn get_snapshot(&self) -> Snapshot;
+    // pixels erased by collisions since the last call
+    fn take_collided(&mut self) -> Screen;
+    // sprites drawn since the last call
+    fn take_sprite_draws(&mut self) -> Vec<SpriteDraw>;
+    fn take_draw_stats(&mut self) -> DrawStats;
 }
 
 pub struct VmDisplay {
-    screen: RawScreen,
+    screen: Screen,
+    last_draw: (usize, usize),
+    generation: u64,
+    collided: Screen,
+    sprite_draws: Vec<SpriteDraw>,
+    draw_stats: DrawStats,
 }
 
 impl VmDisplay {
     pub fn new() -> VmDisplay {
         VmDisplay {
-            screen: [0; SCREEN_SIZE],
+            screen: Screen::new(),
+            last_draw: (0, 0),
+            generation: 0,
+            collided: Screen::new(),
+            sprite_draws: Vec::with_capacity(MAX_SPRITE_DRAWS),
+            draw_stats: DrawStats::default(),
         }
     }
-}
 
-impl Display for VmDisplay {
-    fn get_screen(&self) -> &RawScreen {
-        &self.screen
+    // an empty screen with the resolution of the current one
+    fn cleared_screen(&self) -> Screen {
+        let mut screen = self.screen;
+        screen.clear();
+        screen
     }
 
-    fn set_screen(&mut self, screen: &RawScreen) {
-        self.screen = screen.clone();
-    }
+    // rows of width bytes each
+    fn draw(&mut self, x: usize, y: usize, width: u8, height: u8, data: &[u8], wrap: bool) -> DisplayState {
+        let mut state = DisplayState::Unchanged;
+        let (screen_width, screen_height) = (self.screen.width(), self.screen.height());
 
-    fn clear(&mut self) {
-        for n in 0..self.screen.len() {
-            self.screen[n] = 0;
+        // the origin always wraps around the screen, the sprite itself is clipped at the edges unless it wraps
+        let (x, y) = (x % screen_width, y % screen_height);
+        self.last_draw = (x, y);
+        self.generation += 1;
+
+        if self.sprite_draws.len() < MAX_SPRITE_DRAWS {
+            self.sprite_draws.push(SpriteDraw { x, y, width: width * 8, height });
         }
-    }
 
-    fn draw_sprite(&mut self, x: usize, y: usize, height: u8, data: &[u8]) -> DisplayState {
-        let mut state = DisplayState::Unchanged;
+        self.draw_stats.draws = self.draw_stats.draws.saturating_add(1);
 
         for sprite_y in 0..height as usize {
-            let pixels = data[sprite_y];
+            for sprite_x in 0..width as usize * 8 {
+                let pixels = data[sprite_y * width as usize + sprite_x / 8];
 
-            for sprite_x in 0..8 {
-                if pixels & (0x80 >> sprite_x) != 0 {
-                    let pixel_index = x + sprite_x + ((y + sprite_y) * SCREEN_SIZE_X);
+                if pixels & (0x80 >> (sprite_x % 8)) != 0 {
+                    let (pixel_x, pixel_y) = match wrap {
+                        true => ((x + sprite_x) % screen_width, (y + sprite_y) % screen_height),
+                        false => (x + sprite_x, y + sprite_y),
+                    };
 
-                    if pixel_index < SCREEN_SIZE {
-                        if self.screen[pixel_index] == 1 {
+                    if pixel_x < screen_width && pixel_y < screen_height {
+                        if self.screen.toggle(pixel_x, pixel_y) {
                             state = DisplayState::Changed;
+                            self.collided.set(pixel_x, pixel_y, true);
                         }
 
-                        self.screen[pixel_index] ^= 1;
+                        self.draw_stats.pixels_toggled = self.draw_stats.pixels_toggled.saturating_add(1);
                     }
                 }
             }
@@ -73,14 +149,74 @@ impl Display for VmDisplay {
 
         state
     }
+}
+
+impl Display for VmDisplay {
+    fn get_screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    fn set_screen(&mut self, screen: &Screen) {
+        if self.screen != *screen {
+            self.screen = *screen;
+            self.generation += 1;
+        }
+
+        // collisions are marked at the pixels of the screen, restored screens can have the other resolution
+        if self.collided.width() != self.screen.width() {
+            self.collided = self.cleared_screen();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.screen.clear();
+
+        self.generation += 1;
+        self.draw_stats.clears = self.draw_stats.clears.saturating_add(1);
+    }
+
+    fn draw_sprite(&mut self, x: usize, y: usize, height: u8, data: &[u8], wrap: bool) -> DisplayState {
+        self.draw(x, y, 1, height, data, wrap)
+    }
+
+    fn draw_large_sprite(&mut self, x: usize, y: usize, data: &[u8], wrap: bool) -> DisplayState {
+        self.draw(x, y, 2, 16, data, wrap)
+    }
+
+    fn set_hires(&mut self, hires: bool) {
+        self.screen.set_hires(hires);
+        self.collided.set_hires(hires);
+        self.generation += 1;
+    }
+
+    fn scroll(&mut self, dx: isize, dy: isize) {
+        self.screen.scroll(dx, dy);
+        self.generation += 1;
+    }
 
     fn get_snapshot(&self) -> Snapshot {
         Snapshot {
-            screen: self.screen.clone(),
+            screen: self.screen,
+            last_draw: self.last_draw,
+            generation: self.generation,
         }
     }
+
+    fn take_collided(&mut self) -> Screen {
+        let cleared = self.cleared_screen();
+        std::mem::replace(&mut self.collided, cleared)
+    }
+
+    fn take_sprite_draws(&mut self) -> Vec<SpriteDraw> {
+        std::mem::replace(&mut self.sprite_draws, Vec::with_capacity(MAX_SPRITE_DRAWS))
+    }
+
+    fn take_draw_stats(&mut self) -> DrawStats {
+        std::mem::take(&mut self.draw_stats)
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum DisplayState {
     Changed,
     Unchanged,
@@ -89,7 +225,9 @@ pub enum DisplayState {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use super::super::constants::{HIRES_SCREEN_SIZE_X, SCREEN_SIZE_X, SCREEN_SIZE_Y};
     use rand;
+    use test_case::test_case;
 
     fn new() -> VmDisplay {
         VmDisplay::new()
@@ -99,16 +237,158 @@ mod tests {
     fn clear() {
         let mut d = new();
 
-        for p in d.screen.iter_mut() {
-            *p = rand::random::<u8>();
-        }
+        let bytes: Vec<u8> = d.screen.as_bytes().iter().map(|_| rand::random::<u8>()).collect();
+        d.set_screen(&Screen::from_bytes(&bytes).unwrap());
 
-        println!("{}", d.screen[0]);
+        println!("{}", d.screen.as_bytes()[0]);
 
         d.clear();
 
-        for p in &d.screen {
-            assert_eq!(*p, 0);
+        for (_, _, is_set) in d.screen.iter_pixels() {
+            assert!(!is_set);
+        }
+    }
+
+    #[test]
+    fn take_collided_returns_erased_pixels_once() {
+        let mut d = new();
+
+        d.draw_sprite(0, 0, 1, &[0b1100_0000], false);
+        assert!(d.take_collided() == Screen::new());
+
+        d.draw_sprite(1, 0, 1, &[0b1000_0000], false);
+
+        let collided = d.take_collided();
+        assert!(!collided.is_set(0, 0));
+        assert!(collided.is_set(1, 0));
+        assert!(d.take_collided() == Screen::new());
+    }
+
+    #[test]
+    fn take_collided_keeps_the_hires_resolution() {
+        let mut d = new();
+        d.set_hires(true);
+        d.take_collided();
+
+        d.draw_sprite(100, 40, 1, &[0b1000_0000], false);
+        d.draw_sprite(100, 40, 1, &[0b1000_0000], false);
+
+        let collided = d.take_collided();
+        assert_eq!(collided.width(), HIRES_SCREEN_SIZE_X);
+        assert!(collided.is_set(100, 40));
+
+        let mut screen = Screen::new();
+        screen.set_hires(true);
+        d.set_hires(false);
+        d.set_screen(&screen);
+        d.draw_sprite(100, 40, 1, &[0b1000_0000], false);
+        d.draw_sprite(100, 40, 1, &[0b1000_0000], false);
+        assert!(d.take_collided().is_set(100, 40));
+    }
+
+    #[test_case(SCREEN_SIZE_X,          5,                     0,                 5                 ; "left edge")]
+    #[test_case(SCREEN_SIZE_X * 2 - 1,  5,                     SCREEN_SIZE_X - 1, 5                 ; "right edge")]
+    #[test_case(5,                      SCREEN_SIZE_Y,         5,                 0                 ; "top edge")]
+    #[test_case(5,                      SCREEN_SIZE_Y * 2 - 1, 5,                 SCREEN_SIZE_Y - 1 ; "bottom edge")]
+    #[test_case(68,                     0,                     4,                 0                 ; "past right edge")]
+    fn draw_sprite_wraps_origin(x: usize, y: usize, expected_x: usize, expected_y: usize) {
+        let mut d = new();
+
+        d.draw_sprite(x, y, 1, &[0b1000_0000], false);
+
+        let set: Vec<(usize, usize, bool)> = d.get_screen().iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(set, vec![(expected_x, expected_y, true)]);
+        assert_eq!(d.get_snapshot().get_last_draw(), (expected_x, expected_y));
+    }
+
+    #[test]
+    fn draw_sprite_clips_at_edges() {
+        let mut d = new();
+
+        d.draw_sprite(SCREEN_SIZE_X - 1, SCREEN_SIZE_Y - 1, 2, &[0b1100_0000, 0b1100_0000], false);
+
+        let set: Vec<(usize, usize, bool)> = d.get_screen().iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(set, vec![(SCREEN_SIZE_X - 1, SCREEN_SIZE_Y - 1, true)]);
+    }
+
+    #[test]
+    fn draw_large_sprite_in_hires() {
+        let mut d = new();
+        d.set_hires(true);
+
+        let mut data = [0u8; 32];
+        data[0] = 0x80;
+        data[31] = 0x01;
+        d.draw_large_sprite(HIRES_SCREEN_SIZE_X + 100, 40, &data, false);
+
+        let set: Vec<(usize, usize, bool)> = d.get_screen().iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(set, vec![(100, 40, true), (115, 55, true)]);
+        assert_eq!(d.take_sprite_draws()[0].width, 16);
+    }
+
+    #[test]
+    fn draw_sprite_wraps_with_quirk() {
+        let mut d = new();
+
+        d.draw_sprite(SCREEN_SIZE_X - 1, SCREEN_SIZE_Y - 1, 2, &[0b1100_0000, 0b1000_0000], true);
+
+        let set: Vec<(usize, usize, bool)> = d.get_screen().iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(
+            set,
+            vec![(SCREEN_SIZE_X - 1, 0, true), (0, SCREEN_SIZE_Y - 1, true), (SCREEN_SIZE_X - 1, SCREEN_SIZE_Y - 1, true)]
+        );
+    }
+
+    #[test_case(false, vec![(120, 60, true)] ; "clipped")]
+    #[test_case(true, vec![(7, 11, true), (120, 60, true)] ; "wrapped")]
+    fn draw_large_sprite_over_hires_edge(wrap: bool, expected: Vec<(usize, usize, bool)>) {
+        let mut d = new();
+        d.set_hires(true);
+
+        let mut data = [0u8; 32];
+        data[0] = 0x80;
+        data[31] = 0x01;
+        d.draw_large_sprite(120, 60, &data, wrap);
+
+        let set: Vec<(usize, usize, bool)> = d.get_screen().iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(set, expected);
+    }
+
+    #[test]
+    fn take_sprite_draws_is_bounded() {
+        let mut d = new();
+
+        for _ in 0..MAX_SPRITE_DRAWS + 1 {
+            d.draw_sprite(2, 3, 1, &[0], false);
         }
+
+        let draws = d.take_sprite_draws();
+        assert_eq!(draws.len(), MAX_SPRITE_DRAWS);
+        assert_eq!(draws[0], SpriteDraw { x: 2, y: 3, width: 8, height: 1 });
+        assert!(d.take_sprite_draws().is_empty());
+    }
+
+    #[test]
+    fn take_draw_stats_counts_operations() {
+        let mut d = new();
+
+        d.draw_sprite(0, 0, 2, &[0b1100_0000, 0b1000_0000], false);
+        d.clear();
+
+        assert_eq!(d.take_draw_stats(), DrawStats { draws: 1, clears: 1, pixels_toggled: 3 });
+        assert_eq!(d.take_draw_stats(), DrawStats::default());
+    }
+
+    #[test]
+    fn iter_pixels_yields_coordinates() {
+        let mut d = new();
+
+        d.draw_sprite(2, 1, 1, &[0b1000_0000], false);
+
+        let snapshot = d.get_snapshot();
+        let set: Vec<(usize, usize, bool)> = snapshot.iter_pixels().filter(|x| x.2).collect();
+
+        assert_eq!(snapshot.iter_pixels().count(), snapshot.width() * snapshot.height());
+        assert_eq!(set, vec![(2, 1, true)]);
     }
 }
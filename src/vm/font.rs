@@ -0,0 +1,83 @@
+use std::fs;
+use std::path::Path;
+
+use log::info;
+
+use super::config::Config;
+use super::constants::{BIG_FONTS, FONTS};
+use crate::errors::Errors;
+
+// the small symbols followed by the big digits, as they are loaded at the font address
+pub fn load(config: &Config) -> Result<Vec<u8>, Errors> {
+    let path = match &config.font_file {
+        Some(path) => path,
+        None => return Ok([&FONTS[..], &BIG_FONTS[..]].concat()),
+    };
+
+    let invalid = |message: String| Errors::FontInvalid {
+        name: path.clone(),
+        message,
+    };
+
+    let bytes = fs::read(path).map_err(|x| invalid(x.to_string()))?;
+    let is_hex = Path::new(path)
+        .extension()
+        .map(|x| x.eq_ignore_ascii_case("hex"))
+        .unwrap_or(false);
+
+    let font = match is_hex {
+        true => parse_hex(&String::from_utf8_lossy(&bytes)).map_err(invalid)?,
+        false => bytes,
+    };
+
+    // a font without big digits keeps the built-in ones
+    let font = match font.len() {
+        x if x == FONTS.len() => [&font[..], &BIG_FONTS[..]].concat(),
+        x if x == FONTS.len() + BIG_FONTS.len() => font,
+        x => {
+            return Err(invalid(format!(
+                "{} bytes, expected {} or {} with the big digits",
+                x,
+                FONTS.len(),
+                FONTS.len() + BIG_FONTS.len()
+            )))
+        }
+    };
+
+    info!("Loaded font {}", path);
+    Ok(font)
+}
+
+// bytes as hex numbers separated by whitespace or commas, with an optional 0x prefix, # starts a comment
+fn parse_hex(text: &str) -> Result<Vec<u8>, String> {
+    text.lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split(|x: char| x.is_whitespace() || x == ','))
+        .filter(|token| token.is_empty() == false)
+        .map(|token| {
+            let digits = token.trim_start_matches("0x").trim_start_matches("0X");
+            u8::from_str_radix(digits, 16).map_err(|_| format!("{} is not a hex byte", token))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_hex_reads_listing() {
+        let text = "# zero\n0xF0, 0x90 90\n  F0 # end\n";
+
+        assert_eq!(parse_hex(text), Ok(vec![0xF0, 0x90, 0x90, 0xF0]));
+        assert!(parse_hex("F0 G1").is_err());
+    }
+
+    #[test]
+    fn load_uses_built_in_font_by_default() {
+        let font = load(&Config::default()).unwrap();
+
+        assert_eq!(&font[..FONTS.len()], &FONTS[..]);
+        assert_eq!(&font[FONTS.len()..], &BIG_FONTS[..]);
+    }
+}
@@ -0,0 +1,192 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::data_file;
+use super::save_state::{SaveState, SAVE_STATE_VERSION};
+use crate::errors::Errors;
+
+// bytes which differ from the previous frame, starting at the offset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Patch {
+    pub offset: usize,
+    pub bytes: Vec<u8>,
+}
+
+// a frame stored as the changes against the frame before it, the memory and the screen only keep the changed bytes
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameDelta {
+    pub registers: Vec<u8>,
+    pub stack: Vec<u16>,
+    pub pc: u16,
+    pub i: u16,
+    pub delay_timer: u64,
+    pub sound_timer: u64,
+    pub rpl_flags: Vec<u8>,
+    pub memory_size: usize,
+    pub memory: Vec<Patch>,
+    // the length changes with the resolution
+    pub screen_size: usize,
+    pub screen: Vec<Patch>,
+}
+
+// the frames recorded by the debugger, written to a file to step through a run somewhere else
+// the first frame is stored against empty memory, so every frame only costs the bytes it changed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FrameHistory {
+    pub rom_hash: String,
+    // index of the frame the debugger was at
+    pub frame_pointer: usize,
+    pub frames: Vec<FrameDelta>,
+}
+
+// bytes past the end of the previous frame count as 0
+fn diff(previous: &[u8], current: &[u8]) -> Vec<Patch> {
+    let mut patches: Vec<Patch> = Vec::new();
+
+    for (offset, byte) in current.iter().enumerate() {
+        if previous.get(offset).copied().unwrap_or(0) == *byte {
+            continue;
+        }
+
+        match patches.last_mut() {
+            Some(patch) if patch.offset + patch.bytes.len() == offset => patch.bytes.push(*byte),
+            _ => patches.push(Patch {
+                offset,
+                bytes: vec![*byte],
+            }),
+        }
+    }
+
+    patches
+}
+
+fn apply(previous: &[u8], size: usize, patches: &[Patch]) -> Vec<u8> {
+    let mut bytes = previous.to_vec();
+    bytes.resize(size, 0);
+
+    for patch in patches {
+        if let Some(target) = bytes.get_mut(patch.offset..patch.offset + patch.bytes.len()) {
+            target.copy_from_slice(&patch.bytes);
+        }
+    }
+
+    bytes
+}
+
+impl FrameHistory {
+    // the states of the frames, the oldest first
+    pub fn from_states<I: IntoIterator<Item = SaveState>>(rom_hash: &str, frame_pointer: usize, states: I) -> FrameHistory {
+        let mut frames = Vec::new();
+        let (mut memory, mut screen) = (Vec::new(), Vec::new());
+
+        for state in states {
+            frames.push(FrameDelta {
+                memory_size: state.memory.len(),
+                memory: diff(&memory, &state.memory),
+                screen_size: state.screen.len(),
+                screen: diff(&screen, &state.screen),
+                registers: state.registers,
+                stack: state.stack,
+                pc: state.pc,
+                i: state.i,
+                delay_timer: state.delay_timer,
+                sound_timer: state.sound_timer,
+                rpl_flags: state.rpl_flags,
+            });
+
+            memory = state.memory;
+            screen = state.screen;
+        }
+
+        FrameHistory {
+            rom_hash: String::from(rom_hash),
+            frame_pointer,
+            frames,
+        }
+    }
+
+    // applies the changes of every frame to the one before it, the oldest first
+    pub fn to_states(&self) -> Vec<SaveState> {
+        let mut states: Vec<SaveState> = Vec::with_capacity(self.frames.len());
+
+        for frame in &self.frames {
+            let (memory, screen) = match states.last() {
+                Some(previous) => (&previous.memory[..], &previous.screen[..]),
+                None => (&[][..], &[][..]),
+            };
+
+            states.push(SaveState {
+                version: SAVE_STATE_VERSION,
+                rom_hash: self.rom_hash.clone(),
+                registers: frame.registers.clone(),
+                stack: frame.stack.clone(),
+                memory: apply(memory, frame.memory_size, &frame.memory),
+                pc: frame.pc,
+                i: frame.i,
+                delay_timer: frame.delay_timer,
+                sound_timer: frame.sound_timer,
+                screen: apply(screen, frame.screen_size, &frame.screen),
+                rpl_flags: frame.rpl_flags.clone(),
+            });
+        }
+
+        states
+    }
+
+    pub fn load(path: &Path) -> Result<FrameHistory> {
+        data_file::load(
+            path,
+            |bytes| Ok(serde_json::from_slice::<FrameHistory>(bytes)?),
+            |name, error| Errors::FrameHistoryLoadFailed { name, error },
+        )
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        data_file::save(path, &serde_json::to_vec(self)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(memory: Vec<u8>, pc: u16) -> SaveState {
+        SaveState {
+            version: SAVE_STATE_VERSION,
+            rom_hash: String::from("abc"),
+            registers: vec![0; 16],
+            stack: vec![0x202],
+            memory,
+            pc,
+            i: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            screen: vec![0; 256],
+            rpl_flags: vec![0; 8],
+        }
+    }
+
+    #[test]
+    fn frames_only_store_the_changed_bytes() {
+        let mut memory = vec![0u8; 4096];
+        memory[0x200] = 0x12;
+        let first = state(memory.clone(), 0x200);
+
+        memory[0x300] = 1;
+        memory[0x301] = 2;
+        memory[0x400] = 3;
+        let second = state(memory, 0x202);
+
+        let history = FrameHistory::from_states("abc", 1, vec![first.clone(), second.clone()]);
+
+        assert_eq!(history.frames[0].memory, vec![Patch { offset: 0x200, bytes: vec![0x12] }]);
+        assert_eq!(
+            history.frames[1].memory,
+            vec![Patch { offset: 0x300, bytes: vec![1, 2] }, Patch { offset: 0x400, bytes: vec![3] }]
+        );
+        assert!(history.frames[1].screen.is_empty());
+        assert_eq!(history.to_states(), vec![first, second]);
+    }
+}
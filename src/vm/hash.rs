@@ -0,0 +1,32 @@
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// FNV-1a, unlike std's DefaultHasher the result is stable across Rust versions so it can be written to files
+pub fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash = FNV_OFFSET;
+
+    for byte in bytes {
+        hash ^= *byte as u64;
+        hash = hash.wrapping_mul(FNV_PRIME);
+    }
+
+    hash
+}
+
+// hex representation used to identify ROMs in files
+pub fn rom_hash(rom: &[u8]) -> String {
+    format!("{:016x}", fnv1a(rom))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(b"",    0xcbf29ce484222325 ; "empty")]
+    #[test_case(b"a",   0xaf63dc4c8601ec8c ; "single byte")]
+    #[test_case(b"foobar", 0x85944171f73967e8 ; "word")]
+    fn fnv1a_matches_reference(bytes: &[u8], expected: u64) {
+        assert_eq!(fnv1a(bytes), expected);
+    }
+}
@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::errors::Errors;
+
+// how often every memory address was read, written and fetched as an instruction
+#[derive(Debug, Clone, Default)]
+pub struct MemoryHeatmap {
+    pub reads: Vec<u64>,
+    pub writes: Vec<u64>,
+    pub executes: Vec<u64>,
+}
+
+fn add(counts: &mut Vec<u64>, address: usize, len: usize) {
+    let end = (address + len).min(counts.len());
+    for count in counts.iter_mut().take(end).skip(address) {
+        *count += 1;
+    }
+}
+
+impl MemoryHeatmap {
+    pub fn new(memory_size: usize) -> MemoryHeatmap {
+        MemoryHeatmap {
+            reads: vec![0; memory_size],
+            writes: vec![0; memory_size],
+            executes: vec![0; memory_size],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.reads.len()
+    }
+
+    // accesses past the end of the memory are not counted
+    pub fn record_read(&mut self, address: usize, len: usize) {
+        add(&mut self.reads, address, len);
+    }
+
+    pub fn record_write(&mut self, address: usize, len: usize) {
+        add(&mut self.writes, address, len);
+    }
+
+    pub fn record_execute(&mut self, address: usize, len: usize) {
+        add(&mut self.executes, address, len);
+    }
+
+    // one line per address which was accessed at all
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("address,reads,writes,executes\n");
+
+        for address in 0..self.len() {
+            let (reads, writes, executes) = (self.reads[address], self.writes[address], self.executes[address]);
+            if reads + writes + executes > 0 {
+                csv.push_str(&format!("{:#05X},{},{},{}\n", address, reads, writes, executes));
+            }
+        }
+
+        csv
+    }
+
+    pub fn export(&self, path: &Path) -> Result<()> {
+        let write = || -> std::io::Result<()> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            fs::write(path, self.to_csv())
+        };
+
+        write().map_err(|error| {
+            Errors::HeatmapExportFailed {
+                name: path.display().to_string(),
+                error,
+            }
+            .into()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_lists_the_accessed_addresses() {
+        let mut heatmap = MemoryHeatmap::new(0x1000);
+        heatmap.record_execute(0x200, 2);
+        heatmap.record_read(0x300, 2);
+        heatmap.record_read(0x301, 1);
+        heatmap.record_write(0xFFF, 3);
+
+        assert_eq!(
+            heatmap.to_csv(),
+            "address,reads,writes,executes\n\
+             0x200,0,0,1\n\
+             0x201,0,0,1\n\
+             0x300,1,0,0\n\
+             0x301,2,0,0\n\
+             0xFFF,0,1,0\n"
+        );
+    }
+}
@@ -4,5 +4,6 @@ use mockall::automock;
 #[cfg_attr(test, automock)]
 pub trait Input : Send {
     fn is_pressed(&self, key: u8) -> bool;
-    fn get_pressed_key(&self) -> Option<u8>;
+    // returns a key which was newly pressed since the last call, held keys are only reported once
+    fn take_pressed_key(&mut self) -> Option<u8>;
 }
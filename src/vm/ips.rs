@@ -0,0 +1,106 @@
+use anyhow::Result;
+
+use crate::errors::Errors;
+
+const HEADER: &[u8] = b"PATCH";
+const FOOTER: &[u8] = b"EOF";
+
+// applies an IPS patch, records may extend the ROM and an optional truncation size may follow the footer
+pub fn apply(rom: &mut Vec<u8>, patch: &[u8]) -> Result<()> {
+    if patch.starts_with(HEADER) == false {
+        return Err(invalid("missing PATCH header"));
+    }
+
+    let mut pos = HEADER.len();
+    let mut read = |len: usize| -> Result<&[u8]> {
+        match patch.get(pos..pos + len) {
+            Some(bytes) => {
+                pos += len;
+                Ok(bytes)
+            }
+            None => Err(invalid("unexpected end of patch")),
+        }
+    };
+
+    loop {
+        let offset = read(3)?;
+        if offset == FOOTER {
+            break;
+        }
+
+        let offset = (offset[0] as usize) << 16 | (offset[1] as usize) << 8 | offset[2] as usize;
+        let size = read(2)?;
+        let size = (size[0] as usize) << 8 | size[1] as usize;
+
+        // a size of 0 marks a run-length encoded record
+        let data = match size {
+            0 => {
+                let run = read(3)?;
+                vec![run[2]; (run[0] as usize) << 8 | run[1] as usize]
+            }
+            _ => read(size)?.to_vec(),
+        };
+
+        if rom.len() < offset + data.len() {
+            rom.resize(offset + data.len(), 0);
+        }
+
+        rom[offset..offset + data.len()].copy_from_slice(&data);
+    }
+
+    if let Ok(size) = read(3) {
+        rom.truncate((size[0] as usize) << 16 | (size[1] as usize) << 8 | size[2] as usize);
+    }
+
+    Ok(())
+}
+
+fn invalid(message: &str) -> anyhow::Error {
+    Errors::IpsInvalid {
+        message: String::from(message),
+    }
+    .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patch(records: &[u8]) -> Vec<u8> {
+        let mut patch = HEADER.to_vec();
+        patch.extend_from_slice(records);
+        patch
+    }
+
+    #[test]
+    fn apply_writes_records() {
+        let mut rom = vec![0u8; 4];
+        apply(&mut rom, &patch(&[0, 0, 1, 0, 2, 0xAA, 0xBB, b'E', b'O', b'F'])).unwrap();
+
+        assert_eq!(rom, vec![0, 0xAA, 0xBB, 0]);
+    }
+
+    #[test]
+    fn apply_extends_with_rle_records() {
+        let mut rom = vec![0u8; 2];
+        apply(&mut rom, &patch(&[0, 0, 1, 0, 0, 0, 3, 0x11, b'E', b'O', b'F'])).unwrap();
+
+        assert_eq!(rom, vec![0, 0x11, 0x11, 0x11]);
+    }
+
+    #[test]
+    fn apply_truncates() {
+        let mut rom = vec![0u8; 4];
+        apply(&mut rom, &patch(&[b'E', b'O', b'F', 0, 0, 2])).unwrap();
+
+        assert_eq!(rom.len(), 2);
+    }
+
+    #[test]
+    fn apply_rejects_invalid_patches() {
+        let mut rom = vec![0u8; 4];
+
+        assert!(apply(&mut rom, b"NOPE").is_err());
+        assert!(apply(&mut rom, &patch(&[0, 0, 1, 0, 4, 0xAA])).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::config::Config;
+use super::data_file;
+use super::hash::rom_hash;
+use crate::errors::Errors;
+
+// 2 stores the input per 60hz frame of the VM instead of per rendered frame
+pub const MOVIE_VERSION: u32 = 2;
+// 60hz frames between two checksums of the VM state
+pub const CHECKSUM_INTERVAL: u64 = 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MovieHeader {
+    pub version: u32,
+    pub rom_hash: String,
+    pub quirks: String,
+    // recording generates a seed if none is configured, movies without one cannot reproduce ROMs which use random numbers
+    pub seed: Option<u64>,
+    pub author: String,
+    pub comment: String,
+}
+
+impl MovieHeader {
+    pub fn new(config: &Config, rom: &[u8]) -> MovieHeader {
+        MovieHeader {
+            version: MOVIE_VERSION,
+            rom_hash: rom_hash(rom),
+            quirks: config.get_quirks(rom).to_string(),
+            seed: config.seed,
+            author: config.movie.author.clone(),
+            comment: config.movie.comment.clone(),
+        }
+    }
+}
+
+// hash of the VM state when the VM reached the frame
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Checksum {
+    pub frame: u64,
+    pub hash: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Movie {
+    pub header: MovieHeader,
+    // bitmask of the pressed keys, one entry per 60hz frame of the VM starting at the reset
+    pub frames: Vec<u16>,
+    // movies recorded before checksums were taken have none and are not verified
+    #[serde(default)]
+    pub checksums: Vec<Checksum>,
+}
+
+impl Movie {
+    pub fn new(header: MovieHeader) -> Movie {
+        Movie {
+            header,
+            frames: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Movie> {
+        data_file::load(
+            path,
+            |bytes| Ok(serde_yaml::from_slice::<Movie>(bytes)?),
+            |name, error| Errors::MovieLoadFailed { name, error },
+        )
+    }
+
+    // playback uses the seed of the recording unless one is configured, validate reports a different one
+    pub fn apply_seed(&self, config: &mut Config) {
+        if config.seed.is_none() {
+            config.seed = self.header.seed;
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        data_file::save(path, serde_yaml::to_string(self)?.as_bytes())
+    }
+
+    // playing back a movie under different conditions would desync silently
+    pub fn validate(&self, current: &MovieHeader) -> Result<()> {
+        let recorded = &self.header;
+
+        if recorded.version != MOVIE_VERSION {
+            return Err(Errors::MovieVersionMismatch {
+                version: recorded.version,
+                expected: MOVIE_VERSION,
+            }
+            .into());
+        }
+
+        let mismatch = |field: &str, recorded: String, current: String| -> Result<()> {
+            if recorded == current {
+                Ok(())
+            } else {
+                Err(Errors::MovieMismatch {
+                    field: String::from(field),
+                    recorded,
+                    current,
+                }
+                .into())
+            }
+        };
+
+        mismatch("ROM", recorded.rom_hash.clone(), current.rom_hash.clone())?;
+        mismatch("quirk profile", recorded.quirks.clone(), current.quirks.clone())?;
+        mismatch(
+            "RNG seed",
+            format!("{:?}", recorded.seed),
+            format!("{:?}", current.seed),
+        )
+    }
+
+    // a different state at the same frame means the playback diverged from the recording
+    pub fn verify(&self, checksum: &Checksum) -> std::result::Result<(), Errors> {
+        match self.checksums.iter().find(|x| x.frame == checksum.frame) {
+            Some(recorded) if recorded.hash != checksum.hash => Err(Errors::MovieDesync { frame: checksum.frame }),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header() -> MovieHeader {
+        MovieHeader::new(&Config::default(), &[0x12, 0x00])
+    }
+
+    #[test]
+    fn validate_accepts_matching_header() {
+        let movie = Movie::new(header());
+        assert!(movie.validate(&header()).is_ok());
+    }
+
+    #[test]
+    fn validate_ignores_author_and_comment() {
+        let mut movie = Movie::new(header());
+        movie.header.author = String::from("someone");
+        movie.header.comment = String::from("any%");
+
+        assert!(movie.validate(&header()).is_ok());
+    }
+
+    #[test]
+    fn validate_rejects_other_rom() {
+        let movie = Movie::new(header());
+        let other = MovieHeader::new(&Config::default(), &[0x13, 0x00]);
+
+        assert!(movie.validate(&other).is_err());
+    }
+
+    #[test]
+    fn verify_reports_other_state_at_recorded_frame() {
+        let mut movie = Movie::new(header());
+        movie.checksums.push(Checksum { frame: 60, hash: 1 });
+
+        assert!(movie.verify(&Checksum { frame: 60, hash: 1 }).is_ok());
+        assert!(movie.verify(&Checksum { frame: 120, hash: 2 }).is_ok());
+        assert!(matches!(
+            movie.verify(&Checksum { frame: 60, hash: 2 }),
+            Err(Errors::MovieDesync { frame: 60 })
+        ));
+    }
+
+    #[test]
+    fn validate_rejects_other_version() {
+        let mut movie = Movie::new(header());
+        movie.header.version = MOVIE_VERSION + 1;
+
+        assert!(movie.validate(&header()).is_err());
+    }
+
+    #[test]
+    fn apply_seed_keeps_a_configured_seed() {
+        let mut movie = Movie::new(header());
+        movie.header.seed = Some(7);
+
+        let mut config = Config::default();
+        movie.apply_seed(&mut config);
+        assert_eq!(config.seed, Some(7));
+
+        config.seed = Some(8);
+        movie.apply_seed(&mut config);
+        assert_eq!(config.seed, Some(8));
+    }
+}
@@ -0,0 +1,111 @@
+use super::input::Input;
+
+// the pressed keys of a movie, one bitmask per 60hz frame of the VM starting at the reset
+// the keys are latched at the start of every frame, so the VM sees the same input while recording and playing back
+pub struct MovieInput {
+    playing: bool,
+    // the frames to play back or the recorded frames which were not taken yet
+    frames: Vec<u16>,
+    mask: u16,
+    // keys pressed at the start of the current frame which were not taken by FX0A yet
+    unread_presses: Vec<u8>,
+}
+
+impl MovieInput {
+    pub fn recording() -> MovieInput {
+        MovieInput {
+            playing: false,
+            frames: Vec::new(),
+            mask: 0,
+            unread_presses: Vec::new(),
+        }
+    }
+
+    pub fn playing(frames: Vec<u16>) -> MovieInput {
+        MovieInput {
+            playing: true,
+            frames,
+            mask: 0,
+            unread_presses: Vec::new(),
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.playing == false
+    }
+
+    // called at the start of every frame, keyboard is the mask of the keys which are held while recording
+    pub fn start_frame(&mut self, frame: u64, keyboard: u16) {
+        let mask = match self.playing {
+            true => self.frames.get(frame as usize).copied().unwrap_or(0),
+            false => {
+                self.frames.push(keyboard);
+                keyboard
+            }
+        };
+
+        let previous = self.mask;
+        self.unread_presses = (0..16u8).filter(|x| mask & 1 << x > 0 && previous & 1 << x == 0).collect();
+        self.mask = mask;
+    }
+
+    // the playback ran out of frames at the frame
+    pub fn is_finished(&self, frame: u64) -> bool {
+        self.playing && frame as usize >= self.frames.len()
+    }
+
+    // the frames recorded since the last call
+    pub fn take_recorded(&mut self) -> Vec<u16> {
+        match self.playing {
+            true => Vec::new(),
+            false => self.frames.drain(..).collect(),
+        }
+    }
+}
+
+impl Input for MovieInput {
+    fn is_pressed(&self, key: u8) -> bool {
+        self.mask & 1 << key > 0
+    }
+
+    fn take_pressed_key(&mut self) -> Option<u8> {
+        match self.unread_presses.is_empty() {
+            true => None,
+            false => Some(self.unread_presses.remove(0)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recording_latches_the_keyboard_per_frame() {
+        let mut input = MovieInput::recording();
+        input.start_frame(0, 0b0001);
+        input.start_frame(1, 0b0011);
+
+        assert!(input.is_pressed(0));
+        assert!(input.is_pressed(1));
+        assert_eq!(input.take_pressed_key(), Some(1));
+        assert_eq!(input.take_pressed_key(), None);
+        assert_eq!(input.take_recorded(), vec![0b0001, 0b0011]);
+        assert_eq!(input.take_recorded(), vec![]);
+    }
+
+    #[test]
+    fn playing_replaces_the_keyboard_until_the_frames_end() {
+        let mut input = MovieInput::playing(vec![0b0100, 0]);
+        input.start_frame(0, 0b1000);
+
+        assert!(input.is_pressed(2));
+        assert!(input.is_pressed(3) == false);
+        assert_eq!(input.take_pressed_key(), Some(2));
+
+        input.start_frame(1, 0);
+        assert!(input.is_pressed(2) == false);
+        assert!(input.is_finished(1) == false);
+        assert!(input.is_finished(2));
+    }
+}
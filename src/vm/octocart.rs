@@ -0,0 +1,246 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use anyhow::Result;
+use gif::{ColorOutput, SetParameter};
+use log::info;
+use serde::Deserialize;
+
+use super::config::{Config, RomColors, RomSettings};
+use super::quirks::Quirks;
+use crate::errors::Errors;
+
+// Octo cartridge: a gif whose frames hide the program source and the options in the two low bits of every pixel,
+// four pixels form a byte, the first four bytes are the big-endian length of the JSON payload which follows them
+#[derive(Debug, Clone, Deserialize)]
+pub struct Octocart {
+    pub program: String,
+    #[serde(default)]
+    pub options: OctoOptions,
+}
+
+// the subset of the Octo options this VM understands, the quirks are named the way Octo names them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OctoOptions {
+    // cycles per 60hz frame
+    pub tickrate: Option<u32>,
+    // 8XY6 and 8XYE shift VX in place
+    pub shift_quirks: Option<bool>,
+    pub load_store_quirks: Option<bool>,
+    pub jump_quirks: Option<bool>,
+    pub logic_quirks: Option<bool>,
+    // sprites are clipped instead of wrapped
+    pub clip_quirks: Option<bool>,
+    // DXYN waits for the display refresh
+    pub v_blank_quirks: Option<bool>,
+    pub background_color: Option<String>,
+    pub fill_color: Option<String>,
+}
+
+pub fn is_octocart(path: &str) -> bool {
+    Path::new(path)
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.eq_ignore_ascii_case("gif"))
+        .unwrap_or(false)
+}
+
+impl Octocart {
+    pub fn load(path: &str) -> Result<Octocart> {
+        let invalid = |message: String| Errors::OctocartInvalid {
+            name: String::from(path),
+            message,
+        };
+
+        let bytes = fs::read(path).map_err(|error| Errors::RomLoadFailed {
+            name: String::from(path),
+            error,
+        })?;
+
+        let mut decoder = gif::Decoder::new(bytes.as_slice());
+        decoder.set(ColorOutput::Indexed);
+
+        let mut reader = decoder.read_info().map_err(|x| invalid(x.to_string()))?;
+        let mut frames = Vec::new();
+        while let Some(frame) = reader.read_next_frame().map_err(|x| invalid(x.to_string()))? {
+            frames.push(frame.buffer.to_vec());
+        }
+
+        let payload = extract_payload(&frames).ok_or_else(|| invalid(String::from("payload is truncated")))?;
+        let cart = serde_json::from_slice::<Octocart>(&payload).map_err(|x| invalid(x.to_string()))?;
+
+        Ok(cart)
+    }
+
+    // the embedded options as settings of the ROM
+    pub fn get_rom_settings(&self) -> RomSettings {
+        let options = &self.options;
+
+        // Octo turns every quirk off which is not set
+        let quirks = [
+            options.shift_quirks,
+            options.load_store_quirks,
+            options.jump_quirks,
+            options.logic_quirks,
+            options.clip_quirks,
+            options.v_blank_quirks,
+        ];
+
+        let quirks = match quirks.iter().all(|x| x.is_none()) {
+            true => None,
+            false => Some(Quirks {
+                shift_uses_vy: options.shift_quirks.unwrap_or(false) == false,
+                load_store_keeps_i: options.load_store_quirks.unwrap_or(false),
+                jump_uses_vx: options.jump_quirks.unwrap_or(false),
+                logic_resets_vf: options.logic_quirks.unwrap_or(false),
+                sprites_wrap: options.clip_quirks.unwrap_or(false) == false,
+                display_wait: options.v_blank_quirks.unwrap_or(false),
+                await_key_release: false,
+            }),
+        };
+
+        let colors = match (&options.background_color, &options.fill_color) {
+            (Some(background), Some(foreground)) => Some(RomColors {
+                background: background.clone(),
+                foreground: foreground.clone(),
+            }),
+            _ => None,
+        };
+
+        RomSettings {
+            hz: options.tickrate.map(|x| x as u128 * 60),
+            quirks,
+            colors,
+            ..RomSettings::default()
+        }
+    }
+
+    // programs which only consist of bytes are read directly, everything else needs the Octo compiler
+    pub fn assemble(&self, config: &Config) -> Result<Vec<u8>> {
+        if let Some(bytes) = parse_bytes(&self.program) {
+            return Ok(bytes);
+        }
+
+        let compiler = config.octo_compiler.as_ref().ok_or_else(|| Errors::OctocartInvalid {
+            name: config.rom.clone(),
+            message: String::from("the program is Octo source, set octo_compiler to compile it"),
+        })?;
+
+        let source = config.get_rom_data_path("8o");
+        let output = config.get_rom_data_path("ch8");
+        fs::create_dir_all("data")?;
+        fs::write(&source, &self.program)?;
+
+        let status = Command::new(compiler).arg(&source).arg(&output).status()?;
+        if status.success() == false {
+            return Err(Errors::OctocartInvalid {
+                name: config.rom.clone(),
+                message: format!("{} exited with {}", compiler, status),
+            }
+            .into());
+        }
+
+        info!("Compiled {} with {}", config.rom, compiler);
+        Ok(fs::read(&output)?)
+    }
+}
+
+// joins the two low bits of every four pixels into a byte and cuts the payload out of them
+fn extract_payload(frames: &[Vec<u8>]) -> Option<Vec<u8>> {
+    let bytes: Vec<u8> = frames
+        .iter()
+        .flat_map(|frame| frame.chunks_exact(4))
+        .map(|x| (x[0] & 3) << 6 | (x[1] & 3) << 4 | (x[2] & 3) << 2 | (x[3] & 3))
+        .collect();
+
+    let size = bytes.get(0..4)?.iter().fold(0usize, |size, x| size << 8 | *x as usize);
+    bytes.get(4..4 + size).map(|x| x.to_vec())
+}
+
+// a program written as a plain list of numbers, optionally below the main label
+fn parse_bytes(program: &str) -> Option<Vec<u8>> {
+    let tokens: Vec<&str> = program
+        .lines()
+        .map(|line| line.split('#').next().unwrap_or(""))
+        .flat_map(|line| line.split_whitespace())
+        .collect();
+
+    let numbers = match tokens.starts_with(&[":", "main"]) {
+        true => &tokens[2..],
+        false => &tokens[..],
+    };
+
+    numbers
+        .iter()
+        .map(|token| match token {
+            _ if token.starts_with("0x") => u8::from_str_radix(&token[2..], 16).ok(),
+            _ if token.starts_with("0b") => u8::from_str_radix(&token[2..], 2).ok(),
+            _ => token.parse::<u8>().ok(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crumbs(bytes: &[u8]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|x| vec![x >> 6 | 0x40, x >> 4 & 3, x >> 2 & 3 | 0x10, x & 3])
+            .collect()
+    }
+
+    #[test]
+    fn extract_payload_reads_low_bits_across_frames() {
+        let data = crumbs(&[0, 0, 0, 3, b'{', b'}', b'!', 0xFF]);
+        let frames = vec![data[..12].to_vec(), data[12..].to_vec()];
+
+        assert_eq!(extract_payload(&frames), Some(b"{}!".to_vec()));
+    }
+
+    #[test]
+    fn extract_payload_rejects_truncated_data() {
+        assert_eq!(extract_payload(&[crumbs(&[0, 0, 0, 9, 1])]), None);
+    }
+
+    #[test]
+    fn parse_bytes_reads_number_listing() {
+        let program = ": main\n  0x00 0xE0 # clear\n  0b0001 18\n";
+
+        assert_eq!(parse_bytes(program), Some(vec![0x00, 0xE0, 0x01, 0x12]));
+        assert_eq!(parse_bytes(": main\n  clear\n"), None);
+    }
+
+    #[test]
+    fn rom_settings_translate_octo_quirks() {
+        let cart = Octocart {
+            program: String::new(),
+            options: OctoOptions {
+                tickrate: Some(20),
+                shift_quirks: Some(true),
+                load_store_quirks: Some(true),
+                ..OctoOptions::default()
+            },
+        };
+
+        let settings = cart.get_rom_settings();
+
+        assert_eq!(settings.hz, Some(1200));
+        assert_eq!(
+            settings.quirks,
+            Some(Quirks {
+                shift_uses_vy: false,
+                load_store_keeps_i: true,
+                jump_uses_vx: false,
+                logic_resets_vf: false,
+                sprites_wrap: true,
+                display_wait: false,
+                await_key_release: false,
+            })
+        );
+        assert!(settings.colors.is_none());
+    }
+}
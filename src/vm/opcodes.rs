@@ -8,11 +8,18 @@ pub(super) enum OpCode {
 
     Disp_Clear,
     Disp                    { x: usize, y: usize, n: u8 },
+    Disp_Large              { x: usize, y: usize },
+    Disp_Lores,
+    Disp_Hires,
+    Disp_Scroll_Down        { n: u8 },
+    Disp_Scroll_Right,
+    Disp_Scroll_Left,
 
     Flow_Return,
     Flow_Jump               { nnn: u16 },
     Flow_Call               { nnn: u16 },
     Flow_Jump_Offset        { nnn: u16 },
+    Flow_Exit,
 
     Cond_Eq_Const           { x: usize, nn: u8 },
     Cond_Neq_Const          { x: usize, nn: u8 },
@@ -35,10 +42,14 @@ pub(super) enum OpCode {
     Math_Minus_Reverse      { x: usize, y: usize },
 
     MEM_Set_I               { nnn: u16 },
+    MEM_Set_I_Long,
     MEM_Add_I               { x: usize },
     MEM_Set_Sprite_I        { x: usize },
+    MEM_Set_Large_Sprite_I  { x: usize },
     MEM_Reg_Dump            { x: usize },
     MEM_Reg_Load            { x: usize },
+    MEM_Flags_Dump          { x: usize },
+    MEM_Flags_Load          { x: usize },
 
     Rand                    { x: usize, nn: u8 },
 
@@ -53,3 +64,50 @@ pub(super) enum OpCode {
     KeyOp_Skip_Not_Pressed  { x: usize },
     KeyOp_Await             { x: usize },
 }
+
+impl OpCode {
+    // the names of all variants except Unknown, in the order they are declared
+    pub(super) const NAMES: &'static [&'static str] = &[
+        "Raw_Call",
+        "Disp_Clear", "Disp", "Disp_Large", "Disp_Lores", "Disp_Hires", "Disp_Scroll_Down", "Disp_Scroll_Right", "Disp_Scroll_Left",
+        "Flow_Return", "Flow_Jump", "Flow_Call", "Flow_Jump_Offset", "Flow_Exit",
+        "Cond_Eq_Const", "Cond_Neq_Const", "Cond_Eq_Reg", "Cond_Neq_Reg",
+        "Const_Set_Reg", "Const_Add_Reg",
+        "Assign",
+        "BitOp_Or", "BitOp_And", "BitOp_Xor", "BitOp_Shift_Right", "BitOp_Shift_Left",
+        "Math_Add", "Math_Minus", "Math_Minus_Reverse",
+        "MEM_Set_I", "MEM_Set_I_Long", "MEM_Add_I", "MEM_Set_Sprite_I", "MEM_Set_Large_Sprite_I",
+        "MEM_Reg_Dump", "MEM_Reg_Load", "MEM_Flags_Dump", "MEM_Flags_Load",
+        "Rand",
+        "BCD",
+        "Timer_Delay_Get", "Timer_Delay_Set",
+        "Sound_Set",
+        "KeyOp_Skip_Pressed", "KeyOp_Skip_Not_Pressed", "KeyOp_Await",
+    ];
+
+    // the name of the variant without the operands, e.g. Disp_Large
+    pub(super) fn name(&self) -> String {
+        let debug = format!("{:?}", self);
+        debug.split(' ').next().unwrap_or_default().to_string()
+    }
+
+    // the address a jump, call or I points to
+    pub(super) fn target(&self) -> Option<u16> {
+        match *self {
+            OpCode::Raw_Call { nnn }
+            | OpCode::Flow_Jump { nnn }
+            | OpCode::Flow_Call { nnn }
+            | OpCode::Flow_Jump_Offset { nnn }
+            | OpCode::MEM_Set_I { nnn } => Some(nnn),
+            _ => None,
+        }
+    }
+
+    // a class is a variant or the prefix of variants, Disp includes Disp_Clear and Disp_Large
+    pub(super) fn is_in_class(&self, class: &str) -> bool {
+        let name = self.name().to_lowercase();
+        let class = class.to_lowercase();
+
+        name == class || name.starts_with(&format!("{}_", class))
+    }
+}
@@ -0,0 +1,96 @@
+use std::ops::Range;
+
+use anyhow::Result;
+use log::info;
+
+use super::config::Config;
+
+// extension of the VM which claims opcodes or memory ranges, e.g. a virtual serial port or a second display
+// custom extensions can be prototyped by implementing this trait and adding them to from_config
+pub trait Peripheral: Send {
+    fn name(&self) -> &str;
+
+    // claimed opcodes are executed by the peripheral instead of the VM, the PC is advanced afterwards
+    fn claims_opcode(&self, _opcode: u16) -> bool {
+        false
+    }
+
+    // the registers and the memory of the VM can be read and changed while the opcode executes
+    fn execute(&mut self, _opcode: u16, _registers: &mut [u8], _memory: &mut [u8]) -> Result<()> {
+        Ok(())
+    }
+
+    // writes into this range are reported after the instruction which made them
+    fn memory_range(&self) -> Option<Range<usize>> {
+        None
+    }
+
+    fn memory_written(&mut self, _address: usize, _value: u8) {}
+}
+
+pub fn from_config(config: &Config) -> Vec<Box<dyn Peripheral>> {
+    let mut peripherals: Vec<Box<dyn Peripheral>> = Vec::new();
+
+    if let Some(address) = config.serial_port {
+        peripherals.push(Box::new(SerialPort::new(address as usize)));
+    }
+
+    for peripheral in peripherals.iter() {
+        info!("Attached peripheral {}", peripheral.name());
+    }
+
+    peripherals
+}
+
+// logs the bytes a ROM writes to a single address as lines of text
+pub struct SerialPort {
+    address: usize,
+    line: String,
+}
+
+impl SerialPort {
+    pub fn new(address: usize) -> SerialPort {
+        SerialPort {
+            address,
+            line: String::new(),
+        }
+    }
+}
+
+impl Peripheral for SerialPort {
+    fn name(&self) -> &str {
+        "serial port"
+    }
+
+    fn memory_range(&self) -> Option<Range<usize>> {
+        Some(self.address..self.address + 1)
+    }
+
+    fn memory_written(&mut self, _address: usize, value: u8) {
+        match value {
+            b'\n' => {
+                info!("Serial: {}", self.line);
+                self.line.clear();
+            }
+            _ => self.line.push(value as char),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serial_port_collects_line() {
+        let mut port = SerialPort::new(0xE00);
+
+        for value in b"HI" {
+            port.memory_written(0xE00, *value);
+        }
+        assert_eq!(port.line, "HI");
+
+        port.memory_written(0xE00, b'\n');
+        assert!(port.line.is_empty());
+    }
+}
@@ -0,0 +1,129 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Display, EnumString)]
+pub enum Platform {
+    Chip8,
+    SuperChip,
+    XoChip,
+}
+
+impl Default for Platform {
+    fn default() -> Platform {
+        Platform::Chip8
+    }
+}
+
+// a distinctive extension wins, otherwise the opcodes decide, None if nothing hints at a platform
+// .ch8 and .c8 are used for ROMs of every platform, so they leave the decision to the config and the ROM database
+pub fn detect(path: &str, rom: &[u8], load_address: u16) -> Option<Platform> {
+    let extension = Path::new(path)
+        .extension()
+        .and_then(|x| x.to_str())
+        .map(|x| x.to_lowercase());
+
+    match extension.as_ref().map(|x| x.as_str()) {
+        Some("sc8") => Some(Platform::SuperChip),
+        Some("xo8") => Some(Platform::XoChip),
+        _ => detect_from_opcodes(rom, load_address),
+    }
+}
+
+// only the instructions reachable from the start are looked at, because sprite data can look like any opcode
+fn detect_from_opcodes(rom: &[u8], load_address: u16) -> Option<Platform> {
+    let mut platform = None;
+
+    for (_, opcode) in reachable_opcodes(rom, load_address as usize) {
+        let is_xo_chip = match opcode {
+            // long I load and audio pattern
+            0xF000 | 0xF002 => true,
+            // plane selection and pitch
+            _ if opcode & 0xF0FF == 0xF001 || opcode & 0xF0FF == 0xF03A => true,
+            // save and load register ranges
+            _ if opcode & 0xF00F == 0x5002 || opcode & 0xF00F == 0x5003 => true,
+            // scroll up
+            _ if opcode & 0xFFF0 == 0x00D0 => true,
+            _ => false,
+        };
+
+        // XO-CHIP extends SUPER-CHIP, so it wins over it
+        if is_xo_chip {
+            return Some(Platform::XoChip);
+        }
+
+        let is_super_chip = match opcode {
+            // scroll, exit, lores and hires
+            0x00FB | 0x00FC | 0x00FD | 0x00FE | 0x00FF => true,
+            _ if opcode & 0xFFF0 == 0x00C0 => true,
+            // big font and flag registers
+            _ if opcode & 0xF0FF == 0xF030 || opcode & 0xF0FF == 0xF075 || opcode & 0xF0FF == 0xF085 => true,
+            _ => false,
+        };
+
+        if is_super_chip {
+            platform = Some(Platform::SuperChip);
+        }
+    }
+
+    platform
+}
+
+// follows jumps, calls and skips from the load address, computed jumps (BNNN) are not followed
+// returns the opcodes with their address, ordered by address
+pub(super) fn reachable_opcodes(rom: &[u8], load_address: usize) -> Vec<(usize, u16)> {
+    let mut visited = HashSet::new();
+    let mut pending = vec![load_address];
+    let mut opcodes = Vec::new();
+
+    while let Some(address) = pending.pop() {
+        let offset = match address.checked_sub(load_address) {
+            Some(offset) if offset + 1 < rom.len() => offset,
+            _ => continue,
+        };
+
+        if visited.insert(address) == false {
+            continue;
+        }
+
+        let opcode = (rom[offset] as u16) << 8 | rom[offset + 1] as u16;
+        let target = (opcode & 0x0FFF) as usize;
+        opcodes.push((address, opcode));
+
+        match opcode >> 12 {
+            _ if opcode == 0x00EE || opcode == 0x00FD => (),
+            0x1 => pending.push(target),
+            0x2 => pending.extend(&[target, address + 2]),
+            0xB => (),
+            0x3 | 0x4 => pending.extend(&[address + 2, address + 4]),
+            0x5 | 0x9 if opcode & 0x000F == 0 => pending.extend(&[address + 2, address + 4]),
+            0xE if opcode & 0x00FF == 0x9E || opcode & 0x00FF == 0xA1 => pending.extend(&[address + 2, address + 4]),
+            // the address of the long I load follows the opcode
+            _ if opcode == 0xF000 => pending.push(address + 4),
+            _ => pending.push(address + 2),
+        }
+    }
+
+    opcodes.sort();
+    opcodes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("roms/GAME.sc8", &[0x12, 0x00], Some(Platform::SuperChip) ; "super chip extension")]
+    #[test_case("roms/GAME.XO8", &[0x12, 0x00], Some(Platform::XoChip) ; "xo chip extension")]
+    #[test_case("roms/GAME.ch8", &[0x12, 0x00], None ; "chip 8 extension")]
+    #[test_case("roms/GAME.ch8", &[0x00, 0xFF, 0xF0, 0x30], Some(Platform::SuperChip) ; "opcodes override chip 8 extension")]
+    #[test_case("roms/GAME", &[0x00, 0xFF, 0x00, 0xFE], Some(Platform::SuperChip) ; "super chip opcodes")]
+    #[test_case("roms/GAME", &[0xF0, 0x00, 0x02, 0x00, 0x51, 0x22], Some(Platform::XoChip) ; "xo chip opcodes")]
+    #[test_case("roms/GAME", &[0x12, 0x04, 0x00, 0xFF, 0x12, 0x04], None ; "unreachable opcode")]
+    #[test_case("roms/GAME", &[0x12, 0x00], None ; "plain rom")]
+    fn detect_platform(path: &str, rom: &[u8], expected: Option<Platform>) {
+        assert_eq!(detect(path, rom, 0x200), expected);
+    }
+}
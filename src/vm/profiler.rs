@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+// number of addresses and opcodes in a report unless a different number is asked for
+pub const REPORT_SIZE: usize = 10;
+
+// counts the executed instructions per address and per raw opcode, the opcodes are grouped by their name in the report
+#[derive(Debug, Default)]
+pub struct Profiler {
+    addresses: Vec<u64>,
+    opcodes: HashMap<u16, u64>,
+    executed: u64,
+}
+
+impl Profiler {
+    pub fn new(memory_size: usize) -> Profiler {
+        Profiler {
+            addresses: vec![0; memory_size],
+            opcodes: HashMap::new(),
+            executed: 0,
+        }
+    }
+
+    pub fn record(&mut self, address: u16, raw_opcode: u16) {
+        if let Some(count) = self.addresses.get_mut(address as usize) {
+            *count += 1;
+        }
+
+        *self.opcodes.entry(raw_opcode).or_insert(0) += 1;
+        self.executed += 1;
+    }
+
+    pub fn executed(&self) -> u64 {
+        self.executed
+    }
+
+    // the n most executed addresses, the most executed first
+    pub fn hot_addresses(&self, n: usize) -> Vec<(u16, u64)> {
+        let mut addresses: Vec<(u16, u64)> = self
+            .addresses
+            .iter()
+            .enumerate()
+            .filter(|(_, count)| **count > 0)
+            .map(|(address, count)| (address as u16, *count))
+            .collect();
+
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses.truncate(n);
+        addresses
+    }
+
+    // sums the counts of the raw opcodes with the same name, the most executed first
+    pub fn opcode_counts<F: Fn(u16) -> String>(&self, name: F) -> Vec<(String, u64)> {
+        let mut counts = HashMap::<String, u64>::new();
+        for (raw_opcode, count) in &self.opcodes {
+            *counts.entry(name(*raw_opcode)).or_insert(0) += count;
+        }
+
+        let mut counts: Vec<(String, u64)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_addresses_and_opcodes() {
+        let mut profiler = Profiler::new(0x1000);
+        profiler.record(0x202, 0x7001);
+        profiler.record(0x204, 0x7102);
+        profiler.record(0x202, 0x7001);
+        profiler.record(0x200, 0x00E0);
+
+        assert_eq!(profiler.executed(), 4);
+        assert_eq!(profiler.hot_addresses(2), vec![(0x202, 2), (0x200, 1)]);
+
+        let name = |x: u16| String::from(if x & 0xF000 == 0x7000 { "Add" } else { "Clear" });
+        assert_eq!(
+            profiler.opcode_counts(name),
+            vec![(String::from("Add"), 3), (String::from("Clear"), 1)]
+        );
+    }
+}
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use super::platform::reachable_opcodes;
+
+// number of instructions after a register dump or load which are searched for the next use of I
+const LOOKAHEAD: usize = 4;
+
+// behaviours in which interpreters differ, the defaults are what this VM always did
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Quirks {
+    // 8XY6 and 8XYE shift VY into VX instead of shifting VX
+    pub shift_uses_vy: bool,
+    // FX55 and FX65 leave I unchanged instead of advancing it
+    pub load_store_keeps_i: bool,
+    // BXNN jumps to XNN + VX instead of NNN + V0
+    pub jump_uses_vx: bool,
+    // 8XY1, 8XY2 and 8XY3 reset VF
+    pub logic_resets_vf: bool,
+    // sprites drawn over an edge continue on the opposite side instead of being clipped
+    pub sprites_wrap: bool,
+    // DXYN waits for the next 60hz display refresh, at most one sprite is drawn per frame
+    pub display_wait: bool,
+    // FX0A waits until the pressed key is released again instead of resuming on the press
+    pub await_key_release: bool,
+}
+
+impl fmt::Display for Quirks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&str> = [
+            (self.shift_uses_vy, "shift_uses_vy"),
+            (self.load_store_keeps_i, "load_store_keeps_i"),
+            (self.jump_uses_vx, "jump_uses_vx"),
+            (self.logic_resets_vf, "logic_resets_vf"),
+            (self.sprites_wrap, "sprites_wrap"),
+            (self.display_wait, "display_wait"),
+            (self.await_key_release, "await_key_release"),
+        ]
+        .iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, name)| *name)
+        .collect();
+
+        // movies recorded before quirks were configurable name the default behaviour chip8
+        match names.is_empty() {
+            true => write!(f, "chip8"),
+            false => write!(f, "{}", names.join("+")),
+        }
+    }
+}
+
+enum IUse {
+    LoadStore,
+    AddI,
+}
+
+// guesses the quirks a ROM relies on from its reachable code, quirks without evidence keep the value of base
+pub fn detect(rom: &[u8], load_address: u16, base: Quirks) -> Quirks {
+    let opcodes = reachable_opcodes(rom, load_address as usize);
+    let by_address: HashMap<usize, u16> = opcodes.iter().copied().collect();
+    let mut quirks = base;
+
+    // shifting from a register other than VX or V0 only makes sense if VY is shifted
+    let shifts_from_vy = opcodes.iter().any(|(_, opcode)| {
+        let (x, y) = ((opcode >> 8) & 0xF, (opcode >> 4) & 0xF);
+        is_shift(*opcode) && x != y && y != 0
+    });
+
+    if shifts_from_vy {
+        quirks.shift_uses_vy = true;
+    }
+
+    // computed jumps while V0 is never written only make sense if VX is added
+    let has_computed_jump = opcodes.iter().any(|(_, opcode)| opcode >> 12 == 0xB);
+    let writes_v0 = opcodes.iter().any(|(_, opcode)| writes_register(*opcode, 0));
+
+    if has_computed_jump && writes_v0 == false {
+        quirks.jump_uses_vx = true;
+    }
+
+    // a dump or load followed by another one without setting I relies on I being advanced,
+    // advancing I by hand with FX1E relies on it being kept
+    let (mut advances, mut keeps) = (0, 0);
+    for (address, _) in opcodes.iter().filter(|(_, opcode)| is_load_store(*opcode)) {
+        match next_i_use(&by_address, *address) {
+            Some(IUse::LoadStore) => advances += 1,
+            Some(IUse::AddI) => keeps += 1,
+            None => (),
+        }
+    }
+
+    if advances != keeps {
+        quirks.load_store_keeps_i = keeps > advances;
+    }
+
+    quirks
+}
+
+fn is_shift(opcode: u16) -> bool {
+    opcode >> 12 == 0x8 && (opcode & 0xF == 0x6 || opcode & 0xF == 0xE)
+}
+
+fn is_load_store(opcode: u16) -> bool {
+    opcode & 0xF0FF == 0xF055 || opcode & 0xF0FF == 0xF065
+}
+
+fn writes_register(opcode: u16, register: u16) -> bool {
+    let x = (opcode >> 8) & 0xF;
+
+    match opcode >> 12 {
+        0x6 | 0x7 | 0x8 | 0xC => x == register,
+        0xF if opcode & 0xFF == 0x07 || opcode & 0xFF == 0x0A => x == register,
+        // FX65 always loads V0
+        0xF if opcode & 0xFF == 0x65 => true,
+        _ => false,
+    }
+}
+
+// the next instruction in a straight line which uses I, None if I is set again or the line ends
+fn next_i_use(by_address: &HashMap<usize, u16>, address: usize) -> Option<IUse> {
+    for n in 1..=LOOKAHEAD {
+        let opcode = *by_address.get(&(address + n * 2))?;
+
+        match opcode {
+            _ if is_load_store(opcode) => return Some(IUse::LoadStore),
+            _ if opcode & 0xF0FF == 0xF01E => return Some(IUse::AddI),
+            _ if opcode >> 12 == 0xA || opcode >> 12 == 0x1 || opcode >> 12 == 0xB || opcode == 0x00EE => return None,
+            _ => (),
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn quirks(shift_uses_vy: bool, load_store_keeps_i: bool, jump_uses_vx: bool) -> Quirks {
+        Quirks {
+            shift_uses_vy,
+            load_store_keeps_i,
+            jump_uses_vx,
+            ..Quirks::default()
+        }
+    }
+
+    #[test_case(&[0x81, 0x26], quirks(true, false, false) ; "shift from vy")]
+    #[test_case(&[0x81, 0x06, 0x81, 0x1E], quirks(false, false, false) ; "shift vx")]
+    #[test_case(&[0x61, 0x02, 0xB1, 0x00], quirks(false, false, true) ; "jump without v0")]
+    #[test_case(&[0x60, 0x02, 0xB2, 0x00], quirks(false, false, false) ; "jump with v0")]
+    #[test_case(&[0xF3, 0x55, 0x71, 0x01, 0xF3, 0x55], quirks(false, false, false) ; "consecutive dumps")]
+    #[test_case(&[0xF3, 0x55, 0xF4, 0x1E, 0xF3, 0x55], quirks(false, true, false) ; "manual advance")]
+    #[test_case(&[0xF3, 0x55, 0xA3, 0x00, 0xF3, 0x55], quirks(false, false, false) ; "i set again")]
+    fn detect_quirks(rom: &[u8], expected: Quirks) {
+        assert_eq!(detect(rom, 0x200, Quirks::default()), expected);
+    }
+
+    #[test]
+    fn display_names_enabled_quirks() {
+        assert_eq!(Quirks::default().to_string(), "chip8");
+        assert_eq!(quirks(true, false, true).to_string(), "shift_uses_vy+jump_uses_vx");
+    }
+}
@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use log::info;
+
+use super::config::Config;
+use super::ips;
+use super::octocart::{self, Octocart};
+use crate::errors::Errors;
+
+// reads the configured ROM or the program of an Octocart and applies its IPS patch, if there is one
+pub fn load(config: &Config) -> Result<Vec<u8>> {
+    let mut bytes = match octocart::is_octocart(&config.rom) {
+        true => Octocart::load(&config.rom)?.assemble(config)?,
+        false => read(&config.rom)?,
+    };
+
+    if let Some(path) = get_ips_path(config) {
+        let patch = read(&path)?;
+
+        if let Err(error) = ips::apply(&mut bytes, &patch) {
+            return Err(Errors::IpsApplyFailed { name: path, error }.into());
+        }
+
+        info!("Applied IPS patch {}", path);
+    }
+
+    Ok(bytes)
+}
+
+// an explicitly configured patch or <rom>.ips next to the ROM
+fn get_ips_path(config: &Config) -> Option<String> {
+    if let Some(path) = config.get_rom_settings().and_then(|x| x.ips.clone()) {
+        return Some(path);
+    }
+
+    let path = format!("{}.ips", config.rom);
+    match Path::new(&path).is_file() {
+        true => Some(path),
+        false => None,
+    }
+}
+
+fn read(path: &str) -> Result<Vec<u8>> {
+    match fs::read(path) {
+        Ok(bytes) => Ok(bytes),
+        Err(err) => Err(Errors::RomLoadFailed {
+            name: String::from(path),
+            error: err,
+        }
+        .into()),
+    }
+}
@@ -0,0 +1,64 @@
+use log::warn;
+use serde::Deserialize;
+
+use super::hash::rom_hash;
+use super::platform::Platform;
+use super::quirks::Quirks;
+
+const DATABASE: &str = include_str!("../../rom_database.yml");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RomEntry {
+    pub name: String,
+    pub hash: String,
+    pub cycles_per_frame: u32,
+    #[serde(default)]
+    pub platform: Option<Platform>,
+    #[serde(default)]
+    pub quirks: Option<Quirks>,
+}
+
+// known ROMs and their recommended settings, embedded into the binary
+pub struct RomDatabase {
+    entries: Vec<RomEntry>,
+}
+
+impl RomDatabase {
+    pub fn load() -> RomDatabase {
+        let entries = match serde_yaml::from_str::<Vec<RomEntry>>(DATABASE) {
+            Ok(entries) => entries,
+            Err(err) => {
+                warn!("Failed to parse ROM database: {}", err);
+                Vec::new()
+            }
+        };
+
+        RomDatabase { entries }
+    }
+
+    pub fn find(&self, rom: &[u8]) -> Option<&RomEntry> {
+        let hash = rom_hash(rom);
+        self.entries.iter().find(|x| x.hash == hash)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn find_matches_rom_hash() {
+        let database = RomDatabase {
+            entries: vec![RomEntry {
+                name: String::from("TEST"),
+                hash: rom_hash(&[0x12, 0x00]),
+                cycles_per_frame: 30,
+                platform: None,
+                quirks: None,
+            }],
+        };
+
+        assert_eq!(database.find(&[0x12, 0x00]).map(|x| x.cycles_per_frame), Some(30));
+        assert!(database.find(&[0x13, 0x00]).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::Result;
+use log::{info, warn};
+
+use super::constants::RPL_FLAG_COUNT;
+
+// the HP48 kept the RPL user flags across programs, SUPER-CHIP games store settings and high scores in them
+pub type RplFlags = [u8; RPL_FLAG_COUNT];
+
+// missing or unreadable files start with cleared flags
+pub fn load(path: &Path) -> RplFlags {
+    match fs::read(path) {
+        Ok(bytes) => {
+            info!("Loaded RPL flags from {}", path.display());
+            from_bytes(&bytes)
+        }
+        Err(..) => [0u8; RPL_FLAG_COUNT],
+    }
+}
+
+pub fn save(path: &Path, flags: &RplFlags) {
+    if let Err(err) = write(path, flags) {
+        warn!("Failed to save RPL flags to {}: {}", path.display(), err);
+    }
+}
+
+fn write(path: &Path, flags: &RplFlags) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+
+    fs::write(path, flags)?;
+    Ok(())
+}
+
+// shorter files leave the remaining flags cleared, longer ones are cut
+fn from_bytes(bytes: &[u8]) -> RplFlags {
+    let mut flags = [0u8; RPL_FLAG_COUNT];
+    let count = bytes.len().min(RPL_FLAG_COUNT);
+    flags[..count].copy_from_slice(&bytes[..count]);
+
+    flags
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_bytes_pads_and_cuts() {
+        assert_eq!(from_bytes(&[1, 2, 3]), [1, 2, 3, 0, 0, 0, 0, 0]);
+        assert_eq!(from_bytes(&[9; 12]), [9; RPL_FLAG_COUNT]);
+    }
+}
@@ -0,0 +1,87 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::data_file;
+use crate::errors::Errors;
+
+pub const SAVE_STATE_VERSION: u32 = 1;
+
+// everything a ROM can observe, restoring it continues the ROM where it was saved
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SaveState {
+    pub version: u32,
+    pub rom_hash: String,
+    pub registers: Vec<u8>,
+    // return addresses, the innermost call last
+    pub stack: Vec<u16>,
+    pub memory: Vec<u8>,
+    pub pc: u16,
+    pub i: u16,
+    // remaining time of the timers in nanoseconds
+    pub delay_timer: u64,
+    pub sound_timer: u64,
+    // packed pixels, the resolution follows from the length
+    pub screen: Vec<u8>,
+    pub rpl_flags: Vec<u8>,
+}
+
+impl SaveState {
+    pub fn load(path: &Path) -> Result<SaveState> {
+        data_file::load(
+            path,
+            |bytes| Ok(serde_json::from_slice::<SaveState>(bytes)?),
+            |name, error| Errors::SaveStateLoadFailed { name, error },
+        )
+    }
+
+    // JSON instead of YAML like the other files, it keeps the memory on a single line
+    pub fn save(&self, path: &Path) -> Result<()> {
+        data_file::save(path, &serde_json::to_vec(self)?)
+    }
+
+    // a state of another ROM would continue with the wrong code
+    pub fn validate(&self, rom_hash: &str) -> std::result::Result<(), Errors> {
+        if self.version != SAVE_STATE_VERSION {
+            return Err(Errors::SaveStateVersionMismatch {
+                version: self.version,
+                expected: SAVE_STATE_VERSION,
+            });
+        }
+
+        if self.rom_hash != rom_hash {
+            return Err(Errors::SaveStateRomMismatch);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> SaveState {
+        SaveState {
+            version: SAVE_STATE_VERSION,
+            rom_hash: String::from("abc"),
+            registers: vec![0; 16],
+            stack: Vec::new(),
+            memory: vec![0; 4096],
+            pc: 0x200,
+            i: 0,
+            delay_timer: 0,
+            sound_timer: 0,
+            screen: vec![0; 256],
+            rpl_flags: vec![0; 8],
+        }
+    }
+
+    #[test]
+    fn validate_rejects_other_rom_and_version() {
+        assert!(state().validate("abc").is_ok());
+        assert!(state().validate("def").is_err());
+        assert!(SaveState { version: 0, ..state() }.validate("abc").is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use super::constants::{HIRES_SCREEN_SIZE, HIRES_SCREEN_SIZE_X, HIRES_SCREEN_SIZE_Y, SCREEN_SIZE, SCREEN_SIZE_X, SCREEN_SIZE_Y};
+
+const SCREEN_SIZE_BYTES: usize = SCREEN_SIZE / 8;
+const HIRES_SCREEN_SIZE_BYTES: usize = HIRES_SCREEN_SIZE / 8;
+
+// monochrome screen with one bit per pixel, rows are packed like sprites with the leftmost pixel in the highest bit
+// the debugger keeps a screen per executed instruction so the packing keeps its history small
+// a low-res screen only uses the first SCREEN_SIZE_BYTES of the bits
+#[derive(Clone, Copy)]
+pub struct Screen {
+    bits: [u8; HIRES_SCREEN_SIZE_BYTES],
+    hires: bool,
+}
+
+impl Screen {
+    pub fn new() -> Screen {
+        Screen {
+            bits: [0; HIRES_SCREEN_SIZE_BYTES],
+            hires: false,
+        }
+    }
+
+    // the resolution follows from the number of bytes, None if it matches neither
+    pub fn from_bytes(bytes: &[u8]) -> Option<Screen> {
+        let mut screen = Screen::new();
+
+        match bytes.len() {
+            SCREEN_SIZE_BYTES => screen.hires = false,
+            HIRES_SCREEN_SIZE_BYTES => screen.hires = true,
+            _ => return None,
+        }
+
+        screen.bits[..bytes.len()].copy_from_slice(bytes);
+
+        Some(screen)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bits[..self.width() * self.height() / 8]
+    }
+
+    // switching the resolution clears the screen
+    pub fn set_hires(&mut self, hires: bool) {
+        self.hires = hires;
+        self.clear();
+    }
+
+    pub fn width(&self) -> usize {
+        match self.hires {
+            true => HIRES_SCREEN_SIZE_X,
+            false => SCREEN_SIZE_X,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self.hires {
+            true => HIRES_SCREEN_SIZE_Y,
+            false => SCREEN_SIZE_Y,
+        }
+    }
+
+    pub fn is_set(&self, x: usize, y: usize) -> bool {
+        let (byte, mask) = self.locate(x, y);
+        self.bits[byte] & mask != 0
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: bool) {
+        let (byte, mask) = self.locate(x, y);
+
+        match value {
+            true => self.bits[byte] |= mask,
+            false => self.bits[byte] &= !mask,
+        }
+    }
+
+    // flips the pixel and returns whether it was set before
+    pub fn toggle(&mut self, x: usize, y: usize) -> bool {
+        let was_set = self.is_set(x, y);
+        self.set(x, y, !was_set);
+
+        was_set
+    }
+
+    pub fn clear(&mut self) {
+        self.bits = [0; HIRES_SCREEN_SIZE_BYTES];
+    }
+
+    // moves every pixel by dx, dy, pixels moved off the screen are lost
+    pub fn scroll(&mut self, dx: isize, dy: isize) {
+        let previous = *self;
+        self.clear();
+
+        for (x, y, _) in previous.iter_pixels().filter(|(_, _, is_set)| *is_set) {
+            let (x, y) = (x as isize + dx, y as isize + dy);
+
+            if x >= 0 && y >= 0 && (x as usize) < self.width() && (y as usize) < self.height() {
+                self.set(x as usize, y as usize, true);
+            }
+        }
+    }
+
+    // all pixels row by row as (x, y, is set)
+    pub fn iter_pixels(&self) -> impl Iterator<Item = (usize, usize, bool)> + '_ {
+        let width = self.width();
+
+        (0..width * self.height()).map(move |i| {
+            let (x, y) = (i % width, i / width);
+            (x, y, self.is_set(x, y))
+        })
+    }
+
+    fn locate(&self, x: usize, y: usize) -> (usize, u8) {
+        debug_assert!(x < self.width() && y < self.height());
+
+        let index = x + y * self.width();
+        (index / 8, 0x80 >> (index % 8))
+    }
+}
+
+impl PartialEq for Screen {
+    fn eq(&self, other: &Screen) -> bool {
+        self.hires == other.hires && self.bits[..] == other.bits[..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toggle_flips_single_pixel() {
+        let mut screen = Screen::new();
+
+        assert_eq!(screen.toggle(9, 3), false);
+        assert!(screen.is_set(9, 3));
+        assert!(!screen.is_set(8, 3));
+        assert!(!screen.is_set(9, 2));
+
+        assert_eq!(screen.toggle(9, 3), true);
+        assert!(screen == Screen::new());
+    }
+
+    #[test]
+    fn pixels_are_packed_msb_first() {
+        let mut screen = Screen::new();
+
+        screen.set(0, 0, true);
+        screen.set(SCREEN_SIZE_X - 1, 1, true);
+
+        assert_eq!(screen.as_bytes()[0], 0x80);
+        assert_eq!(screen.as_bytes()[2 * SCREEN_SIZE_X / 8 - 1], 0x01);
+        assert!(Screen::from_bytes(screen.as_bytes()) == Some(screen));
+        assert!(Screen::from_bytes(&[0; SCREEN_SIZE]).is_none());
+    }
+
+    #[test]
+    fn hires_screen_round_trips_bytes() {
+        let mut screen = Screen::new();
+        screen.set(3, 0, true);
+        screen.set_hires(true);
+
+        assert!(!screen.is_set(3, 0));
+        screen.set(HIRES_SCREEN_SIZE_X - 1, HIRES_SCREEN_SIZE_Y - 1, true);
+
+        assert_eq!(screen.as_bytes().len(), HIRES_SCREEN_SIZE_BYTES);
+        assert!(Screen::from_bytes(screen.as_bytes()) == Some(screen));
+        assert!(Screen::from_bytes(&[0; SCREEN_SIZE_BYTES]).map(|x| x.width()) == Some(SCREEN_SIZE_X));
+    }
+
+    #[test]
+    fn scroll_drops_pixels_moved_off_screen() {
+        let mut screen = Screen::new();
+        screen.set(1, 0, true);
+        screen.set(SCREEN_SIZE_X - 2, 5, true);
+
+        screen.scroll(4, 2);
+
+        let set: Vec<(usize, usize, bool)> = screen.iter_pixels().filter(|x| x.2).collect();
+        assert_eq!(set, vec![(5, 2, true)]);
+    }
+}
@@ -0,0 +1,23 @@
+use std::fmt;
+
+// a write into an address which was already executed as an instruction
+#[derive(Debug, Clone, PartialEq)]
+pub struct SelfModification {
+    // number of 60hz frames since the VM started
+    pub frame: u64,
+    pub address: u16,
+    pub previous: u8,
+    pub value: u8,
+    // address of the instruction which made the write
+    pub pc: u16,
+}
+
+impl fmt::Display for SelfModification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "frame {:>6}  address {:#05X}  {:#04X} -> {:#04X}  PC {:#05X}",
+            self.frame, self.address, self.previous, self.value, self.pc
+        )
+    }
+}
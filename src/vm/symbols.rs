@@ -0,0 +1,145 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{info, warn};
+
+use super::config::Config;
+
+// labels of a ROM, e.g. from the symbol file Octo writes next to the ROM
+#[derive(Debug, Clone, Default)]
+pub struct Symbols {
+    labels: BTreeMap<u16, String>,
+    addresses: HashMap<String, u16>,
+}
+
+// hex with 0x like the debugger prints them, or decimal
+pub fn parse_number(word: &str) -> Option<usize> {
+    match word.starts_with("0x") || word.starts_with("0X") {
+        true => usize::from_str_radix(&word[2..], 16).ok(),
+        false => word.parse().ok(),
+    }
+}
+
+pub fn parse_address(word: &str) -> Option<u16> {
+    parse_number(word).filter(|x| *x <= u16::MAX as usize).map(|x| x as u16)
+}
+
+impl Symbols {
+    // one label per line, e.g. "main 0x202", "0x202 main", ": main 0x202" or "main = 0x202", # starts a comment
+    // constants declared with :const are not addresses and are skipped
+    pub fn parse(text: &str) -> Result<Symbols, String> {
+        let mut symbols = Symbols::default();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.splitn(2, '#').next().unwrap_or_default().trim();
+            if line.is_empty() || line.starts_with(":const") {
+                continue;
+            }
+
+            let words: Vec<&str> = line
+                .split(|x: char| x.is_whitespace() || x == '=')
+                .map(|x| x.trim_start_matches(':'))
+                .filter(|x| x.is_empty() == false)
+                .collect();
+
+            let (name, address) = match words.as_slice() {
+                [first, second] => match (parse_address(first), parse_address(second)) {
+                    (_, Some(address)) => (*first, address),
+                    (Some(address), None) => (*second, address),
+                    (None, None) => return Err(format!("line {}: {} has no address", number + 1, line)),
+                },
+                _ => return Err(format!("line {}: expected a label and an address, got {}", number + 1, line)),
+            };
+
+            symbols.add(name, address);
+        }
+
+        Ok(symbols)
+    }
+
+    // the symbol file of the debugger config or the ROM with a .sym extension, missing files give no symbols
+    pub fn load(config: &Config) -> Symbols {
+        let path = match &config.debugger.symbols {
+            Some(path) => PathBuf::from(path),
+            None => Path::new(&config.rom).with_extension("sym"),
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(err) => {
+                if config.debugger.symbols.is_some() {
+                    warn!("Failed to read symbols {}: {}", path.display(), err);
+                }
+                return Symbols::default();
+            }
+        };
+
+        match Symbols::parse(&text) {
+            Ok(symbols) => {
+                info!("Loaded {} symbols from {}", symbols.labels.len(), path.display());
+                symbols
+            }
+            Err(err) => {
+                warn!("Failed to parse symbols {}: {}", path.display(), err);
+                Symbols::default()
+            }
+        }
+    }
+
+    pub fn add(&mut self, name: &str, address: u16) {
+        self.labels.insert(address, String::from(name));
+        self.addresses.insert(String::from(name), address);
+    }
+
+    pub fn label(&self, address: u16) -> Option<&str> {
+        self.labels.get(&address).map(|x| x.as_str())
+    }
+
+    // a label name or a number, hex with 0x or decimal
+    pub fn resolve(&self, text: &str) -> Option<u16> {
+        self.addresses.get(text).copied().or_else(|| parse_address(text))
+    }
+
+    // the closest label at or before the address, e.g. main_loop+4
+    pub fn locate(&self, address: u16) -> Option<String> {
+        let (label_address, name) = self.labels.range(..=address).next_back()?;
+
+        Some(match address - label_address {
+            0 => name.clone(),
+            offset => format!("{}+{}", name, offset),
+        })
+    }
+
+    // the address followed by its location, e.g. 0x206 (main_loop+4), only the address without labels
+    pub fn describe(&self, address: u16) -> String {
+        match self.locate(address) {
+            Some(location) => format!("{:#05X} ({})", address, location),
+            None => format!("{:#05X}", address),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_the_label_formats() {
+        let symbols = Symbols::parse("# generated\nmain 0x200\n0x20A draw_loop\n: sprite 0x300\nscore = 784\n:const speed 4\n").unwrap();
+
+        assert_eq!(symbols.label(0x200), Some("main"));
+        assert_eq!(symbols.resolve("draw_loop"), Some(0x20A));
+        assert_eq!(symbols.resolve("sprite"), Some(0x300));
+        assert_eq!(symbols.resolve("score"), Some(0x310));
+        assert_eq!(symbols.resolve("speed"), None);
+        assert_eq!(symbols.resolve("0x2A4"), Some(0x2A4));
+        assert_eq!(symbols.resolve("0x10000"), None);
+
+        assert_eq!(symbols.describe(0x20E), "0x20E (draw_loop+4)");
+        assert_eq!(symbols.describe(0x100), "0x100");
+
+        assert!(Symbols::parse("main").is_err());
+        assert!(Symbols::parse("main loop").is_err());
+    }
+}
@@ -0,0 +1,103 @@
+// length of the window over which the measurements are aggregated
+const WINDOW_NANO: u128 = 1_000_000_000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TelemetryReport {
+    pub target_hz: u128,
+    // cycles executed and skipped during the last window
+    pub executed: u64,
+    pub dropped: u64,
+    // average time in nanoseconds by which cycles ran late
+    pub average_jitter: u128,
+}
+
+// measures whether the VM keeps up with the requested hz
+pub struct Telemetry {
+    target_hz: u128,
+    elapsed: u128,
+    executed: u64,
+    dropped: u64,
+    jitter: u128,
+    report: Option<TelemetryReport>,
+}
+
+impl Telemetry {
+    pub fn new(target_hz: u128) -> Telemetry {
+        Telemetry {
+            target_hz,
+            elapsed: 0,
+            executed: 0,
+            dropped: 0,
+            jitter: 0,
+            report: None,
+        }
+    }
+
+    // lateness is the time which passed beyond the scheduled start of the cycle
+    pub fn record_cycle(&mut self, lateness: u128, tick_duration: u128) {
+        self.executed += 1;
+        self.jitter += lateness;
+
+        // the VM executes at most one cycle per tick, the remaining time is lost
+        if tick_duration > 0 {
+            self.dropped += (lateness / tick_duration) as u64;
+        }
+    }
+
+    pub fn advance(&mut self, delta: u128) {
+        self.elapsed += delta;
+
+        if self.elapsed >= WINDOW_NANO {
+            self.report = Some(TelemetryReport {
+                target_hz: self.target_hz,
+                executed: self.executed,
+                dropped: self.dropped,
+                average_jitter: match self.executed {
+                    0 => 0,
+                    n => self.jitter / n as u128,
+                },
+            });
+
+            self.elapsed = 0;
+            self.executed = 0;
+            self.dropped = 0;
+            self.jitter = 0;
+        }
+    }
+
+    // report of the last completed window, if it was not taken yet
+    pub fn take_report(&mut self) -> Option<TelemetryReport> {
+        self.report.take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_cycle_counts_dropped_cycles() {
+        let mut telemetry = Telemetry::new(1000);
+
+        telemetry.record_cycle(100, 1000);
+        telemetry.record_cycle(2500, 1000);
+        telemetry.advance(WINDOW_NANO);
+
+        let report = telemetry.take_report().unwrap();
+        assert_eq!(report.executed, 2);
+        assert_eq!(report.dropped, 2);
+        assert_eq!(report.average_jitter, 1300);
+    }
+
+    #[test]
+    fn advance_reports_once_per_window() {
+        let mut telemetry = Telemetry::new(1000);
+
+        telemetry.advance(WINDOW_NANO - 1);
+        assert_eq!(telemetry.take_report(), None);
+
+        telemetry.advance(1);
+        assert!(telemetry.take_report().is_some());
+        assert_eq!(telemetry.take_report(), None);
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::errors::Errors;
+
+// an executed instruction with the registers and I it changed
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub address: u16,
+    pub raw_opcode: u16,
+    // the decoded instruction or the name of the peripheral which executed it
+    pub decoded: String,
+    pub registers_before: Vec<u8>,
+    pub registers_after: Vec<u8>,
+    pub i_before: u16,
+    pub i_after: u16,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:#05X}  {:04X}  {}", self.address, self.raw_opcode, self.decoded)?;
+
+        let changes = self.registers_before.iter().zip(self.registers_after.iter()).enumerate();
+        for (i, (before, after)) in changes.filter(|(_, (before, after))| before != after) {
+            write!(f, "  V{:X} {:#04X} -> {:#04X}", i, before, after)?;
+        }
+
+        if self.i_before != self.i_after {
+            write!(f, "  I {:#05X} -> {:#05X}", self.i_before, self.i_after)?;
+        }
+
+        Ok(())
+    }
+}
+
+// writes one line per executed instruction, the file is complete once the trace is dropped
+pub struct InstructionTrace {
+    writer: BufWriter<File>,
+}
+
+impl InstructionTrace {
+    pub fn create(path: &Path) -> Result<InstructionTrace> {
+        let create = || -> std::io::Result<File> {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+
+            File::create(path)
+        };
+
+        match create() {
+            Ok(file) => Ok(InstructionTrace {
+                writer: BufWriter::new(file),
+            }),
+            Err(error) => Err(Errors::TraceCreateFailed {
+                name: path.display().to_string(),
+                error,
+            }
+            .into()),
+        }
+    }
+
+    pub fn record(&mut self, entry: &TraceEntry) -> std::io::Result<()> {
+        writeln!(self.writer, "{}", entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entry_lists_changed_registers_and_i() {
+        let mut entry = TraceEntry {
+            address: 0x202,
+            raw_opcode: 0x6A02,
+            decoded: String::from("Const { x: 10, value: 2 }"),
+            registers_before: vec![0; 16],
+            registers_after: vec![0; 16],
+            i_before: 0x300,
+            i_after: 0x300,
+        };
+        assert_eq!(entry.to_string(), "0x202  6A02  Const { x: 10, value: 2 }");
+
+        entry.registers_after[0xA] = 2;
+        entry.registers_after[0xF] = 1;
+        entry.i_after = 0x2A0;
+        assert_eq!(
+            entry.to_string(),
+            "0x202  6A02  Const { x: 10, value: 2 }  VA 0x00 -> 0x02  VF 0x00 -> 0x01  I 0x300 -> 0x2A0"
+        );
+    }
+}
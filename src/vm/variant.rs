@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use strum_macros::{Display, EnumString};
+
+use super::platform::Platform;
+use super::quirks::Quirks;
+
+// interpreters whose platform, quirks and speed are set at once, so the individual flags do not have to be known
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Display, EnumString)]
+pub enum Variant {
+    // the original interpreter of the COSMAC VIP
+    Chip8,
+    // the HP48 port which SUPER-CHIP is based on
+    Chip48,
+    SuperChip,
+    XoChip,
+}
+
+impl Variant {
+    pub fn platform(self) -> Platform {
+        match self {
+            Variant::Chip8 | Variant::Chip48 => Platform::Chip8,
+            Variant::SuperChip => Platform::SuperChip,
+            Variant::XoChip => Platform::XoChip,
+        }
+    }
+
+    pub fn quirks(self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks {
+                shift_uses_vy: true,
+                logic_resets_vf: true,
+                display_wait: true,
+                await_key_release: true,
+                ..Quirks::default()
+            },
+            Variant::Chip48 => Quirks {
+                jump_uses_vx: true,
+                ..Quirks::default()
+            },
+            Variant::SuperChip => Quirks {
+                load_store_keeps_i: true,
+                jump_uses_vx: true,
+                ..Quirks::default()
+            },
+            Variant::XoChip => Quirks {
+                shift_uses_vy: true,
+                sprites_wrap: true,
+                ..Quirks::default()
+            },
+        }
+    }
+
+    // cycles per 60hz frame the ROMs of the variant were usually written for
+    pub fn hz(self) -> u128 {
+        let cycles_per_frame = match self {
+            Variant::Chip8 => 15,
+            Variant::Chip48 | Variant::SuperChip => 30,
+            Variant::XoChip => 1000,
+        };
+
+        cycles_per_frame * 60
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    #[test]
+    fn only_chip8_waits_for_display() {
+        for variant in [Variant::Chip8, Variant::Chip48, Variant::SuperChip, Variant::XoChip].iter() {
+            assert_eq!(variant.quirks().display_wait, *variant == Variant::Chip8);
+        }
+    }
+
+    #[test]
+    fn parses_names() {
+        assert_eq!(Variant::from_str("SuperChip").map(|x| x.platform()), Ok(Platform::SuperChip));
+        assert!(Variant::from_str("schip").is_err());
+    }
+}
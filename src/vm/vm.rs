@@ -1,34 +1,60 @@
+use std::collections::VecDeque;
 use std::vec::Vec;
 use std::sync::{Arc, Mutex};
 use std::sync::atomic::Ordering;
+use std::thread;
 
-#[cfg(test)]
-use mocktopus::macros::*;
-
-use anyhow::Result;
-use log::{trace, warn};
-use rand;
+use log::{info, trace, warn};
+use rand::{rngs::StdRng, Rng, SeedableRng};
 
 use crate::errors::Errors;
 
-use super::display::{Display, DisplayState, RawScreen};
+use super::display::{Display, DisplayState};
+use super::font;
 use super::audio::Audio;
+use super::cheats::Cheats;
+use super::collisions::Collision;
+use super::condition::ConditionContext;
 use super::input::Input;
-use super::config::Config;
-use super::debugger::{Debugger,DebuggerCommand};
+use super::peripheral::{self, Peripheral};
+use super::platform::Platform;
+use super::quirks::Quirks;
+use super::hash::{fnv1a, rom_hash};
+use super::movie::{Checksum, CHECKSUM_INTERVAL};
+use super::movie_input::MovieInput;
+use super::rpl_flags::RplFlags;
+use super::frame_history::FrameHistory;
+use super::save_state::{SaveState, SAVE_STATE_VERSION};
+use super::screen::Screen;
+use super::self_modification::SelfModification;
+use super::telemetry::{Telemetry, TelemetryReport};
+use super::coverage::OpcodeCoverage;
+use super::heatmap::MemoryHeatmap;
+use super::profiler::Profiler;
+use super::trace::{InstructionTrace, TraceEntry};
+use super::watchpoints::{Access, WatchpointHit};
+use super::config::{Config, Scheduler, WriteProtection};
+use super::debugger::{format_memory, DebugView, Debugger, DebuggerCommand};
+use super::watch::Watch;
 
 use super::constants::*;
 use super::opcodes::*;
 use super::timer::Timer;
 
-#[cfg_attr(test, mockable)]
-fn get_random() -> u8 {
-    rand::random::<u8>()
-}
+// instructions the debugger panel disassembles before and after PC
+const DISASSEMBLY_BEFORE: u16 = 4;
+const DISASSEMBLY_AFTER: u16 = 10;
+
+// time advanced per tick when a frame is run in emulated time
+const FRAME_ADVANCE_TICK: u128 = 100_000;
+
+// errors of the VM are always typed, the frontend matches on them
+type Result<T> = std::result::Result<T, Errors>;
 
 type VmRegisters = [u8; REGISTER_COUNT];
 type VmStack = Vec<StackFrame>;
-type VmMemory = [u8; MEMORY_SIZE];
+// 4K, or 64K for XO-CHIP
+type VmMemory = Vec<u8>;
 
 #[derive(Clone)]
 #[allow(non_snake_case)]
@@ -42,22 +68,100 @@ struct VmFrame {
     delay_timer:    Timer,
     sound_timer:    Timer,
 
-    screen:         RawScreen,
+    screen:         Screen,
+    rpl_flags:      [u8; RPL_FLAG_COUNT],
 }
 
 impl VmFrame {
-    fn new() -> VmFrame {
+    fn new(load_address: u16, memory_size: usize) -> VmFrame {
         VmFrame {
             registers: [0u8; REGISTER_COUNT],
             stack: Vec::with_capacity(16),
-            memory: [0u8; MEMORY_SIZE],
-            PC: PC_START,
+            memory: vec![0u8; memory_size],
+            PC: load_address,
             I: 0,
 
             delay_timer: Timer::new(),
             sound_timer: Timer::new(),
 
-            screen: [0; SCREEN_SIZE],
+            screen: Screen::new(),
+            rpl_flags: [0u8; RPL_FLAG_COUNT],
+        }
+    }
+
+    // accesses past the end of the memory are reported instead of panicking, I and PC are set by the ROM
+    fn read(&self, address: usize, len: usize) -> Result<&[u8]> {
+        self.memory
+            .get(address..address + len)
+            .ok_or_else(|| Errors::MemoryOutOfBounds { address, len })
+    }
+
+    // one line per changed value, consecutive changed bytes are grouped
+    fn diff(&self, other: &VmFrame) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        for (i, (before, after)) in self.registers.iter().zip(other.registers.iter()).enumerate() {
+            if before != after {
+                lines.push(format!("V{:X}: {:#04X} -> {:#04X}", i, before, after));
+            }
+        }
+
+        let values = [
+            ("I", self.I, other.I),
+            ("PC", self.PC, other.PC),
+            ("DT", self.delay_timer.get_scaled() as u16, other.delay_timer.get_scaled() as u16),
+            ("ST", self.sound_timer.get_scaled() as u16, other.sound_timer.get_scaled() as u16),
+        ];
+        for (name, before, after) in values.iter().filter(|(_, before, after)| before != after) {
+            lines.push(format!("{}: {:#05X} -> {:#05X}", name, before, after));
+        }
+
+        let entry = |stack: &Vec<StackFrame>, i: usize| match stack.get(i) {
+            Some(x) => format!("{:#05X}", x.return_address),
+            None => String::from("none"),
+        };
+        for i in 0..self.stack.len().max(other.stack.len()) {
+            let (before, after) = (entry(&self.stack, i), entry(&other.stack, i));
+            if before != after {
+                lines.push(format!("Stack #{}: {} -> {}", i, before, after));
+            }
+        }
+
+        let mut address = 0;
+        let len = self.memory.len().min(other.memory.len());
+        while address < len {
+            let changed = (address..len).take_while(|x| self.memory[*x] != other.memory[*x]).count();
+            if changed > 0 {
+                let bytes = |memory: &[u8]| {
+                    memory[address..address + changed].iter().map(|x| format!("{:02X}", x)).collect::<Vec<String>>().join(" ")
+                };
+                lines.push(format!("Memory {:#05X}: {} -> {}", address, bytes(&self.memory), bytes(&other.memory)));
+            }
+            address += changed + 1;
+        }
+
+        lines
+    }
+
+    fn condition_context(&self) -> ConditionContext<'_> {
+        ConditionContext {
+            registers: &self.registers,
+            i: self.I,
+            pc: self.PC,
+            delay_timer: self.delay_timer.get_scaled(),
+            sound_timer: self.sound_timer.get_scaled(),
+        }
+    }
+
+    fn write(&mut self, address: usize, data: &[u8]) -> Result<()> {
+        let len = data.len();
+
+        match self.memory.get_mut(address..address + len) {
+            Some(memory) => {
+                memory.copy_from_slice(data);
+                Ok(())
+            }
+            None => Err(Errors::MemoryOutOfBounds { address, len }),
         }
     }
 }
@@ -73,9 +177,61 @@ pub struct Vm {
     audio:          Arc<Mutex<Audio>>,
 
     debugger: Debugger,
+    cheats: Arc<Mutex<Cheats>>,
+    peripherals: Vec<Box<dyn Peripheral>>,
+
+    // identifies the ROM in save states
+    rom_hash:       String,
+    // random numbers of CXNN, seeded from the config for reproducible runs
+    rng:            StdRng,
+
+    font_address:   u16,
+    stack_depth:    usize,
+    // writes below it are trapped depending on the write protection
+    load_address:   u16,
+    write_protection: WriteProtection,
+    quirks:         Quirks,
+    // address of the 00FD the ROM exited with, it is only logged once
+    exit_address:   Option<u16>,
+    // address of the 1NNN which jumps to itself, many ROMs end with it and never leave it
+    loop_address:   Option<u16>,
 
     tick_timer:     u128,
     tick_duration:  u128,
+    // instructions executed at the start of every 60hz frame, None spreads them evenly
+    cycles_per_frame: Option<u128>,
+    // time which passed since the timers were advanced, they run at 60hz regardless of hz
+    timer_elapsed:  u128,
+    telemetry:      Telemetry,
+
+    // 60hz frames since the VM started, advanced by the same time as the timers
+    frame_count:    u64,
+    frame_timer:    u128,
+    // frame in which the last sprite was drawn while the display wait quirk is set
+    draw_frame:     Option<u64>,
+    // key pressed during FX0A which has to be released before it resumes, with the await key release quirk
+    awaited_key:    Option<u8>,
+
+    // hashes of the state every CHECKSUM_INTERVAL frames, only taken while a movie is recorded or played
+    checksums:      Option<Vec<Checksum>>,
+    // replaces the keyboard while a movie is recorded or played
+    movie_input:    Option<MovieInput>,
+
+    // the frame count and the state at the start of the most recent frames, the newest last
+    rewind_frames:  VecDeque<(u64, VmFrame)>,
+    rewind_capacity: usize,
+
+    // every executed instruction is written to it while it is set
+    trace:          Option<InstructionTrace>,
+    // counts the executed instructions while it is set
+    profiler:       Option<Profiler>,
+    // counts the executed opcode variants while it is set
+    coverage:       Option<OpcodeCoverage>,
+    // counts the reads, writes and instruction fetches per address while it is set
+    heatmap:        Option<MemoryHeatmap>,
+
+    // addresses which were fetched as instructions, only tracked while self-modifying code is logged
+    executed: Vec<bool>,
 
     frames: Vec<VmFrame>,
     frame_pointer: usize,
@@ -88,44 +244,111 @@ impl Vm {
         display: Arc<Mutex<dyn Display>>,
         input: Arc<Mutex<dyn Input>>,
         audio: Arc<Mutex<Audio>>,
-        debugger: Debugger) -> Result<Vm> {
+        debugger: Debugger,
+        cheats: Arc<Mutex<Cheats>>) -> Result<Vm> {
         let result;
-
-        if rom.len() == 0 {
-            result = Err(Errors::RomEmpty.into());
-        } else if rom.len() > ROM_SIZE {
-            result = Err(Errors::RomTooBig { size: rom.len(), max: ROM_SIZE }.into());
+        let load_address = config.get_load_address();
+        let rom_begin = load_address as usize;
+        let platform = config.get_platform(rom);
+        let (memory_size, reserved_end) = match platform {
+            Platform::XoChip => (XO_CHIP_MEMORY_SIZE, 0),
+            _ => (MEMORY_SIZE, config.reserved_end as usize),
+        };
+        let memory_size = config.memory_size.unwrap_or(memory_size);
+        let rom_size = memory_size
+            .saturating_sub(reserved_end)
+            .saturating_sub(rom_begin);
+        let memory_image = config.is_memory_image(rom);
+
+        let font = font::load(config)?;
+        let font_begin = config.font_address as usize;
+        let font_end = font_begin + font.len();
+        let font_overlaps_rom = memory_image == false && font_begin < rom_begin + rom.len() && rom_begin < font_end;
+
+        if memory_size < MEMORY_SIZE || memory_size > XO_CHIP_MEMORY_SIZE {
+            result = Err(Errors::MemorySizeInvalid { size: memory_size, min: MEMORY_SIZE, max: XO_CHIP_MEMORY_SIZE });
+        } else if rom_size == 0 {
+            result = Err(Errors::LoadAddressInvalid { address: load_address });
+        } else if font_end > MEMORY_SIZE || font_overlaps_rom {
+            result = Err(Errors::FontAddressInvalid { address: config.font_address });
+        } else if rom.len() == 0 {
+            result = Err(Errors::RomEmpty);
+        } else if memory_image && rom.len() != MEMORY_SIZE {
+            result = Err(Errors::MemoryImageSizeInvalid { size: rom.len(), expected: MEMORY_SIZE });
+        } else if memory_image == false && rom.len() > rom_size {
+            result = Err(Errors::RomTooBig { size: rom.len(), max: rom_size });
         } else {
-            let mut memory = [0u8; MEMORY_SIZE];
-            let rom_slice = &mut memory[VM_RESERVED_BEGIN..rom.len() + VM_RESERVED_BEGIN];
-            rom_slice.copy_from_slice(rom.as_slice());
+            let mut memory = vec![0u8; memory_size];
 
-            for n in 0..FONTS.len() {
-                memory[n] = FONTS[n];
+            // a memory image brings its own font and interpreter area
+            if memory_image {
+                memory[..MEMORY_SIZE].copy_from_slice(rom.as_slice());
+                info!("Loaded memory image, starting at {:#05X}", load_address);
+            } else {
+                let rom_slice = &mut memory[rom_begin..rom.len() + rom_begin];
+                rom_slice.copy_from_slice(rom.as_slice());
+
+                memory[font_begin..font_end].copy_from_slice(&font);
+            }
+
+            {
+                let mut cheats = cheats.lock().unwrap();
+                cheats.validate(memory.len());
+                cheats.apply(&mut memory);
             }
 
             let mut frames = Vec::with_capacity(match debugger.enabled {
                 true => 1024 * 1024,
                 false => 1,
             });
-            let mut frame = VmFrame::new();
+            let mut frame = VmFrame::new(load_address, memory_size);
             frame.memory = memory;
             frames.push(frame);
 
+            if platform == Platform::XoChip {
+                warn!("Only the memory and long addressing of {} are supported yet, planes and audio patterns are ignored", platform);
+            }
+
+            let hz = config.get_hz(rom);
+            let executed = match debugger.log_self_modification {
+                true => vec![false; memory_size],
+                false => Vec::new(),
+            };
             let vm = Vm {
                 display,
                 input,
                 audio,
 
                 debugger,
+                cheats,
+                peripherals: peripheral::from_config(config),
+
+                rom_hash: rom_hash(rom),
+                rng: match config.seed {
+                    Some(seed) => StdRng::seed_from_u64(seed),
+                    None => StdRng::from_entropy(),
+                },
+
+                font_address: config.font_address,
+                stack_depth: config.stack_depth,
+                load_address,
+                write_protection: config.write_protection,
+                quirks: config.get_quirks(rom),
+                exit_address: None,
+                loop_address: None,
 
                 tick_timer: 0,
+                timer_elapsed: 0,
+                cycles_per_frame: match config.scheduler {
+                    Scheduler::Frame if hz > 0 => Some((hz / VM_ORIGINAL_HZ).max(1)),
+                    _ => None,
+                },
                 tick_duration: {
-                    if config.hz == 0 {
+                    if hz == 0 {
                         0
                     } else {
                         let nano_1_sec = u128::pow(10, 9);
-                        let multiplicator = config.hz as f64 / VM_ORIGINAL_HZ as f64;
+                        let multiplicator = hz as f64 / VM_ORIGINAL_HZ as f64;
 
                         let ticks_per_seconds = VM_ORIGINAL_HZ;
                         let tick_duration_original = nano_1_sec / ticks_per_seconds;
@@ -133,6 +356,22 @@ impl Vm {
                         (tick_duration_original as f64 / multiplicator) as u128
                     }
                 },
+                telemetry: Telemetry::new(hz),
+
+                frame_count: 0,
+                frame_timer: 0,
+                draw_frame: None,
+                awaited_key: None,
+
+                checksums: None,
+                movie_input: None,
+                rewind_frames: VecDeque::new(),
+                rewind_capacity: 0,
+                trace: None,
+                profiler: None,
+                coverage: None,
+                heatmap: None,
+                executed,
 
                 frames,
                 frame_pointer: 0,
@@ -146,59 +385,573 @@ impl Vm {
 
     // delta in nanoseconds
     pub fn tick(&mut self, delta: u128) -> Result<()> {
+        self.telemetry.advance(delta);
+        self.timer_elapsed += delta;
+
+        match self.cycles_per_frame {
+            Some(cycles) => self.tick_frames(cycles),
+            None => self.tick_spread(delta),
+        }
+    }
+
+    fn tick_spread(&mut self, delta: u128) -> Result<()> {
         let mut result = Ok(());
 
         if self.tick_timer > self.tick_duration {
+            self.telemetry.record_cycle(self.tick_timer - self.tick_duration, self.tick_duration);
             self.tick_timer = 0;
 
-            let execute_cycle = match self.debugger.enabled {
-                true => self.process_debugger(),
-                false => true,
-            };
+            let timer_delta = std::mem::replace(&mut self.timer_elapsed, 0);
+            result = self.cycle(timer_delta);
+        } else {
+            self.tick_timer += delta;
+        }
+
+        result
+    }
+
+    // the instructions of every frame which passed run back to back, the timers advance with the first of them
+    fn tick_frames(&mut self, cycles: u128) -> Result<()> {
+        while self.timer_elapsed >= TIMER_DURATION_NANO {
+            self.timer_elapsed -= TIMER_DURATION_NANO;
+
+            for n in 0..cycles {
+                self.telemetry.record_cycle(0, 0);
+                self.cycle(if n == 0 { TIMER_DURATION_NANO } else { 0 })?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // executes a single instruction regardless of the elapsed time
+    pub fn step(&mut self) -> Result<()> {
+        self.cycle(self.tick_duration)
+    }
+
+    // runs in emulated time until the next 60hz frame starts, regardless of the elapsed time
+    // gives up after two frames of time, e.g. while the debugger halts the VM
+    pub fn advance_frame(&mut self) -> Result<()> {
+        let frame_count = self.frame_count;
+        let mut elapsed = 0;
+
+        while self.frame_count == frame_count && elapsed < 2 * TIMER_DURATION_NANO {
+            self.tick(FRAME_ADVANCE_TICK)?;
+            elapsed += FRAME_ADVANCE_TICK;
+        }
+
+        Ok(())
+    }
+
+    fn cycle(&mut self, timer_delta: u128) -> Result<()> {
+        let mut result = Ok(());
+
+        let execute_cycle = match self.debugger.enabled {
+            true => self.process_debugger(),
+            false => true,
+        };
+
+        if execute_cycle {
+            let mut frame = self.next_frame();
+
+            let previous_frame_count = self.frame_count;
+            self.frame_timer += timer_delta;
+            self.frame_count += (self.frame_timer / TIMER_DURATION_NANO) as u64;
+            self.frame_timer %= TIMER_DURATION_NANO;
+
+            if previous_frame_count / CHECKSUM_INTERVAL != self.frame_count / CHECKSUM_INTERVAL {
+                self.record_checksum(&frame);
+            }
+
+            if previous_frame_count != self.frame_count {
+                self.record_rewind_frame(&frame);
+                self.start_movie_frames(previous_frame_count);
+            }
+
+            if frame.delay_timer.get() > 0 {
+                let dt = frame.delay_timer.get_mut();
+                *dt = dt.saturating_sub(timer_delta);
+            }
+
+            if frame.sound_timer.get() > 0 {
+                let st = frame.sound_timer.get_mut();
+                *st = st.saturating_sub(timer_delta);
+
+                if frame.sound_timer.get() == 0 {
+                    let mut audio = self.audio.lock().unwrap();
+                    audio.playing = false;
+                }
+            }
+
+            if self.waits_for_display() == false {
+                result = match self.fetch(&frame) {
+                    Ok(raw_opcode) => {
+                        self.mark_executed(frame.PC);
+                        if let Some(profiler) = self.profiler.as_mut() {
+                            profiler.record(frame.PC, raw_opcode);
+                        }
+                        if let Some(heatmap) = self.heatmap.as_mut() {
+                            heatmap.record_execute(frame.PC as usize, PC_INCREMENT as usize);
+                        }
+                        let watched_memory = self.read_watched_memory(&frame);
+                        let traced = self.trace.as_ref().map(|_| (frame.PC, frame.registers, frame.I));
+                        let mut decoded = String::new();
+
+                        let executed = match self.peripherals.iter().position(|x| x.claims_opcode(raw_opcode)) {
+                            Some(index) => {
+                                if traced.is_some() {
+                                    decoded = self.peripherals[index].name().to_string();
+                                }
+                                self.execute_peripheral(index, &mut frame, raw_opcode)
+                            }
+                            None => {
+                                let opcode = self.decode(raw_opcode);
+                                if traced.is_some() {
+                                    decoded = format!("{:?}", opcode);
+                                }
+                                if let Some(coverage) = self.coverage.as_mut() {
+                                    coverage.record(&opcode.name());
+                                }
+                                self.execute(&mut frame, opcode)
+                            }
+                        };
+
+                        if let Some((address, registers, i)) = traced {
+                            self.record_trace(&TraceEntry {
+                                address,
+                                raw_opcode,
+                                decoded,
+                                registers_before: registers.to_vec(),
+                                registers_after: frame.registers.to_vec(),
+                                i_before: i,
+                                i_after: frame.I,
+                            });
+                        }
 
-            if execute_cycle {
-                let mut frame = self.next_frame();
+                        self.report_memory_writes(&frame, watched_memory);
+                        self.cheats.lock().unwrap().apply(&mut frame.memory);
 
-                let timer_delta = match self.tick_duration {
-                    0 => delta,
-                    _ => self.tick_duration,
+                        executed
+                    }
+                    Err(msg) => Err(msg),
                 };
+            }
+
+            self.update_stack(frame);
+        }
+
+        result
+    }
+
+    // None stops the trace, the buffered lines are written when the trace is dropped
+    pub fn set_trace(&mut self, trace: Option<InstructionTrace>) {
+        self.trace = trace;
+    }
+
+    pub fn enable_profiler(&mut self) {
+        if self.profiler.is_none() {
+            self.profiler = Some(Profiler::new(self.get_current_frame().memory.len()));
+        }
+    }
+
+    // the n most executed addresses and the executed instructions per opcode, None if the profiler is not enabled
+    pub fn profile_report(&self, n: usize) -> Option<Vec<String>> {
+        let profiler = self.profiler.as_ref()?;
+        let executed = profiler.executed().max(1) as f64;
+        let frame = self.get_current_frame();
+
+        let mut lines = vec![format!("Executed {} instructions", profiler.executed()), String::from("Hot addresses:")];
+        for (address, count) in profiler.hot_addresses(n) {
+            let name = match frame.read(address as usize, 2) {
+                Ok(bytes) => self.opcode_name(u16::from_be_bytes([bytes[0], bytes[1]])),
+                Err(..) => String::from("?"),
+            };
+            lines.push(format!("  {:#05X}  {:>10}  {:5.1}%  {}", address, count, count as f64 * 100. / executed, name));
+        }
+
+        lines.push(String::from("Opcodes:"));
+        for (name, count) in profiler.opcode_counts(|x| self.opcode_name(x)).into_iter().take(n) {
+            lines.push(format!("  {:<24}  {:>10}  {:5.1}%", name, count, count as f64 * 100. / executed));
+        }
+
+        Some(lines)
+    }
+
+    pub fn enable_coverage(&mut self) {
+        if self.coverage.is_none() {
+            self.coverage = Some(OpcodeCoverage::new(OpCode::NAMES));
+        }
+    }
+
+    // the executed and the missing opcode variants, None if the coverage is not enabled
+    pub fn coverage_report(&self) -> Option<Vec<String>> {
+        self.coverage.as_ref().map(|x| x.report())
+    }
+
+    pub fn enable_heatmap(&mut self) {
+        if self.heatmap.is_none() {
+            self.heatmap = Some(MemoryHeatmap::new(self.get_current_frame().memory.len()));
+        }
+    }
+
+    pub fn get_heatmap(&self) -> Option<MemoryHeatmap> {
+        self.heatmap.clone()
+    }
+
+    // the label a jump, call or I points to, e.g. " -> draw_loop", empty without a label
+    fn label_target(&self, opcode: &OpCode) -> String {
+        match opcode.target().and_then(|x| self.debugger.symbols.locate(x)) {
+            Some(location) => format!(" -> {}", location),
+            None => String::new(),
+        }
+    }
+
+    // the opcode without its operands, e.g. Disp or Const_Add_Reg
+    fn opcode_name(&self, raw_opcode: u16) -> String {
+        match self.peripherals.iter().find(|x| x.claims_opcode(raw_opcode)) {
+            Some(peripheral) => peripheral.name().to_string(),
+            None => self.decode_quietly(raw_opcode).name(),
+        }
+    }
+
+    // a trace which cannot be written anymore is stopped instead of failing the VM
+    fn record_trace(&mut self, entry: &TraceEntry) {
+        if let Some(trace) = self.trace.as_mut() {
+            if let Err(err) = trace.record(entry) {
+                warn!("Stopped the instruction trace, writing failed: {}", err);
+                self.trace = None;
+            }
+        }
+    }
+
+    // the CPU stalls after a draw until the display refreshed, the timers keep running
+    fn waits_for_display(&mut self) -> bool {
+        match self.draw_frame {
+            Some(frame) if frame == self.frame_count => true,
+            _ => {
+                self.draw_frame = None;
+                false
+            }
+        }
+    }
+
+    // replaces the keyboard with the input of a movie from the current frame on
+    pub fn set_movie_input(&mut self, mut movie_input: MovieInput) {
+        let keyboard = self.keyboard_mask();
+        movie_input.start_frame(self.frame_count, keyboard);
+        self.movie_input = Some(movie_input);
+    }
+
+    // the input recorded since the last call, one bitmask per frame
+    pub fn take_movie_input(&mut self) -> Vec<u16> {
+        match self.movie_input.as_mut() {
+            Some(movie_input) => movie_input.take_recorded(),
+            None => Vec::new(),
+        }
+    }
+
+    fn keyboard_mask(&self) -> u16 {
+        let input = self.input.lock().unwrap();
+        (0..16u8).filter(|x| input.is_pressed(*x)).fold(0, |mask, key| mask | 1 << key)
+    }
+
+    // the keyboard is used again once the played back movie ends
+    fn start_movie_frames(&mut self, previous_frame_count: u64) {
+        let keyboard = match &self.movie_input {
+            Some(movie_input) if movie_input.is_recording() => self.keyboard_mask(),
+            Some(..) => 0,
+            None => return,
+        };
+
+        if let Some(movie_input) = self.movie_input.as_mut() {
+            for frame in previous_frame_count + 1..=self.frame_count {
+                movie_input.start_frame(frame, keyboard);
+            }
+
+            if movie_input.is_finished(self.frame_count) {
+                info!("Movie input ended at frame {}", self.frame_count);
+                self.movie_input = None;
+            }
+        }
+    }
+
+    // the input of a movie replaces the keyboard while it is recorded or played
+    fn with_input<R, F: FnOnce(&mut dyn Input) -> R>(&mut self, f: F) -> R {
+        match self.movie_input.as_mut() {
+            Some(movie_input) => f(movie_input),
+            None => f(&mut *self.input.lock().unwrap()),
+        }
+    }
+
+    pub fn take_telemetry_report(&mut self) -> Option<TelemetryReport> {
+        self.telemetry.take_report()
+    }
+
+    pub fn get_frame_count(&self) -> u64 {
+        self.frame_count
+    }
+
+    pub fn get_quirks(&self) -> Quirks {
+        self.quirks
+    }
+
+    pub fn get_rpl_flags(&self) -> RplFlags {
+        self.get_current_frame().rpl_flags
+    }
+
+    pub fn set_rpl_flags(&mut self, flags: RplFlags) {
+        self.frames[self.frame_pointer].rpl_flags = flags;
+    }
+
+    // the ROM executed 00FD, it does not do anything anymore
+    pub fn has_exited(&self) -> bool {
+        self.exit_address.is_some()
+    }
+
+    // the ROM is stuck in a jump to itself, it does not do anything anymore
+    pub fn has_halted(&self) -> bool {
+        self.loop_address.is_some()
+    }
+
+    pub fn enable_checksums(&mut self) {
+        self.checksums = Some(Vec::new());
+    }
+
+    pub fn take_checksums(&mut self) -> Vec<Checksum> {
+        match self.checksums.as_mut() {
+            Some(checksums) => checksums.drain(..).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    // taken before the first instruction of the frame, so it only depends on the input of the previous frames
+    fn record_checksum(&mut self, frame: &VmFrame) {
+        if self.checksums.is_none() {
+            return;
+        }
+
+        let screen = *self.display.lock().unwrap().get_screen();
+        let bytes = [
+            &frame.registers[..],
+            &frame.memory[..],
+            &frame.PC.to_be_bytes()[..],
+            &frame.I.to_be_bytes()[..],
+            &[frame.delay_timer.get_scaled(), frame.sound_timer.get_scaled()][..],
+            screen.as_bytes(),
+        ]
+        .concat();
+
+        let checksum = Checksum {
+            frame: self.frame_count,
+            hash: fnv1a(&bytes),
+        };
+
+        if let Some(checksums) = self.checksums.as_mut() {
+            checksums.push(checksum);
+        }
+    }
 
-                if frame.delay_timer.get() > 0 {
-                    let dt = frame.delay_timer.get_mut();
-                    *dt = dt.saturating_sub(timer_delta);
+    // keeps the start of the last seconds of frames, the headless runner and tests do without
+    pub fn enable_rewind(&mut self, seconds: u32) {
+        self.rewind_capacity = seconds as usize * 60;
+        self.rewind_frames = VecDeque::with_capacity(self.rewind_capacity);
+    }
+
+    fn record_rewind_frame(&mut self, frame: &VmFrame) {
+        if self.rewind_capacity == 0 {
+            return;
+        }
+
+        if self.rewind_frames.len() == self.rewind_capacity {
+            self.rewind_frames.pop_front();
+        }
+
+        let mut frame = frame.clone();
+        frame.screen = *self.display.lock().unwrap().get_screen();
+        self.rewind_frames.push_back((self.frame_count, frame));
+    }
+
+    // goes back to the start of the previous frame, false once the buffer is empty
+    // while debugging the rewound frame is appended to the history and the frames after the current one are dropped
+    pub fn rewind(&mut self) -> bool {
+        match self.rewind_frames.pop_back() {
+            Some((frame_count, frame)) => {
+                self.restore_frame(&frame);
+                self.frame_count = frame_count;
+                self.frame_timer = 0;
+
+                if self.debugger.enabled {
+                    self.frames.truncate(self.frame_pointer + 1);
+                    self.frames.push(frame);
+                    self.frame_pointer += 1;
+                } else {
+                    self.frames[self.frame_pointer] = frame;
                 }
+                true
+            }
+            None => false,
+        }
+    }
 
-                if frame.sound_timer.get() > 0 {
-                    let st = frame.sound_timer.get_mut();
-                    *st = st.saturating_sub(timer_delta);
+    // the screen is taken from the display, the frame only keeps it while debugging
+    pub fn get_state(&self) -> SaveState {
+        let screen = *self.display.lock().unwrap().get_screen();
+        self.frame_to_state(self.get_current_frame(), &screen)
+    }
 
-                    if frame.sound_timer.get() == 0 {
-                        let mut audio = self.audio.lock().unwrap();
-                        audio.playing = false;
+    // instructions are assumed to be aligned to PC, the ones before it may be data
+    pub fn get_debug_view(&self) -> DebugView {
+        let frame = self.get_current_frame();
+        let start = frame.PC.saturating_sub(DISASSEMBLY_BEFORE * PC_INCREMENT);
+
+        let disassembly = (0..DISASSEMBLY_BEFORE + 1 + DISASSEMBLY_AFTER)
+            .map(|x| start.wrapping_add(x * PC_INCREMENT))
+            .filter_map(|address| {
+                let bytes = frame.read(address as usize, 2).ok()?;
+                let raw_opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+
+                let mut decoded = match self.peripherals.iter().find(|x| x.claims_opcode(raw_opcode)) {
+                    Some(peripheral) => peripheral.name().to_string(),
+                    None => {
+                        let opcode = self.decode_quietly(raw_opcode);
+                        format!("{:?}{}", opcode, self.label_target(&opcode))
                     }
+                };
+                if let Some(label) = self.debugger.symbols.label(address) {
+                    decoded = format!("{}: {}", label, decoded);
                 }
 
-                let raw_opcode = self.fetch(&frame);
-                let opcode = self.decode(raw_opcode);
+                Some((address, raw_opcode, decoded))
+            })
+            .collect();
+
+        DebugView {
+            registers: frame.registers.to_vec(),
+            i: frame.I,
+            pc: frame.PC,
+            stack: frame.stack.iter().map(|x| x.return_address).collect(),
+            delay_timer: frame.delay_timer.get_scaled(),
+            sound_timer: frame.sound_timer.get_scaled(),
+            disassembly,
+        }
+    }
 
-                result = self.execute(&mut frame, opcode);
+    // replaces the current frame, the debugger history before it is kept
+    pub fn set_state(&mut self, state: &SaveState) -> Result<()> {
+        let frame = self.state_to_frame(state)?;
 
-                self.update_stack(frame);
-            }
-        } else {
-            self.tick_timer += delta;
+        self.restore_frame(&frame);
+        self.frames[self.frame_pointer] = frame;
+
+        Ok(())
+    }
+
+    pub fn get_history(&self) -> FrameHistory {
+        FrameHistory::from_states(
+            &self.rom_hash,
+            self.frame_pointer,
+            self.frames.iter().map(|x| self.frame_to_state(x, &x.screen)),
+        )
+    }
+
+    // replaces the whole debugger history, the VM continues at the selected frame
+    pub fn set_history(&mut self, history: &FrameHistory) -> Result<()> {
+        if history.frame_pointer >= history.frames.len() {
+            return Err(Errors::SaveStateInvalid {
+                message: String::from("the selected frame is not part of the history"),
+            });
         }
 
-        result
+        let frames = history
+            .to_states()
+            .iter()
+            .map(|x| self.state_to_frame(x))
+            .collect::<Result<Vec<VmFrame>>>()?;
+
+        self.restore_frame(&frames[history.frame_pointer]);
+        self.frames = frames;
+        self.frame_pointer = history.frame_pointer;
+
+        Ok(())
+    }
+
+    fn frame_to_state(&self, frame: &VmFrame, screen: &Screen) -> SaveState {
+        SaveState {
+            version: SAVE_STATE_VERSION,
+            rom_hash: self.rom_hash.clone(),
+            registers: frame.registers.to_vec(),
+            stack: frame.stack.iter().map(|x| x.return_address).collect(),
+            memory: frame.memory.clone(),
+            pc: frame.PC,
+            i: frame.I,
+            delay_timer: frame.delay_timer.get() as u64,
+            sound_timer: frame.sound_timer.get() as u64,
+            screen: screen.as_bytes().to_vec(),
+            rpl_flags: frame.rpl_flags.to_vec(),
+        }
+    }
+
+    fn state_to_frame(&self, state: &SaveState) -> Result<VmFrame> {
+        state.validate(&self.rom_hash)?;
+
+        let invalid = |message: &str| Errors::SaveStateInvalid { message: String::from(message) };
+        let mut frame = self.next_frame();
+
+        if state.memory.len() != frame.memory.len() {
+            return Err(invalid("the memory size differs"));
+        }
+        if state.registers.len() != REGISTER_COUNT || state.rpl_flags.len() != RPL_FLAG_COUNT {
+            return Err(invalid("the number of registers differs"));
+        }
+        let screen = Screen::from_bytes(&state.screen).ok_or_else(|| invalid("the screen size is unknown"))?;
+
+        frame.registers.copy_from_slice(&state.registers);
+        frame.stack = state.stack.iter().map(|x| StackFrame { return_address: *x }).collect();
+        frame.memory.copy_from_slice(&state.memory);
+        frame.PC = state.pc;
+        frame.I = state.i;
+        *frame.delay_timer.get_mut() = state.delay_timer as u128;
+        *frame.sound_timer.get_mut() = state.sound_timer as u128;
+        frame.screen = screen;
+        frame.rpl_flags.copy_from_slice(&state.rpl_flags);
+
+        Ok(frame)
+    }
+
+    // the state outside of the frames follows the restored frame
+    fn restore_frame(&mut self, frame: &VmFrame) {
+        self.display.lock().unwrap().set_screen(&frame.screen);
+        self.audio.lock().unwrap().playing = frame.sound_timer.get() > 0;
+        self.exit_address = None;
+        self.loop_address = None;
+        self.awaited_key = None;
+        self.draw_frame = None;
+    }
+
+    // takes effect with the next instruction
+    pub fn set_quirks(&mut self, quirks: Quirks) {
+        self.quirks = quirks;
+        self.draw_frame = None;
+    }
+
+    pub fn get_memory(&self) -> Vec<u8> {
+        self.get_current_frame().memory.to_vec()
     }
 
     fn process_debugger(&mut self) -> bool {
         fn print_debug(s: &Vm, command: &DebuggerCommand) {
             let frame = s.get_current_frame();
-            let opcode = s.decode(s.fetch(frame));
-
-            println!("Debugger: {:width$} {:?}", command.to_string(), opcode, width=8);
+            match s.fetch(frame) {
+                Ok(raw_opcode) => {
+                    let opcode = s.decode(raw_opcode);
+                    let location = match s.debugger.symbols.locate(frame.PC) {
+                        Some(location) => format!(" at {}", location),
+                        None => String::new(),
+                    };
+                    println!("Debugger: {:width$} {:?}{}{}", command.to_string(), opcode, s.label_target(&opcode), location, width=8)
+                }
+                Err(msg) => println!("Debugger: {:width$} {}", command.to_string(), msg, width=8),
+            }
         }
 
         if self.debugger.enable_break.load(Ordering::SeqCst) {
@@ -220,6 +973,24 @@ impl Vm {
 
                             print_debug(self, &command);
                         },
+                    DebuggerCommand::GotoFrame(n) =>
+                        if n < self.frames.len() {
+                            self.frame_pointer = n;
+
+                            print_debug(self, &command);
+                        } else {
+                            println!("Debugger: there is no frame {}, {} frames are recorded", n, self.frames.len());
+                        },
+                    DebuggerCommand::GotoFirstFrame => {
+                        self.frame_pointer = 0;
+
+                        print_debug(self, &command);
+                    },
+                    DebuggerCommand::GotoLastFrame => {
+                        self.frame_pointer = self.frames.len() - 1;
+
+                        print_debug(self, &command);
+                    },
                     DebuggerCommand::PrintRegisters => {
                         let frame = self.get_current_frame();
 
@@ -241,7 +1012,10 @@ impl Vm {
                                 .iter()
                                 .enumerate()
                                 .rev()
-                                .for_each(|(i, x)| println!("Frame #{}: {:?}", i, x));
+                                .for_each(|(i, x)| match self.debugger.symbols.locate(x.return_address) {
+                                    Some(location) => println!("Frame #{}: {:?} in {}", i, x, location),
+                                    None => println!("Frame #{}: {:?}", i, x),
+                                });
                         }
                     },
                     DebuggerCommand::PrintTimers => {
@@ -249,16 +1023,313 @@ impl Vm {
 
                         println!("Delay Timer: Scaled: {} Raw: {}", frame.delay_timer.get_scaled(), frame.delay_timer.get());
                         println!("Sound Timer: Scaled: {} Raw: {}", frame.sound_timer.get_scaled(), frame.sound_timer.get());
+                    },
+                    DebuggerCommand::PrintCollisions => {
+                        if self.debugger.log_collisions == false {
+                            println!("Debugger: collision logging is disabled");
+                        }
+
+                        self.debugger.collisions.entries().for_each(|x| println!("Collision: {}", x));
+                    },
+                    DebuggerCommand::PrintMemory { start, len } => {
+                        format_memory(&self.get_current_frame().memory, start, len)
+                            .iter()
+                            .for_each(|x| println!("{}", x));
+                    },
+                    DebuggerCommand::PrintMemoryAroundI => {
+                        let start = (self.get_current_frame().I & !0xF).saturating_sub(0x10);
+
+                        format_memory(&self.get_current_frame().memory, start, 0x40)
+                            .iter()
+                            .for_each(|x| println!("{}", x));
+                    },
+                    DebuggerCommand::ReadState(ref reply) => {
+                        let _ = reply.send(self.get_state());
+                    },
+                    DebuggerCommand::SetRegister { register, value } => {
+                        if register < REGISTER_COUNT {
+                            self.poke_frame().registers[register] = value;
+
+                            println!("Debugger: V{:X} = {:#04X}", register, value);
+                        } else {
+                            println!("Debugger: V{:X} is not a register", register);
+                        }
+                    },
+                    DebuggerCommand::SetI(address) => {
+                        self.poke_frame().I = address;
+
+                        println!("Debugger: I = {:#05X}", address);
+                    },
+                    DebuggerCommand::SetPC(address) => {
+                        self.poke_frame().PC = address;
+
+                        print_debug(self, &command);
+                    },
+                    DebuggerCommand::WriteMemory { address, ref data } => {
+                        match self.poke_frame().write(address as usize, data) {
+                            Ok(..) => println!("Debugger: wrote {} bytes at {:#05X}", data.len(), address),
+                            Err(msg) => println!("Debugger: failed to write memory: {}", msg),
+                        }
+                    },
+                    DebuggerCommand::DiffFrames { from, to } => self.print_frame_diff(from, to),
+                    DebuggerCommand::DiffPrevious => {
+                        match self.frame_pointer.checked_sub(1) {
+                            Some(previous) => self.print_frame_diff(previous, self.frame_pointer),
+                            None => println!("Debugger: there is no frame before the first one"),
+                        }
+                    },
+                    DebuggerCommand::ToggleBreakpoint => {
+                        let address = self.get_current_frame().PC;
+                        self.debugger.breakpoints.lock().unwrap().toggle_at(address);
+
+                        println!("Debugger: toggled breakpoint at {}", self.debugger.symbols.describe(address));
+                    }
+                    DebuggerCommand::AddBreakpoint(address) => {
+                        self.debugger.breakpoints.lock().unwrap().add(address);
+
+                        println!("Debugger: added breakpoint at {}", self.debugger.symbols.describe(address));
+                    }
+                    DebuggerCommand::Continue => {
+                        self.leave_break();
+                        result = true;
+                    }
+                    DebuggerCommand::ReverseContinue => {
+                        match self.find_previous_break() {
+                            Some((frame, reason)) => {
+                                self.frame_pointer = frame;
+
+                                println!(
+                                    "Debugger: {} at {} in frame {}",
+                                    reason,
+                                    self.debugger.symbols.describe(self.get_current_frame().PC),
+                                    frame
+                                );
+                            }
+                            None => {
+                                self.frame_pointer = 0;
+
+                                println!("Debugger: nothing breaks before, at the first frame");
+                            }
+                        }
+                    }
+                    DebuggerCommand::AddWatch(text) => {
+                        match Watch::parse(&text) {
+                            Ok(watch) => {
+                                self.debugger.watches.push((text, watch));
+                                self.debugger.watched_frame = None;
+                            }
+                            Err(err) => println!("Debugger: invalid watch {}: {}", text, err),
+                        }
+                    }
+                    DebuggerCommand::ClearWatches => self.debugger.watches.clear(),
+                    DebuggerCommand::PrintProfile(n) => match self.profile_report(n) {
+                        Some(lines) => lines.iter().for_each(|x| println!("{}", x)),
+                        None => {
+                            self.enable_profiler();
+                            println!("Debugger: started the profiler, instructions are counted from now on");
+                        }
+                    },
+                    DebuggerCommand::PrintCoverage => match self.coverage_report() {
+                        Some(lines) => lines.iter().for_each(|x| println!("{}", x)),
+                        None => {
+                            self.enable_coverage();
+                            println!("Debugger: started the opcode coverage, opcodes are counted from now on");
+                        }
+                    },
+                    DebuggerCommand::RunTo(address) => {
+                        self.debugger.run_to = Some(address);
+                        self.leave_break();
+                        result = true;
+
+                        println!("Debugger: running to {}", self.debugger.symbols.describe(address));
+                    }
+                    DebuggerCommand::RunUntilReturn => {
+                        let depth = self.get_current_frame().stack.len();
+
+                        if depth == 0 {
+                            println!("Debugger: not inside a subroutine");
+                        } else {
+                            self.debugger.run_until_depth = Some(depth);
+                            self.leave_break();
+                            result = true;
+
+                            println!("Debugger: running until the subroutine returns");
+                        }
+                    }
+                    DebuggerCommand::ExportHistory(path) => {
+                        let history = self.get_history();
+
+                        // a long history takes a while to write, the VM does not wait for it
+                        thread::spawn(move || match history.save(&path) {
+                            Ok(..) => println!("Debugger: exported {} frames to {}", history.frames.len(), path.display()),
+                            Err(msg) => println!("Debugger: failed to export the history: {}", msg),
+                        });
+                    }
+                    DebuggerCommand::ImportHistory(path) => {
+                        match FrameHistory::load(&path).and_then(|x| Ok(self.set_history(&x)?)) {
+                            Ok(..) => println!("Debugger: imported {} frames from {}", self.frames.len(), path.display()),
+                            Err(msg) => println!("Debugger: failed to import the history: {}", msg),
+                        }
                     }
                 }
             };
 
+            if self.debugger.watched_frame != Some(self.frame_pointer) {
+                self.print_watches();
+                self.debugger.watched_frame = Some(self.frame_pointer);
+            }
+
             self.display.lock().unwrap().set_screen(&self.get_current_frame().screen);
 
             result
         } else {
-            true
+            let address = self.get_current_frame().PC;
+
+            if self.debugger.resume_address.take() == Some(address) {
+                true
+            } else if self.debugger.run_to == Some(address) {
+                self.debugger.run_to = None;
+                self.debugger.enable_break.store(true, Ordering::SeqCst);
+                self.debugger.resume_address = Some(address);
+
+                println!("Debugger: reached {}", self.debugger.symbols.describe(address));
+
+                false
+            } else if self.debugger.run_until_depth.filter(|x| self.get_current_frame().stack.len() < *x).is_some() {
+                self.debugger.run_until_depth = None;
+                self.debugger.enable_break.store(true, Ordering::SeqCst);
+                self.debugger.resume_address = Some(address);
+
+                println!("Debugger: returned to {}", self.debugger.symbols.describe(address));
+
+                false
+            } else if self.debugger.breakpoints.lock().unwrap().hit(address, &self.get_current_frame().condition_context()) {
+                self.debugger.enable_break.store(true, Ordering::SeqCst);
+                self.debugger.resume_address = Some(address);
+
+                println!("Debugger: breakpoint hit at {}", self.debugger.symbols.describe(address));
+
+                false
+            } else if let Some(opcode) = self.check_break_on_opcodes() {
+                self.debugger.enable_break.store(true, Ordering::SeqCst);
+                self.debugger.resume_address = Some(address);
+
+                println!("Debugger: {} at {}", opcode, self.debugger.symbols.describe(address));
+
+                false
+            } else if let Some(condition) = self.check_break_conditions() {
+                self.debugger.enable_break.store(true, Ordering::SeqCst);
+                self.debugger.resume_address = Some(address);
+
+                println!("Debugger: {} holds at {}", condition, self.debugger.symbols.describe(address));
+
+                false
+            } else {
+                true
+            }
+        }
+    }
+
+    // name of the instruction at PC if its class is one to break on
+    fn check_break_on_opcodes(&self) -> Option<String> {
+        if self.debugger.break_on_opcodes.is_empty() {
+            return None;
+        }
+
+        let opcode = self.decode(self.fetch(self.get_current_frame()).ok()?);
+        match self.debugger.break_on_opcodes.iter().any(|x| opcode.is_in_class(x)) {
+            true => Some(opcode.name()),
+            false => None,
+        }
+    }
+
+    // text of the first condition which started to hold, all of them are updated
+    fn check_break_conditions(&mut self) -> Option<String> {
+        if self.debugger.break_conditions.is_empty() {
+            return None;
         }
+
+        // the frames and the conditions are borrowed separately
+        let context = self.frames[self.frame_pointer].condition_context();
+
+        let mut result = None;
+        for condition in self.debugger.break_conditions.iter_mut() {
+            let holds = condition.condition.evaluate(&context);
+            if holds && condition.held == false && result.is_none() {
+                result = Some(condition.text.clone());
+            }
+            condition.held = holds;
+        }
+
+        result
+    }
+
+    fn print_watches(&self) {
+        let frame = self.get_current_frame();
+        let context = frame.condition_context();
+
+        for (text, watch) in &self.debugger.watches {
+            println!("Watch: {} = {}", text, watch.format(&context, &frame.memory));
+        }
+    }
+
+    fn print_frame_diff(&self, from: usize, to: usize) {
+        match (self.frames.get(from), self.frames.get(to)) {
+            (Some(before), Some(after)) => {
+                let lines = before.diff(after);
+                if lines.is_empty() {
+                    println!("Debugger: frame {} and {} are the same", from, to);
+                }
+
+                lines.iter().for_each(|x| println!("{}", x));
+            }
+            _ => println!("Debugger: {} frames are recorded, cannot compare {} with {}", self.frames.len(), from, to),
+        }
+    }
+
+    // the most recent frame before the current one at which execution would have stopped and why
+    // a write watchpoint stops at the frame after the write like it does while running
+    fn find_previous_break(&self) -> Option<(usize, String)> {
+        let breakpoints = self.debugger.breakpoints.lock().unwrap();
+        let watchpoints = self.debugger.watchpoints.lock().unwrap();
+
+        (0..self.frame_pointer).rev().find_map(|x| {
+            let frame = &self.frames[x];
+            let previous = x.checked_sub(1).map(|x| &self.frames[x]);
+            let context = frame.condition_context();
+
+            if breakpoints.matches(frame.PC, &context) {
+                return Some((x, String::from("breakpoint")));
+            }
+
+            if previous.filter(|previous| watchpoints.written(&previous.memory, &frame.memory)).is_some() {
+                return Some((x, String::from("watchpoint written")));
+            }
+
+            // break conditions stop when they start to hold
+            let previous_context = previous.map(|x| x.condition_context());
+            self.debugger
+                .break_conditions
+                .iter()
+                .find(|condition| {
+                    condition.condition.evaluate(&context)
+                        && previous_context.as_ref().map_or(true, |x| condition.condition.evaluate(x) == false)
+                })
+                .map(|condition| (x, format!("{} holds", condition.text)))
+        })
+    }
+
+    // the instruction at the current address is executed even if there is a breakpoint
+    fn leave_break(&mut self) {
+        self.debugger.enable_break.store(false, Ordering::SeqCst);
+        self.debugger.resume_address = Some(self.get_current_frame().PC);
+    }
+
+    // stepping forward has to execute the changed frame instead of replaying the recorded ones
+    fn poke_frame(&mut self) -> &mut VmFrame {
+        self.debugger.watched_frame = None;
+        self.frames.truncate(self.frame_pointer + 1);
+        &mut self.frames[self.frame_pointer]
     }
 
     fn get_current_frame(&self) -> &VmFrame {
@@ -272,7 +1343,7 @@ impl Vm {
     fn update_stack(&mut self, mut frame: VmFrame) {
         if self.debugger.enabled {
             {
-                frame.screen = self.display.lock().unwrap().get_screen().clone();
+                frame.screen = *self.display.lock().unwrap().get_screen();
             }
 
             if self.frame_pointer + 1 == self.frames.len() {
@@ -288,12 +1359,25 @@ impl Vm {
         }
     }
 
-    fn fetch(&self, frame: &VmFrame) -> u16 {
-        let slice = &frame.memory[frame.PC as usize..(frame.PC + 2) as usize];
-        u16::from_be_bytes([slice[0], slice[1]])
+    // jumps and skips can move the PC anywhere, the instruction has to fit into the memory
+    fn fetch(&self, frame: &VmFrame) -> Result<u16> {
+        match frame.read(frame.PC as usize, 2) {
+            Ok(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+            Err(..) => Err(Errors::PcOutOfRange { address: frame.PC, size: frame.memory.len() }),
+        }
     }
 
     fn decode(&self, code: u16) -> OpCode {
+        let opcode = self.decode_quietly(code);
+        if opcode == OpCode::Unknown {
+            warn!("unknown OpCode {:#06X}", code);
+        }
+
+        opcode
+    }
+
+    // data between the instructions decodes to Unknown, the disassembly does not warn about it
+    fn decode_quietly(&self, code: u16) -> OpCode {
         //  1    2    3    4
         // 1111 1111 1111 1111
         //
@@ -333,6 +1417,12 @@ impl Vm {
             0x0 => match nnn {
                 0x0E0 => OpCode::Disp_Clear,
                 0x0EE => OpCode::Flow_Return,
+                0x0FB => OpCode::Disp_Scroll_Right,
+                0x0FC => OpCode::Disp_Scroll_Left,
+                0x0FD => OpCode::Flow_Exit,
+                0x0FE => OpCode::Disp_Lores,
+                0x0FF => OpCode::Disp_Hires,
+                _ if nnn & 0xFF0 == 0x0C0 => OpCode::Disp_Scroll_Down { n: n },
                 _     => OpCode::Raw_Call { nnn: nnn },
             },
             0x1 => OpCode::Flow_Jump { nnn: nnn },
@@ -354,55 +1444,106 @@ impl Vm {
                     0x6 => OpCode::BitOp_Shift_Right { x: x, y: y },
                     0x7 => OpCode::Math_Minus_Reverse { x: x, y: y },
                     0xE => OpCode::BitOp_Shift_Left { x: x, y: y },
-                    _   => {
-                        warn!("unknown OpCode {}", code);
-                        OpCode::Unknown
-                    }
+                    _   => OpCode::Unknown,
                 }
             }
             0x9 => OpCode::Cond_Neq_Reg { x: x, y: y },
             0xA => OpCode::MEM_Set_I { nnn: nnn },
             0xB => OpCode::Flow_Jump_Offset { nnn: nnn },
             0xC => OpCode::Rand { x: x, nn: nn },
-            0xD => OpCode::Disp { x: x, y: y, n: n },
+            0xD => match n {
+                0 => OpCode::Disp_Large { x: x, y: y },
+                _ => OpCode::Disp { x: x, y: y, n: n },
+            },
             0xE => {
                 let sub_code = code & (nibble_3 | nibble_4);
                 match sub_code {
                     0x9E => OpCode::KeyOp_Skip_Pressed { x: x },
                     0xA1 => OpCode::KeyOp_Skip_Not_Pressed { x: x },
-                    _    => {
-                        warn!("unknown OpCode {}", code);
-                        OpCode::Unknown
-                    }
+                    _    => OpCode::Unknown,
                 }
             }
             0xF => {
                 let sub_code = code & (nibble_3 | nibble_4);
                 match sub_code {
+                    _ if code == 0xF000 => OpCode::MEM_Set_I_Long,
                     0x07 => OpCode::Timer_Delay_Get { x: x },
                     0x0A => OpCode::KeyOp_Await { x: x },
                     0x15 => OpCode::Timer_Delay_Set { x: x },
                     0x18 => OpCode::Sound_Set { x: x },
                     0x1E => OpCode::MEM_Add_I { x: x },
                     0x29 => OpCode::MEM_Set_Sprite_I { x: x },
+                    0x30 => OpCode::MEM_Set_Large_Sprite_I { x: x },
                     0x33 => OpCode::BCD { x: x },
                     0x55 => OpCode::MEM_Reg_Dump { x: x },
                     0x65 => OpCode::MEM_Reg_Load { x: x },
-                    _    => {
-                        warn!("unknown OpCode {}", code);
-                        OpCode::Unknown
-                    }
+                    0x75 => OpCode::MEM_Flags_Dump { x: x },
+                    0x85 => OpCode::MEM_Flags_Load { x: x },
+                    _    => OpCode::Unknown,
                 }
             }
-            _   => {
-                warn!("unknown OpCode prefix {} OpCode {}", op, code);
-                OpCode::Unknown
+            _   => OpCode::Unknown,
+        }
+    }
+
+    fn execute_peripheral(&mut self, index: usize, frame: &mut VmFrame, raw_opcode: u16) -> Result<()> {
+        let peripheral = &mut self.peripherals[index];
+        trace!("Executing {:#06X} on {}", raw_opcode, peripheral.name());
+
+        let result = peripheral.execute(raw_opcode, &mut frame.registers, &mut frame.memory).map_err(|error| Errors::PeripheralFailed {
+            name: peripheral.name().to_string(),
+            error,
+        });
+        self.increment_pc(frame);
+
+        result
+    }
+
+    // memory ranges claimed by the peripherals before an instruction is executed
+    fn read_watched_memory(&self, frame: &VmFrame) -> Vec<(usize, Vec<u8>)> {
+        self.peripherals
+            .iter()
+            .enumerate()
+            .filter_map(|(index, peripheral)| {
+                let range = peripheral.memory_range()?;
+                let memory = frame.memory.get(range)?;
+
+                Some((index, memory.to_vec()))
+            })
+            .collect()
+    }
+
+    fn report_memory_writes(&mut self, frame: &VmFrame, watched_memory: Vec<(usize, Vec<u8>)>) {
+        for (index, before) in watched_memory {
+            let peripheral = &mut self.peripherals[index];
+            let start = match peripheral.memory_range() {
+                Some(range) => range.start,
+                None => continue,
+            };
+
+            for (offset, previous) in before.iter().enumerate() {
+                let value = frame.memory[start + offset];
+                if value != *previous {
+                    peripheral.memory_written(start + offset, value);
+                }
             }
         }
     }
 
-    fn increment_pc(&mut self, frame: &mut VmFrame) {
-        frame.PC += PC_INCREMENT;
+    fn increment_pc(&mut self, frame: &mut VmFrame) {
+        // wraps with 64K of memory, fetch reports the PC if it ends up outside of a smaller memory
+        frame.PC = frame.PC.wrapping_add(PC_INCREMENT);
+    }
+
+    // skips the instruction after the current one, F000 NNNN is skipped as a whole
+    fn skip_next(&mut self, frame: &mut VmFrame) {
+        let next = frame.PC as usize + PC_INCREMENT as usize;
+        let is_long = frame.memory.get(next..next + 2) == Some(&[0xF0, 0x00][..]);
+
+        self.increment_pc(frame);
+        if is_long {
+            self.increment_pc(frame);
+        }
     }
 
     fn set_vf_flag(&mut self, frame: &mut VmFrame, value: u8) {
@@ -417,28 +1558,35 @@ impl Vm {
 
         match code {
             OpCode::Disp_Clear                      => self.op_clear(),
-            OpCode::Disp { x, y, n }                => self.op_draw(frame, frame.registers[x], frame.registers[y], n),
-
-            OpCode::Flow_Call { nnn }               => { self.op_call(frame, nnn); inc_pc = false },
+            OpCode::Disp { x, y, n }                => { result = self.op_draw(frame, frame.registers[x], frame.registers[y], n) },
+            OpCode::Disp_Large { x, y }             => { result = self.op_draw(frame, frame.registers[x], frame.registers[y], 0) },
+            OpCode::Disp_Lores                      => self.display.lock().unwrap().set_hires(false),
+            OpCode::Disp_Hires                      => self.display.lock().unwrap().set_hires(true),
+            OpCode::Disp_Scroll_Down { n }          => self.display.lock().unwrap().scroll(0, n as isize),
+            OpCode::Disp_Scroll_Right               => self.display.lock().unwrap().scroll(4, 0),
+            OpCode::Disp_Scroll_Left                => self.display.lock().unwrap().scroll(-4, 0),
+
+            OpCode::Flow_Call { nnn }               => { result = self.op_call(frame, nnn); inc_pc = false },
             OpCode::Flow_Return                     => { result = self.op_return(frame); },
-            OpCode::Flow_Jump { nnn }               => { frame.PC = nnn; inc_pc = false },
-            OpCode::Flow_Jump_Offset { nnn }        => { frame.PC = frame.registers[0] as u16 + nnn; inc_pc = false },
+            OpCode::Flow_Jump { nnn }               => { self.op_jump(frame, nnn); inc_pc = false },
+            OpCode::Flow_Jump_Offset { nnn }        => { self.op_jump_offset(frame, nnn); inc_pc = false },
+            OpCode::Flow_Exit                       => { self.op_exit(frame); inc_pc = false },
 
-            OpCode::Cond_Eq_Const { x, nn }         => if frame.registers[x] == nn { self.increment_pc(frame) }
-            OpCode::Cond_Neq_Const { x, nn }        => if frame.registers[x] != nn { self.increment_pc(frame) }
-            OpCode::Cond_Eq_Reg { x, y }            => if frame.registers[x] == frame.registers[y] { self.increment_pc(frame) }
-            OpCode::Cond_Neq_Reg { x, y }           => if frame.registers[x] != frame.registers[y] { self.increment_pc(frame) }
+            OpCode::Cond_Eq_Const { x, nn }         => if frame.registers[x] == nn { self.skip_next(frame) }
+            OpCode::Cond_Neq_Const { x, nn }        => if frame.registers[x] != nn { self.skip_next(frame) }
+            OpCode::Cond_Eq_Reg { x, y }            => if frame.registers[x] == frame.registers[y] { self.skip_next(frame) }
+            OpCode::Cond_Neq_Reg { x, y }           => if frame.registers[x] != frame.registers[y] { self.skip_next(frame) }
 
             OpCode::Const_Set_Reg { x, nn }         => frame.registers[x] = nn,
             OpCode::Const_Add_Reg { x, nn }         => frame.registers[x] = frame.registers[x].wrapping_add(nn),
 
             OpCode::Assign { x, y }                 => frame.registers[x] = frame.registers[y],
 
-            OpCode::BitOp_Or { x, y }               => frame.registers[x] |= frame.registers[y],
-            OpCode::BitOp_And { x, y }              => frame.registers[x] &= frame.registers[y],
-            OpCode::BitOp_Xor { x, y }              => frame.registers[x] ^= frame.registers[y],
-            OpCode::BitOp_Shift_Right { x, .. }     => self.op_right_shift(frame, x, x),
-            OpCode::BitOp_Shift_Left { x, .. }      => self.op_left_shift(frame, x, x),
+            OpCode::BitOp_Or { x, y }               => self.op_logic(frame, x, frame.registers[x] | frame.registers[y]),
+            OpCode::BitOp_And { x, y }              => self.op_logic(frame, x, frame.registers[x] & frame.registers[y]),
+            OpCode::BitOp_Xor { x, y }              => self.op_logic(frame, x, frame.registers[x] ^ frame.registers[y]),
+            OpCode::BitOp_Shift_Right { x, y }      => self.op_right_shift(frame, self.shift_source(x, y), x),
+            OpCode::BitOp_Shift_Left { x, y }       => self.op_left_shift(frame, self.shift_source(x, y), x),
 
             OpCode::Math_Add { x, y }               => self.op_math_add(frame, x, y, x),
             OpCode::Math_Minus { x, y }             => self.op_math_minus(frame, x, y, x),
@@ -450,7 +1598,7 @@ impl Vm {
 
             OpCode::Rand { x, nn }                  => self.op_rand(frame, x, nn),
 
-            OpCode::BCD { x }                       => self.op_bcd(frame, frame.registers[x]),
+            OpCode::BCD { x }                       => { result = self.op_bcd(frame, frame.registers[x]) },
 
             OpCode::Timer_Delay_Get { x }           => frame.registers[x] = frame.delay_timer.get_scaled(),
             OpCode::Timer_Delay_Set { x }           => frame.delay_timer.set_scaled(frame.registers[x]),
@@ -458,10 +1606,14 @@ impl Vm {
             OpCode::Sound_Set { x }                 => self.op_sound_set(frame, frame.registers[x]),
 
             OpCode::MEM_Set_I { nnn }               => frame.I = nnn,
+            OpCode::MEM_Set_I_Long                  => { result = self.op_set_i_long(frame) },
             OpCode::MEM_Add_I { x }                 => self.op_mem_add_i(frame, frame.registers[x] as u16),
-            OpCode::MEM_Reg_Dump { x }              => self.op_dump(frame, x),
-            OpCode::MEM_Reg_Load { x }              => self.op_load(frame, x),
-            OpCode::MEM_Set_Sprite_I { x }          => frame.I = (frame.registers[x] as usize * FONT_SYMBOL_SIZE) as u16,
+            OpCode::MEM_Reg_Dump { x }              => { result = self.op_dump(frame, x) },
+            OpCode::MEM_Reg_Load { x }              => { result = self.op_load(frame, x) },
+            OpCode::MEM_Set_Sprite_I { x }          => frame.I = self.font_address + (frame.registers[x] as usize * FONT_SYMBOL_SIZE) as u16,
+            OpCode::MEM_Set_Large_Sprite_I { x }    => self.op_mem_set_large_sprite_i(frame, frame.registers[x]),
+            OpCode::MEM_Flags_Dump { x }            => self.op_flags_dump(frame, x),
+            OpCode::MEM_Flags_Load { x }            => self.op_flags_load(frame, x),
             _                                       => warn!("{:?} not implemented", code),
         };
 
@@ -482,10 +1634,16 @@ impl Vm {
     }
 
     fn op_await_key(&mut self, frame: &mut VmFrame, reg: usize) {
-        let result = {
-            let input = self.input.lock().unwrap();
-            input.get_pressed_key()
-        };
+        let (awaited_key, await_key_release) = (self.awaited_key, self.quirks.await_key_release);
+        let (awaited_key, result) = self.with_input(|input| match awaited_key {
+            Some(key) if input.is_pressed(key) => (Some(key), None),
+            Some(key) => (None, Some(key)),
+            None => match input.take_pressed_key() {
+                Some(key) if await_key_release => (Some(key), None),
+                key => (None, key),
+            },
+        });
+        self.awaited_key = awaited_key;
 
         if let Some(key) = result {
             frame.registers[reg] = key;
@@ -502,22 +1660,52 @@ impl Vm {
     }
 
     fn op_key_conditional_jump(&mut self, frame: &mut VmFrame, key: u8, jump_if_pressed: bool) {
-        let is_pressed;
-        {
-            let input = self.input.lock().unwrap();
-            is_pressed = input.is_pressed(key);
-        }
+        let is_pressed = self.with_input(|input| input.is_pressed(key));
 
         if is_pressed == jump_if_pressed {
-            self.increment_pc(frame)
+            self.skip_next(frame)
         }
     }
 
     fn op_rand(&mut self, frame: &mut VmFrame, reg: usize, mask: u8) {
-        let number: u8 = get_random();
+        let number: u8 = self.rng.gen();
         frame.registers[reg] = number & mask;
     }
 
+    fn op_logic(&mut self, frame: &mut VmFrame, store_reg: usize, value: u8) {
+        frame.registers[store_reg] = value;
+
+        if self.quirks.logic_resets_vf {
+            self.set_vf_flag(frame, 0);
+        }
+    }
+
+    fn shift_source(&self, x: usize, y: usize) -> usize {
+        match self.quirks.shift_uses_vy {
+            true => y,
+            false => x,
+        }
+    }
+
+    fn op_jump(&mut self, frame: &mut VmFrame, address: u16) {
+        if address == frame.PC && self.loop_address.is_none() {
+            info!("ROM halted in an endless loop at {:#05X}", frame.PC);
+            self.loop_address = Some(frame.PC);
+            self.audio.lock().unwrap().playing = false;
+        }
+
+        frame.PC = address;
+    }
+
+    fn op_jump_offset(&mut self, frame: &mut VmFrame, nnn: u16) {
+        let reg = match self.quirks.jump_uses_vx {
+            true => (nnn >> 8) as usize,
+            false => 0,
+        };
+
+        frame.PC = frame.registers[reg] as u16 + nnn;
+    }
+
     fn op_right_shift(&mut self, frame: &mut VmFrame, reg: usize, store_reg: usize) {
         self.set_vf_flag(frame, frame.registers[reg] & 0x1);
         frame.registers[store_reg] = frame.registers[reg] >> 1;
@@ -562,42 +1750,152 @@ impl Vm {
         display.clear();
     }
 
-    fn op_draw(&mut self, frame: &mut VmFrame, x: u8, y: u8, height: u8) {
-        let size = 8 * height;
-        let data = &frame.memory[frame.I as usize..(frame.I + size as u16) as usize];
+    // a height of 0 draws a 16x16 SUPER-CHIP sprite
+    fn op_draw(&mut self, frame: &mut VmFrame, x: u8, y: u8, height: u8) -> Result<()> {
+        let size = match height {
+            0 => 32,
+            _ => height as usize,
+        };
+        self.check_watchpoints(frame, frame.I as usize, size, Access::Read);
+        let data = frame.read(frame.I as usize, size)?;
 
         let result;
         {
             let mut display = self.display.lock().unwrap();
-            result = display.draw_sprite(x as usize, y as usize, height, data);
+            result = match height {
+                0 => display.draw_large_sprite(x as usize, y as usize, data, self.quirks.sprites_wrap),
+                _ => display.draw_sprite(x as usize, y as usize, height, data, self.quirks.sprites_wrap),
+            };
+        }
+
+        if self.quirks.display_wait {
+            self.draw_frame = Some(self.frame_count);
         }
 
-        self.set_vf_flag(frame, match result {
+        let collided = match result {
             DisplayState::Changed => 1,
             DisplayState::Unchanged => 0,
-        });
+        };
+
+        if self.debugger.log_collisions && collided == 1 {
+            let collision = Collision {
+                frame: self.frame_count,
+                x,
+                y,
+                sprite: frame.I,
+                address: frame.PC,
+            };
+
+            info!("Collision: {}", collision);
+            self.debugger.collisions.record(collision);
+        }
+
+        self.set_vf_flag(frame, collided);
+        Ok(())
     }
 
-    fn op_call(&mut self, frame: &mut VmFrame, address: u16) {
+    // the PC stays on the call when the stack is full
+    fn op_call(&mut self, frame: &mut VmFrame, address: u16) -> Result<()> {
+        if frame.stack.len() >= self.stack_depth {
+            return Err(Errors::StackOverflow { depth: self.stack_depth });
+        }
+
         frame.stack.push(StackFrame { return_address: frame.PC });
         frame.PC = address;
+
+        Ok(())
     }
 
     fn op_return(&mut self, vm_frame: &mut VmFrame) -> Result<()> {
         match vm_frame.stack.pop() {
             Some(frame) => { vm_frame.PC = frame.return_address; Ok(()) }
-            None => Err(Errors::StackEmpty.into()),
+            None => Err(Errors::StackEmpty),
         }
     }
 
-    fn op_bcd(&mut self, frame: &mut VmFrame, data: u8) {
+    fn op_bcd(&mut self, frame: &mut VmFrame, data: u8) -> Result<()> {
         let hundreds = data / 100;
         let tens = (data / 10 ) % 10;
         let ones = (data % 100) % 10;
 
-        frame.memory[(frame.I + 0) as usize] = hundreds;
-        frame.memory[(frame.I + 1) as usize] = tens;
-        frame.memory[(frame.I + 2) as usize] = ones;
+        self.write_memory(frame, frame.I as usize, &[hundreds, tens, ones])
+    }
+
+    // every memory read and write of an instruction passes through here, it is counted in the heatmap too
+    // the instruction completes, the debugger halts before the next one
+    fn check_watchpoints(&mut self, frame: &VmFrame, address: usize, len: usize, access: Access) {
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            match access {
+                Access::Write => heatmap.record_write(address, len),
+                _ => heatmap.record_read(address, len),
+            }
+        }
+
+        if self.debugger.enabled == false || self.debugger.watchpoints.lock().unwrap().hit(address, len, access) == false {
+            return;
+        }
+
+        let hit = WatchpointHit {
+            frame: self.frame_count,
+            access,
+            address,
+            len,
+            pc: frame.PC,
+        };
+
+        println!("Debugger: watchpoint hit, {}", hit);
+        self.debugger.enable_break.store(true, Ordering::SeqCst);
+    }
+
+    // the write still happens when it only breaks, so it can be inspected in the debugger
+    fn write_memory(&mut self, frame: &mut VmFrame, address: usize, data: &[u8]) -> Result<()> {
+        self.check_watchpoints(frame, address, data.len(), Access::Write);
+
+        if address < self.load_address as usize {
+            let error = Errors::WriteProtected { address, len: data.len(), pc: frame.PC, end: self.load_address };
+
+            match self.write_protection {
+                WriteProtection::Off => {}
+                WriteProtection::Error => return Err(error),
+                WriteProtection::Break => {
+                    warn!("{}", error);
+                    self.debugger.enable_break.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+
+        for modification in self.find_self_modifications(frame, address, data) {
+            info!("Self-modification: {}", modification);
+        }
+
+        frame.write(address, data)
+    }
+
+    fn mark_executed(&mut self, address: u16) {
+        let address = address as usize;
+        if let Some(executed) = self.executed.get_mut(address..address + 2) {
+            executed.copy_from_slice(&[true, true]);
+        }
+    }
+
+    // writes of the same value do not change the code
+    fn find_self_modifications(&self, frame: &VmFrame, address: usize, data: &[u8]) -> Vec<SelfModification> {
+        data.iter()
+            .enumerate()
+            .filter(|(offset, _)| self.executed.get(address + offset) == Some(&true))
+            .filter_map(|(offset, value)| {
+                let previous = *frame.memory.get(address + offset)?;
+
+                Some(SelfModification {
+                    frame: self.frame_count,
+                    address: (address + offset) as u16,
+                    previous,
+                    value: *value,
+                    pc: frame.PC,
+                })
+            })
+            .filter(|x| x.previous != x.value)
+            .collect()
     }
 
     fn op_mem_add_i(&mut self, frame: &mut VmFrame, data: u16) {
@@ -610,20 +1908,65 @@ impl Vm {
         });
     }
 
-    fn op_dump(&mut self, frame: &mut VmFrame, offset: usize) {
-        for n in 0..offset + 1 {
-            frame.memory[frame.I as usize + n] = frame.registers[n];
+    fn op_dump(&mut self, frame: &mut VmFrame, offset: usize) -> Result<()> {
+        let registers = frame.registers;
+        self.write_memory(frame, frame.I as usize, &registers[..offset + 1])?;
+
+        if self.quirks.load_store_keeps_i == false {
+            frame.I = frame.I.wrapping_add(offset as u16 + 1);
+        }
+
+        Ok(())
+    }
+
+    fn op_load(&mut self, frame: &mut VmFrame, offset: usize) -> Result<()> {
+        self.check_watchpoints(frame, frame.I as usize, offset + 1, Access::Read);
+        let values = frame.read(frame.I as usize, offset + 1)?.to_vec();
+        frame.registers[..offset + 1].copy_from_slice(&values);
+
+        if self.quirks.load_store_keeps_i == false {
+            frame.I = frame.I.wrapping_add(offset as u16 + 1);
         }
 
-        frame.I += offset as u16 + 1;
+        Ok(())
     }
 
-    fn op_load(&mut self, frame: &mut VmFrame, offset: usize) {
-        for n in 0..offset + 1 {
-            frame.registers[n] = frame.memory[frame.I as usize + n];
+    // F000 NNNN, the address is the word after the instruction
+    fn op_set_i_long(&mut self, frame: &mut VmFrame) -> Result<()> {
+        let address = frame.PC as usize + PC_INCREMENT as usize;
+        if let Some(heatmap) = self.heatmap.as_mut() {
+            heatmap.record_execute(address, 2);
         }
+        let bytes = frame.read(address, 2)?;
+        frame.I = u16::from_be_bytes([bytes[0], bytes[1]]);
+
+        self.increment_pc(frame);
+        Ok(())
+    }
+
+    fn op_mem_set_large_sprite_i(&mut self, frame: &mut VmFrame, digit: u8) {
+        let offset = FONTS.len() + (digit as usize % 10) * BIG_FONT_SYMBOL_SIZE;
+        frame.I = self.font_address + offset as u16;
+    }
+
+    // there are only RPL_FLAG_COUNT flags, higher registers are ignored
+    fn op_flags_dump(&mut self, frame: &mut VmFrame, offset: usize) {
+        let count = (offset + 1).min(RPL_FLAG_COUNT);
+        frame.rpl_flags[..count].copy_from_slice(&frame.registers[..count]);
+    }
+
+    fn op_flags_load(&mut self, frame: &mut VmFrame, offset: usize) {
+        let count = (offset + 1).min(RPL_FLAG_COUNT);
+        frame.registers[..count].copy_from_slice(&frame.rpl_flags[..count]);
+    }
 
-        frame.I += offset as u16 + 1;
+    // the PC stays on 00FD so the ROM halts with its last screen
+    fn op_exit(&mut self, frame: &mut VmFrame) {
+        if self.exit_address != Some(frame.PC) {
+            info!("ROM exited at {:#05X}", frame.PC);
+            self.exit_address = Some(frame.PC);
+            self.audio.lock().unwrap().playing = false;
+        }
     }
 }
 
@@ -632,12 +1975,15 @@ mod tests {
     use super::*;
     use super::super::display::MockDisplay;
     use super::super::input::MockInput;
+    use super::super::breakpoints::Breakpoints;
+    use super::super::symbols::Symbols;
+    use super::super::watchpoints::{Watchpoint, Watchpoints};
 
     use std::sync::atomic::AtomicBool;
     use std::sync::mpsc::{channel, Sender};
 
     use mockall::*;
-    use mocktopus::mocking::*;
+    use proptest::prelude::*;
     use test_case::test_case;
 
     #[allow(dead_code)]
@@ -651,7 +1997,10 @@ mod tests {
     }
 
     fn new() -> TestData {
-        let config = Config::default();
+        with_config(&Config::default(), &vec![0, 0])
+    }
+
+    fn with_config(config: &Config, rom: &Vec<u8>) -> TestData {
         let (tx, rx) = channel::<DebuggerCommand>();
 
         let display = Arc::new(Mutex::new(MockDisplay::new()));
@@ -660,14 +2009,20 @@ mod tests {
         TestData {
             tx,
             vm: Vm::new(
-                &config,
-                &vec![0, 0],
+                config,
+                rom,
                 display.clone(),
                 input.clone(),
                 Arc::new(Mutex::new(Audio::new())),
-                Debugger::new(&config, Arc::new(AtomicBool::new(false)), rx))
+                Debugger::new(
+                    config,
+                    Arc::new(AtomicBool::new(false)),
+                    rx,
+                    Arc::new(Mutex::new(Breakpoints::new())),
+                    Arc::new(Mutex::new(Watchpoints::new(config.debugger.watchpoints.clone())))),
+                Arc::new(Mutex::new(Cheats::default())))
             .unwrap(),
-            frame: VmFrame::new(),
+            frame: VmFrame::new(PC_START, MEMORY_SIZE),
             display,
             input,
         }
@@ -676,6 +2031,12 @@ mod tests {
     #[test_case(0x_0123_u16, OpCode::Raw_Call                { nnn: 0x123 }              )]
     #[test_case(0x_00E0_u16, OpCode::Disp_Clear                                          )]
     #[test_case(0x_00EE_u16, OpCode::Flow_Return                                         )]
+    #[test_case(0x_00C4_u16, OpCode::Disp_Scroll_Down        { n: 4 }                    )]
+    #[test_case(0x_00FB_u16, OpCode::Disp_Scroll_Right                                   )]
+    #[test_case(0x_00FC_u16, OpCode::Disp_Scroll_Left                                    )]
+    #[test_case(0x_00FD_u16, OpCode::Flow_Exit                                           )]
+    #[test_case(0x_00FE_u16, OpCode::Disp_Lores                                          )]
+    #[test_case(0x_00FF_u16, OpCode::Disp_Hires                                          )]
     #[test_case(0x_1ABC_u16, OpCode::Flow_Jump               { nnn: 0xABC }              )]
     #[test_case(0x_2ABC_u16, OpCode::Flow_Call               { nnn: 0xABC }              )]
     #[test_case(0x_3123_u16, OpCode::Cond_Eq_Const           { x: 0x1, nn: 0x23 }        )]
@@ -697,23 +2058,611 @@ mod tests {
     #[test_case(0x_B123_u16, OpCode::Flow_Jump_Offset        { nnn: 0x123 }              )]
     #[test_case(0x_C123_u16, OpCode::Rand                    { x: 0x1, nn: 0x23 }        )]
     #[test_case(0x_D123_u16, OpCode::Disp                    { x: 0x1, y: 0x2, n: 3 }    )]
+    #[test_case(0x_D120_u16, OpCode::Disp_Large              { x: 0x1, y: 0x2 }          )]
     #[test_case(0x_E19E_u16, OpCode::KeyOp_Skip_Pressed      { x: 0x1 }                  )]
     #[test_case(0x_E1A1_u16, OpCode::KeyOp_Skip_Not_Pressed  { x: 0x1 }                  )]
+    #[test_case(0x_F000_u16, OpCode::MEM_Set_I_Long                                      )]
     #[test_case(0x_F107_u16, OpCode::Timer_Delay_Get         { x: 0x1 }                  )]
     #[test_case(0x_F10A_u16, OpCode::KeyOp_Await             { x: 0x1 }                  )]
     #[test_case(0x_F115_u16, OpCode::Timer_Delay_Set         { x: 0x1 }                  )]
     #[test_case(0x_F118_u16, OpCode::Sound_Set               { x: 0x1 }                  )]
     #[test_case(0x_F11E_u16, OpCode::MEM_Add_I               { x: 0x1 }                  )]
     #[test_case(0x_F129_u16, OpCode::MEM_Set_Sprite_I        { x: 0x1 }                  )]
+    #[test_case(0x_F130_u16, OpCode::MEM_Set_Large_Sprite_I  { x: 0x1 }                  )]
     #[test_case(0x_F133_u16, OpCode::BCD                     { x: 0x1 }                  )]
     #[test_case(0x_F155_u16, OpCode::MEM_Reg_Dump            { x: 0x1 }                  )]
     #[test_case(0x_F165_u16, OpCode::MEM_Reg_Load            { x: 0x1 }                  )]
+    #[test_case(0x_F175_u16, OpCode::MEM_Flags_Dump          { x: 0x1 }                  )]
+    #[test_case(0x_F185_u16, OpCode::MEM_Flags_Load          { x: 0x1 }                  )]
     fn decode(code: u16, expected: OpCode) {
         let vm = new().vm;
 
-        let actual = vm.decode(code);
+        let actual = vm.decode(code);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn new_loads_rom_at_load_address() {
+        let mut config = Config::default();
+        config.load_address = 0x600;
+
+        let d = with_config(&config, &vec![0x12, 0x34]);
+        let frame = &d.vm.frames[0];
+
+        assert_eq!(frame.PC, 0x600);
+        assert_eq!(&frame.memory[0x600..0x602], &[0x12, 0x34]);
+        assert_eq!(&frame.memory[0x200..0x202], &[0, 0]);
+    }
+
+    #[test]
+    fn new_loads_memory_image() {
+        let mut image = vec![0u8; MEMORY_SIZE];
+        image[0] = 0xAB;
+        image[0x200] = 0x12;
+
+        let d = with_config(&Config::default(), &image);
+        let frame = &d.vm.frames[0];
+
+        assert_eq!(frame.PC, PC_START);
+        assert_eq!(&frame.memory[..], &image[..]);
+    }
+
+    struct TestPeripheral {
+        writes: Arc<Mutex<Vec<(usize, u8)>>>,
+    }
+
+    impl Peripheral for TestPeripheral {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn claims_opcode(&self, opcode: u16) -> bool {
+            opcode == 0x0123
+        }
+
+        fn execute(&mut self, _opcode: u16, registers: &mut [u8], memory: &mut [u8]) -> anyhow::Result<()> {
+            registers[0] = 7;
+            memory[0xE00] = 1;
+            Ok(())
+        }
+
+        fn memory_range(&self) -> Option<std::ops::Range<usize>> {
+            Some(0xE00..0xE02)
+        }
+
+        fn memory_written(&mut self, address: usize, value: u8) {
+            self.writes.lock().unwrap().push((address, value));
+        }
+    }
+
+    #[test]
+    fn peripheral_executes_claimed_opcode() {
+        let writes = Arc::new(Mutex::new(Vec::new()));
+        let mut d = with_config(&Config::default(), &vec![0x01, 0x23]);
+        d.vm.peripherals.push(Box::new(TestPeripheral { writes: writes.clone() }));
+
+        d.vm.step().unwrap();
+
+        let frame = d.vm.get_current_frame();
+        assert_eq!(frame.registers[0], 7);
+        assert_eq!(frame.PC, PC_START + 2);
+        assert_eq!(*writes.lock().unwrap(), vec![(0xE00, 1)]);
+    }
+
+    #[test]
+    fn run_to_breaks_once_at_address() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0x60, 0x03]);
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+        d.tx.send(DebuggerCommand::RunTo(0x204)).unwrap();
+
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        for _ in 0..3 {
+            d.vm.step().unwrap();
+        }
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, 0x204);
+        assert_eq!(d.vm.get_current_frame().registers[0], 2);
+        assert_eq!(d.vm.debugger.run_to, None);
+    }
+
+    #[test]
+    fn added_breakpoint_breaks_at_address() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0x60, 0x03]);
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+        d.tx.send(DebuggerCommand::AddBreakpoint(0x204)).unwrap();
+        d.tx.send(DebuggerCommand::Continue).unwrap();
+
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        for _ in 0..4 {
+            d.vm.step().unwrap();
+        }
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, 0x204);
+        assert_eq!(d.vm.debugger.breakpoints.lock().unwrap().entries()[0].hit_count, 1);
+    }
+
+    #[test]
+    fn reverse_continue_goes_back_to_the_previous_break() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+        config.debugger.watchpoints = vec![Watchpoint::new(0x300, None, Access::Write)];
+
+        // 0x200: V0 = 1, 0x202: V0 = 2, 0x204: I = 0x300, 0x206: store V0 at I, 0x208: V0 = 3
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0xA3, 0x00, 0xF0, 0x55, 0x60, 0x03, 0x00, 0x00]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        // the write breaks, the last instruction runs after it
+        for _ in 0..4 {
+            d.vm.step().unwrap();
+        }
+        d.vm.debugger.enable_break.store(false, Ordering::SeqCst);
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.get_current_frame().PC, 0x20A);
+
+        d.vm.debugger.breakpoints.lock().unwrap().add(0x202);
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+
+        d.tx.send(DebuggerCommand::ReverseContinue).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.get_current_frame().PC, 0x208);
+
+        d.tx.send(DebuggerCommand::ReverseContinue).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.get_current_frame().PC, 0x202);
+        assert_eq!(d.vm.debugger.breakpoints.lock().unwrap().entries()[0].hit_count, 0);
+
+        d.tx.send(DebuggerCommand::ReverseContinue).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.frame_pointer, 0);
+    }
+
+    #[test]
+    fn diff_lists_only_changed_values() {
+        let mut before = VmFrame::new(PC_START, MEMORY_SIZE);
+        let mut after = before.clone();
+        assert!(before.diff(&after).is_empty());
+
+        after.registers[0xA] = 2;
+        after.PC = 0x202;
+        after.stack.push(StackFrame { return_address: 0x200 });
+        after.memory[0x300..0x302].copy_from_slice(&[1, 2]);
+        after.memory[0x310] = 0xFF;
+        before.I = 0x300;
+
+        assert_eq!(
+            before.diff(&after),
+            vec![
+                "VA: 0x00 -> 0x02",
+                "I: 0x300 -> 0x000",
+                "PC: 0x200 -> 0x202",
+                "Stack #0: none -> 0x200",
+                "Memory 0x300: 00 00 -> 01 02",
+                "Memory 0x310: 00 -> FF",
+            ]
+        );
+    }
+
+    #[test]
+    fn opcode_names_match_the_decoded_variants() {
+        let d = new();
+        let mut decoded: Vec<String> = (0..=0xFFFF_u16)
+            .map(|x| d.vm.decode_quietly(x))
+            .filter(|x| *x != OpCode::Unknown)
+            .map(|x| x.name())
+            .collect();
+        decoded.sort();
+        decoded.dedup();
+
+        let mut names: Vec<String> = OpCode::NAMES.iter().map(|x| x.to_string()).collect();
+        names.sort();
+
+        assert_eq!(decoded, names);
+    }
+
+    #[test]
+    fn coverage_counts_the_executed_variants() {
+        let mut d = with_config(&Config::default(), &vec![0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        d.vm.enable_coverage();
+        for _ in 0..4 {
+            d.vm.step().unwrap();
+        }
+
+        let report = d.vm.coverage_report().unwrap();
+        assert_eq!(report[0], format!("Opcode coverage: 3 of {} executed (6.5%)", OpCode::NAMES.len()));
+        assert_eq!(report[1], "  Flow_Jump                          1");
+        assert_eq!(report[2], "  Const_Set_Reg                      1");
+        assert_eq!(report[3], "  Const_Add_Reg                      2");
+    }
+
+    #[test]
+    fn heatmap_counts_reads_writes_and_executes() {
+        let mut d = with_config(&Config::default(), &vec![0xA3, 0x00, 0xF1, 0x55, 0xF0, 0x65]);
+        assert!(d.vm.get_heatmap().is_none());
+
+        d.vm.enable_heatmap();
+        for _ in 0..3 {
+            d.vm.step().unwrap();
+        }
+
+        let heatmap = d.vm.get_heatmap().unwrap();
+        assert_eq!(&heatmap.executes[0x1FF..0x207], &[0, 1, 1, 1, 1, 1, 1, 0]);
+        assert_eq!(&heatmap.writes[0x300..0x303], &[1, 1, 0]);
+        assert_eq!(heatmap.writes.iter().sum::<u64>(), 2);
+        assert_eq!(heatmap.reads.iter().sum::<u64>(), 1);
+    }
+
+    #[test]
+    fn profile_report_lists_hot_addresses_and_opcodes() {
+        let mut d = with_config(&Config::default(), &vec![0x60, 0x01, 0x70, 0x01, 0x12, 0x02]);
+        assert_eq!(d.vm.profile_report(2), None);
+
+        d.vm.enable_profiler();
+        for _ in 0..7 {
+            d.vm.step().unwrap();
+        }
+
+        assert_eq!(
+            d.vm.profile_report(2).unwrap(),
+            vec![
+                "Executed 7 instructions",
+                "Hot addresses:",
+                "  0x202           3   42.9%  Const_Add_Reg",
+                "  0x204           3   42.9%  Flow_Jump",
+                "Opcodes:",
+                "  Const_Add_Reg                      3   42.9%",
+                "  Flow_Jump                          3   42.9%",
+            ]
+        );
+    }
+
+    #[test]
+    fn watches_are_printed_once_per_shown_frame() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+        config.debugger.watches = vec![String::from("V0"), String::from("V16")];
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0x00, 0x00]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+        assert_eq!(d.vm.debugger.watches.len(), 1);
+
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.debugger.watched_frame, None);
+
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+        d.tx.send(DebuggerCommand::AddWatch(String::from("[0x200:2]"))).unwrap();
+        d.tx.send(DebuggerCommand::AddWatch(String::from("[0x200"))).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.debugger.watches.len(), 2);
+        assert_eq!(d.vm.debugger.watched_frame, Some(1));
+
+        d.tx.send(DebuggerCommand::Previous).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.debugger.watched_frame, Some(0));
+    }
+
+    #[test]
+    fn goto_frame_moves_the_frame_pointer() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0x60, 0x03, 0x00, 0x00]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        for _ in 0..3 {
+            d.vm.step().unwrap();
+        }
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+
+        d.tx.send(DebuggerCommand::GotoFirstFrame).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.frame_pointer, 0);
+        assert_eq!(d.vm.get_current_frame().PC, PC_START);
+
+        d.tx.send(DebuggerCommand::GotoFrame(2)).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.get_current_frame().registers[0], 2);
+
+        d.tx.send(DebuggerCommand::GotoFrame(4)).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.frame_pointer, 2);
+
+        d.tx.send(DebuggerCommand::GotoLastFrame).unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.frame_pointer, 3);
+        assert_eq!(d.vm.get_current_frame().registers[0], 3);
+    }
+
+    #[test]
+    fn debug_view_disassembles_around_pc() {
+        // 0x200: call 0x206, 0x202: V0 = 2, 0x206: unknown
+        let mut d = with_config(&Config::default(), &vec![0x22, 0x06, 0x60, 0x02, 0x00, 0x00, 0xFF, 0xFF]);
+        d.vm.step().unwrap();
+
+        let view = d.vm.get_debug_view();
+        assert_eq!(view.pc, 0x206);
+        assert_eq!(view.stack, vec![0x200]);
+        assert_eq!(view.disassembly[0], (0x1FE, 0x0000, String::from("Raw_Call { nnn: 0 }")));
+        assert_eq!(view.disassembly[2].1, 0x6002);
+        assert_eq!(view.disassembly[4], (0x206, 0xFFFF, String::from("Unknown")));
+        assert_eq!(view.disassembly.len(), 15);
+    }
+
+    #[test]
+    fn debug_view_shows_labels() {
+        // 0x200: jump 0x204, 0x204: I = 0x206
+        let mut d = with_config(&Config::default(), &vec![0x12, 0x04, 0x00, 0x00, 0xA2, 0x06]);
+        let mut symbols = Symbols::default();
+        symbols.add("main", 0x200);
+        symbols.add("sprite", 0x204);
+        d.vm.debugger.symbols = Arc::new(symbols);
+
+        let view = d.vm.get_debug_view();
+        let decoded = |address: u16| view.disassembly.iter().find(|x| x.0 == address).map(|x| x.2.clone());
+        assert_eq!(decoded(0x200).unwrap(), "main: Flow_Jump { nnn: 516 } -> sprite");
+        assert_eq!(decoded(0x202).unwrap(), "Raw_Call { nnn: 0 }");
+        assert_eq!(decoded(0x204).unwrap(), "sprite: MEM_Set_I { nnn: 518 } -> sprite+2");
+    }
+
+    #[test]
+    fn pokes_change_the_current_frame() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        // 0x200: V0 += 1, 0x202: V0 += 1
+        let mut d = with_config(&config, &vec![0x70, 0x01, 0x70, 0x01, 0x00, 0x00]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+        d.tx.send(DebuggerCommand::Previous).unwrap();
+        d.tx.send(DebuggerCommand::SetRegister { register: 0, value: 0x10 }).unwrap();
+        d.tx.send(DebuggerCommand::SetRegister { register: 0x10, value: 1 }).unwrap();
+        d.tx.send(DebuggerCommand::SetI(0x300)).unwrap();
+        d.tx.send(DebuggerCommand::WriteMemory { address: 0x204, data: vec![0x70, 0x02] }).unwrap();
+        d.vm.step().unwrap();
+
+        let frame = d.vm.get_current_frame();
+        assert_eq!(d.vm.frames.len(), 2);
+        assert_eq!(frame.registers[0], 0x10);
+        assert_eq!(frame.I, 0x300);
+        assert_eq!(frame.memory[0x204..0x206], [0x70, 0x02]);
+
+        d.tx.send(DebuggerCommand::SetPC(0x204)).unwrap();
+        d.tx.send(DebuggerCommand::Next).unwrap();
+        d.vm.step().unwrap();
+
+        assert_eq!(d.vm.get_current_frame().registers[0], 0x12);
+        assert_eq!(d.vm.get_current_frame().PC, 0x206);
+    }
+
+    #[test]
+    fn run_until_return_breaks_after_the_return() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        // 0x200: call 0x206, 0x202: V0 = 2, 0x206: V0 = 1, V1 = 1, return
+        let mut d = with_config(&config, &vec![0x22, 0x06, 0x60, 0x02, 0x00, 0x00, 0x60, 0x01, 0x61, 0x01, 0x00, 0xEE]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        d.vm.step().unwrap();
+        d.vm.debugger.enable_break.store(true, Ordering::SeqCst);
+        d.tx.send(DebuggerCommand::RunUntilReturn).unwrap();
+
+        for _ in 0..6 {
+            d.vm.step().unwrap();
+        }
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 2);
+        assert_eq!(d.vm.get_current_frame().registers[1], 1);
+        assert_eq!(d.vm.debugger.run_until_depth, None);
+    }
+
+    #[test]
+    fn break_on_opcode_class_breaks_before_it_executes() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+        config.debugger.break_on_opcodes = vec![String::from("disp"), String::from("Flow_Call")];
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x00, 0xE0, 0x60, 0x02]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 2);
+        assert!(OpCode::Disp_Large { x: 0, y: 0 }.is_in_class("Disp"));
+        assert!(OpCode::Flow_Call { nnn: 0 }.is_in_class("Flow") && OpCode::Flow_Jump { nnn: 0 }.is_in_class("Flow_Call") == false);
+        assert_eq!(OpCode::Disp { x: 0, y: 0, n: 1 }.is_in_class("Dis"), false);
+    }
+
+    #[test]
+    fn break_condition_breaks_when_it_starts_to_hold() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+        config.debugger.break_conditions = vec![String::from("V0 == 2"), String::from("V0 ==")];
+
+        let mut d = with_config(&config, &vec![0x70, 0x01, 0x70, 0x01, 0x70, 0x00, 0x70, 0x01]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        for _ in 0..3 {
+            d.vm.step().unwrap();
+        }
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 4);
+        assert_eq!(d.vm.debugger.break_conditions.len(), 1);
+
+        // it still holds after resuming, which does not break again
+        d.vm.leave_break();
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.debugger.enable_break.load(Ordering::SeqCst), false);
+        assert_eq!(d.vm.get_current_frame().registers[0], 3);
+    }
+
+    #[test]
+    fn write_watchpoint_breaks_after_the_write() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+        config.debugger.watchpoints = vec![Watchpoint::new(0x300, Some(0x30F), Access::Write)];
+
+        let mut d = with_config(&config, &vec![0xA3, 0x00, 0xF0, 0x65, 0xF0, 0x55, 0x60, 0x01]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+        assert_eq!(d.vm.debugger.enable_break.load(Ordering::SeqCst), false);
+
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+
+        assert!(d.vm.debugger.enable_break.load(Ordering::SeqCst));
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 6);
+    }
+
+    #[test]
+    fn advance_frame_runs_a_single_frame() {
+        let mut d = with_config(&Config::default(), &vec![0x70, 0x01, 0x12, 0x00]);
+
+        d.vm.advance_frame().unwrap();
+        assert_eq!(d.vm.get_frame_count(), 1);
+
+        d.vm.advance_frame().unwrap();
+        assert_eq!(d.vm.get_frame_count(), 2);
+        assert!(d.vm.get_current_frame().registers[0] > 0);
+    }
+
+    #[test]
+    fn rewind_restores_the_start_of_previous_frames() {
+        let mut d = with_config(&Config::default(), &vec![0x70, 0x01, 0x12, 0x00]);
+        d.vm.enable_rewind(1);
+        {
+            let mut display = d.display.lock().unwrap();
+            display.expect_get_screen().return_const(Screen::new());
+            display.expect_set_screen().return_const(());
+        }
 
-        assert_eq!(actual, expected);
+        for _ in 0..3 {
+            d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        }
+        assert_eq!(d.vm.get_current_frame().registers[0], 2);
+        assert_eq!(d.vm.get_frame_count(), 3);
+
+        assert!(d.vm.rewind());
+        assert_eq!(d.vm.get_current_frame().registers[0], 1);
+        assert_eq!(d.vm.get_current_frame().PC, PC_START);
+        assert_eq!(d.vm.get_frame_count(), 3);
+
+        assert!(d.vm.rewind());
+        assert!(d.vm.rewind());
+        assert_eq!(d.vm.get_current_frame().registers[0], 0);
+        assert_eq!(d.vm.get_frame_count(), 1);
+        assert_eq!(d.vm.rewind(), false);
+    }
+
+    #[test]
+    fn rewind_appends_to_the_debugger_history() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        let mut d = with_config(&config, &vec![0x70, 0x01, 0x12, 0x00]);
+        d.vm.enable_rewind(1);
+        {
+            let mut display = d.display.lock().unwrap();
+            display.expect_get_screen().return_const(Screen::new());
+            display.expect_set_screen().return_const(());
+        }
+
+        d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        assert_eq!(d.vm.frame_pointer, 2);
+
+        assert!(d.vm.rewind());
+        assert_eq!(d.vm.frames.len(), 4);
+        assert_eq!(d.vm.frame_pointer, 3);
+        assert_eq!(d.vm.frames[2].registers[0], 1);
+        assert_eq!(d.vm.get_current_frame().registers[0], 1);
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 2);
+        assert_eq!(d.vm.get_frame_count(), 2);
+    }
+
+    #[test]
+    fn set_history_restores_exported_frames() {
+        let mut config = Config::default();
+        config.debugger.enable = true;
+
+        let mut d = with_config(&config, &vec![0x60, 0x01, 0x60, 0x02, 0x60, 0x03]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen.expect_set_screen().return_const(());
+            screen.expect_get_screen().return_const(Screen::new());
+        }
+
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+        let history = d.vm.get_history();
+        d.vm.step().unwrap();
+
+        d.vm.set_history(&history).unwrap();
+
+        assert_eq!(d.vm.frames.len(), 3);
+        assert_eq!(d.vm.frame_pointer, 2);
+        assert_eq!(d.vm.get_current_frame().registers[0], 2);
+        assert!(d.vm.set_history(&FrameHistory { frame_pointer: 3, ..history }).is_err());
     }
 
     #[test]
@@ -731,6 +2680,34 @@ mod tests {
         d.vm.execute(&mut d.frame, OpCode::Disp_Clear).unwrap();
     }
 
+    #[test_case(DisplayState::Changed,   1 ; "collision")]
+    #[test_case(DisplayState::Unchanged, 0 ; "no collision")]
+    fn op_draw_logs_collisions(state: DisplayState, expected: usize) {
+        let mut d = new();
+        d.vm.debugger.log_collisions = true;
+        d.frame.registers[1] = 12;
+        d.frame.registers[2] = 7;
+        d.frame.I = 0x300;
+
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen
+                .expect_draw_sprite()
+                .times(1)
+                .return_const(state);
+        }
+
+        d.vm.execute(&mut d.frame, OpCode::Disp { x: 1, y: 2, n: 1 }).unwrap();
+
+        let collisions: Vec<&Collision> = d.vm.debugger.collisions.entries().collect();
+        assert_eq!(collisions.len(), expected);
+        assert_eq!(d.frame.registers[0xF] as usize, expected);
+
+        if let Some(collision) = collisions.first() {
+            assert_eq!((collision.x, collision.y, collision.sprite), (12, 7, 0x300));
+        }
+    }
+
     #[test]
     fn op_flow_call_return() {
         let mut d = new();
@@ -748,6 +2725,109 @@ mod tests {
         assert_eq!(d.frame.stack.len(), 0);
     }
 
+    #[test]
+    fn op_flow_call_overflows_stack() {
+        let mut d = new();
+        d.frame.PC = 0x300;
+
+        for _ in 0..STACK_DEPTH {
+            d.vm.execute(&mut d.frame, OpCode::Flow_Call { nnn: 0x300 }).unwrap();
+        }
+
+        let result = d.vm.execute(&mut d.frame, OpCode::Flow_Call { nnn: 0x400 });
+
+        assert!(matches!(result, Err(Errors::StackOverflow { depth: STACK_DEPTH })));
+        assert_eq!(d.frame.PC, 0x300);
+        assert_eq!(d.frame.stack.len(), STACK_DEPTH);
+    }
+
+    #[test_case(OpCode::Disp { x: 0x1, y: 0x2, n: 3 } ; "draw")]
+    #[test_case(OpCode::BCD { x: 0x1 }                 ; "bcd")]
+    #[test_case(OpCode::MEM_Reg_Dump { x: 0x3 }        ; "dump")]
+    #[test_case(OpCode::MEM_Reg_Load { x: 0x3 }        ; "load")]
+    fn op_memory_access_out_of_bounds(opcode: OpCode) {
+        let mut d = new();
+        d.frame.I = (MEMORY_SIZE - 2) as u16;
+
+        let result = d.vm.execute(&mut d.frame, opcode);
+
+        assert!(result.is_err());
+    }
+
+    #[test_case(WriteProtection::Error, false ; "error")]
+    #[test_case(WriteProtection::Break, true   ; "break")]
+    fn write_below_load_address_is_trapped(write_protection: WriteProtection, written: bool) {
+        let mut config = Config::default();
+        config.write_protection = write_protection;
+
+        let mut d = with_config(&config, &vec![0x00, 0xE0]);
+        d.frame.registers[0] = 123;
+        d.frame.I = 0x10;
+
+        let result = d.vm.execute(&mut d.frame, OpCode::BCD { x: 0 });
+
+        assert_eq!(result.is_ok(), written);
+        assert_eq!(d.frame.memory[0x10] == 1, written);
+        assert_eq!(d.vm.debugger.enable_break.load(Ordering::SeqCst), written);
+    }
+
+    #[test]
+    fn writes_into_executed_code_are_self_modifications() {
+        let mut config = Config::default();
+        config.debugger.log_self_modification = true;
+
+        let mut d = with_config(&config, &vec![0xA2, 0x00, 0x60, 0xAB]);
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+
+        let frame = d.vm.get_current_frame();
+        let modifications = d.vm.find_self_modifications(frame, 0x200, &[0xAB, 0x00]);
+
+        assert_eq!(
+            modifications,
+            vec![SelfModification { frame: d.vm.get_frame_count(), address: 0x200, previous: 0xA2, value: 0xAB, pc: 0x204 }]
+        );
+        assert!(d.vm.find_self_modifications(frame, 0x300, &[0xFF]).is_empty());
+    }
+
+    #[test]
+    fn set_state_restores_saved_state() {
+        let mut d = with_config(&Config::default(), &vec![0x60, 0x01, 0x22, 0x00]);
+        {
+            let mut display = d.display.lock().unwrap();
+            display.expect_get_screen().return_const(Screen::new());
+            display.expect_set_screen().return_const(());
+        }
+
+        d.vm.step().unwrap();
+        let state = d.vm.get_state();
+        d.vm.step().unwrap();
+        d.vm.step().unwrap();
+
+        d.vm.set_state(&state).unwrap();
+
+        assert_eq!(d.vm.get_state(), state);
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 2);
+        assert_eq!(d.vm.get_current_frame().stack.len(), 0);
+        assert!(matches!(
+            d.vm.set_state(&SaveState { rom_hash: String::new(), ..state }),
+            Err(Errors::SaveStateRomMismatch)
+        ));
+    }
+
+    #[test]
+    fn fetch_reports_pc_out_of_range() {
+        let mut d = with_config(&Config::default(), &vec![0x1F, 0xFF]);
+
+        d.vm.step().unwrap();
+        let error = d.vm.step().unwrap_err();
+
+        match error {
+            Errors::PcOutOfRange { address, size } => assert_eq!((address, size), (0xFFF, MEMORY_SIZE)),
+            _ => panic!("unexpected error {}", error),
+        }
+    }
+
     #[test]
     fn op_jump() {
         let mut d = new();
@@ -755,6 +2835,23 @@ mod tests {
         d.vm.execute(&mut d.frame, OpCode::Flow_Jump { nnn: 123 }).unwrap();
 
         assert_eq!(d.frame.PC, 123);
+        assert!(d.vm.has_halted() == false);
+    }
+
+    #[test]
+    fn jump_to_self_halts() {
+        let mut d = with_config(&Config::default(), &vec![0x00, 0xE0, 0x12, 0x02]);
+        {
+            let mut display = d.display.lock().unwrap();
+            display.expect_clear().return_const(());
+        }
+
+        d.vm.step().unwrap();
+        assert!(d.vm.has_halted() == false);
+
+        d.vm.step().unwrap();
+        assert!(d.vm.has_halted());
+        assert_eq!(d.vm.get_current_frame().PC, 0x202);
     }
 
     // Cond_Eq
@@ -852,6 +2949,32 @@ mod tests {
         }
     }
 
+    #[test]
+    fn op_await_key_waits_for_release_with_quirk() {
+        let mut d = new();
+        d.vm.quirks.await_key_release = true;
+
+        let input = d.input.clone();
+        let expect_pressed = |pressed: bool| {
+            let mut input = input.lock().unwrap();
+            input.checkpoint();
+            input.expect_is_pressed().return_const(pressed);
+        };
+
+        d.input.lock().unwrap().expect_take_pressed_key().times(1).return_const(Some(3u8));
+        d.vm.execute(&mut d.frame, OpCode::KeyOp_Await { x: 0 }).unwrap();
+        assert_eq!(d.frame.PC, PC_START);
+
+        expect_pressed(true);
+        d.vm.execute(&mut d.frame, OpCode::KeyOp_Await { x: 0 }).unwrap();
+        assert_eq!(d.frame.PC, PC_START);
+
+        expect_pressed(false);
+        d.vm.execute(&mut d.frame, OpCode::KeyOp_Await { x: 0 }).unwrap();
+        assert_eq!(d.frame.PC, PC_START + 2);
+        assert_eq!(d.frame.registers[0], 3);
+    }
+
     #[test_case(None, PC_START ; "nothing pressed")]
     #[test_case(Some(0), PC_START + 2 ; "key pressed")]
     fn op_await_key(key: Option<u8>, pc: u16) {
@@ -859,7 +2982,7 @@ mod tests {
 
         {
             let mut input = d.input.lock().unwrap();
-            input.expect_get_pressed_key()
+            input.expect_take_pressed_key()
                 .times(1)
                 .return_const(key);
         }
@@ -890,15 +3013,38 @@ mod tests {
         assert_eq!(d.frame.PC, pc);
     }
 
+    #[test_case(0, 4, OpCode::Disp_Scroll_Down { n: 4 } ; "Disp_Scroll_Down")]
+    #[test_case(4, 0, OpCode::Disp_Scroll_Right ; "Disp_Scroll_Right")]
+    #[test_case(-4, 0, OpCode::Disp_Scroll_Left ; "Disp_Scroll_Left")]
+    fn op_disp_scroll(dx: isize, dy: isize, opcode: OpCode) {
+        let mut d = new();
+        d.frame.PC = PC_START;
+
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen
+                .expect_scroll()
+                .withf(move |x: &isize, y: &isize| *x == dx && *y == dy)
+                .times(1)
+                .return_const(());
+        }
+
+        d.vm.execute(&mut d.frame, opcode).unwrap();
+
+        assert_eq!(d.frame.PC, PC_START + 2);
+    }
+
     #[test]
     fn op_rand() {
-        let mut d = new();
+        let mut config = Config::default();
+        config.seed = Some(28);
 
-        get_random.mock_safe(|| MockResult::Return(28));
+        let mut d = with_config(&config, &vec![0x00, 0xE0]);
+        let number: u8 = StdRng::seed_from_u64(28).gen();
 
         d.vm.execute(&mut d.frame, OpCode::Rand { x: 0 , nn: 0xAB }).unwrap();
 
-        assert_ne!(d.frame.registers[0], 28);
+        assert_eq!(d.frame.registers[0], number & 0xAB);
     }
 
     #[test_case(255, 0b0010, 0b0101, 0b0101)]
@@ -918,6 +3064,78 @@ mod tests {
         assert_eq!(d.frame.memory[(d.frame.I + 2) as usize], ones);
     }
 
+    // executes the opcode with V0 = a and V1 = b, returns V0 and VF
+    fn execute_math(a: u8, b: u8, opcode: OpCode) -> (u8, u8) {
+        let mut d = new();
+        d.frame.registers[0] = a;
+        d.frame.registers[1] = b;
+
+        d.vm.execute(&mut d.frame, opcode).unwrap();
+
+        (d.frame.registers[0], d.frame.registers[0xF])
+    }
+
+    proptest! {
+        #[test]
+        fn prop_math_add_carries_overflow(a in any::<u8>(), b in any::<u8>()) {
+            let (result, vf) = execute_math(a, b, OpCode::Math_Add { x: 0, y: 1 });
+
+            prop_assert_eq!(result as u16 + vf as u16 * 256, a as u16 + b as u16);
+        }
+
+        #[test]
+        fn prop_math_minus_borrows(a in any::<u8>(), b in any::<u8>()) {
+            let (result, vf) = execute_math(a, b, OpCode::Math_Minus { x: 0, y: 1 });
+
+            prop_assert_eq!(result.wrapping_add(b), a);
+            prop_assert_eq!(vf == 1, a >= b);
+        }
+
+        #[test]
+        fn prop_math_minus_reverse_borrows(a in any::<u8>(), b in any::<u8>()) {
+            let (result, vf) = execute_math(a, b, OpCode::Math_Minus_Reverse { x: 0, y: 1 });
+
+            prop_assert_eq!(result.wrapping_add(a), b);
+            prop_assert_eq!(vf == 1, b >= a);
+        }
+
+        #[test]
+        fn prop_math_add_then_minus_restores(a in any::<u8>(), b in any::<u8>()) {
+            let (sum, _) = execute_math(a, b, OpCode::Math_Add { x: 0, y: 1 });
+            let (result, _) = execute_math(sum, b, OpCode::Math_Minus { x: 0, y: 1 });
+
+            prop_assert_eq!(result, a);
+        }
+
+        #[test]
+        fn prop_shift_right_divides(a in any::<u8>()) {
+            let (result, vf) = execute_math(a, 0, OpCode::BitOp_Shift_Right { x: 0, y: 1 });
+
+            prop_assert_eq!(result, a / 2);
+            prop_assert_eq!(vf, a % 2);
+        }
+
+        #[test]
+        fn prop_shift_left_multiplies(a in any::<u8>()) {
+            let (result, vf) = execute_math(a, 0, OpCode::BitOp_Shift_Left { x: 0, y: 1 });
+
+            prop_assert_eq!(result as u16 + vf as u16 * 256, a as u16 * 2);
+        }
+
+        #[test]
+        fn prop_bcd_digits_reassemble(value in any::<u8>()) {
+            let mut d = new();
+            d.frame.registers[0] = value;
+            d.frame.I = 0x300;
+
+            d.vm.execute(&mut d.frame, OpCode::BCD { x: 0 }).unwrap();
+
+            let digits = &d.frame.memory[0x300..0x303];
+            prop_assert!(digits.iter().all(|x| *x < 10));
+            prop_assert_eq!(digits[0] as u16 * 100 + digits[1] as u16 * 10 + digits[2] as u16, value as u16);
+        }
+    }
+
     #[test]
     fn op_timer_delay_get() {
         let mut d = new();
@@ -1039,4 +3257,306 @@ mod tests {
 
         assert_eq!(d.frame.I, address);
     }
+
+    #[test_case(false, 1, OpCode::BitOp_Or { x: 0, y: 1 } ; "BitOp_Or keeps VF")]
+    #[test_case(true, 0, OpCode::BitOp_Or { x: 0, y: 1 } ; "BitOp_Or resets VF")]
+    #[test_case(true, 0, OpCode::BitOp_And { x: 0, y: 1 } ; "BitOp_And resets VF")]
+    #[test_case(true, 0, OpCode::BitOp_Xor { x: 0, y: 1 } ; "BitOp_Xor resets VF")]
+    fn op_logic_resets_vf_with_quirk(resets_vf: bool, vf: u8, opcode: OpCode) {
+        let mut d = new();
+        d.vm.set_quirks(Quirks {
+            logic_resets_vf: resets_vf,
+            ..Quirks::default()
+        });
+        d.frame.registers[0xF] = 1;
+
+        d.vm.execute(&mut d.frame, opcode).unwrap();
+
+        assert_eq!(d.frame.registers[0xF], vf);
+    }
+
+    #[test_case(false, 0x210 + 0x01)]
+    #[test_case(true, 0x210 + 0x22)]
+    fn op_flow_jump_offset(uses_vx: bool, address: u16) {
+        let mut d = new();
+        d.vm.set_quirks(Quirks {
+            jump_uses_vx: uses_vx,
+            ..Quirks::default()
+        });
+        d.frame.registers[0] = 0x01;
+        d.frame.registers[2] = 0x22;
+
+        d.vm.execute(&mut d.frame, OpCode::Flow_Jump_Offset { nnn: 0x210 }).unwrap();
+
+        assert_eq!(d.frame.PC, address);
+    }
+
+    #[test_case(false, 0x303)]
+    #[test_case(true, 0x300)]
+    fn op_mem_reg_dump_and_load_advance_i_without_quirk(keeps_i: bool, address: u16) {
+        let mut d = new();
+        d.vm.set_quirks(Quirks {
+            load_store_keeps_i: keeps_i,
+            ..Quirks::default()
+        });
+        d.frame.registers[2] = 0x42;
+        d.frame.I = 0x300;
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Reg_Dump { x: 2 }).unwrap();
+        assert_eq!(d.frame.I, address);
+
+        d.frame.I = 0x300;
+        d.frame.registers[2] = 0;
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Reg_Load { x: 2 }).unwrap();
+        assert_eq!(d.frame.I, address);
+        assert_eq!(d.frame.registers[2], 0x42);
+    }
+
+    #[test]
+    fn quirks_change_shift_jump_and_load_store() {
+        let mut config = Config::default();
+        config.detect_quirks = false;
+        config.quirks = Quirks {
+            shift_uses_vy: true,
+            load_store_keeps_i: true,
+            jump_uses_vx: true,
+            logic_resets_vf: true,
+            sprites_wrap: false,
+            display_wait: false,
+            await_key_release: false,
+        };
+
+        let mut d = with_config(&config, &vec![0, 0]);
+        d.frame.registers[1] = 0x10;
+        d.frame.registers[2] = 0x04;
+        d.frame.I = 0x300;
+
+        d.vm.execute(&mut d.frame, OpCode::BitOp_Shift_Right { x: 1, y: 2 }).unwrap();
+        assert_eq!(d.frame.registers[1], 0x02);
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Reg_Dump { x: 2 }).unwrap();
+        assert_eq!(d.frame.I, 0x300);
+
+        d.vm.execute(&mut d.frame, OpCode::Flow_Jump_Offset { nnn: 0x210 }).unwrap();
+        assert_eq!(d.frame.PC, 0x214);
+
+        d.frame.registers[0xF] = 1;
+        d.vm.execute(&mut d.frame, OpCode::BitOp_Or { x: 1, y: 2 }).unwrap();
+        assert_eq!(d.frame.registers[0xF], 0);
+    }
+
+    #[test]
+    fn op_mem_set_sprite_i_uses_font_address() {
+        let mut config = Config::default();
+        config.font_address = 0x50;
+
+        let mut d = with_config(&config, &vec![0, 0]);
+        d.frame.registers[0] = 0x2;
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Set_Sprite_I { x: 0 }).unwrap();
+
+        assert_eq!(d.frame.I, 0x50 + 0x2 * FONT_SYMBOL_SIZE as u16);
+        assert_eq!(&d.vm.frames[0].memory[0x50..0x50 + FONTS.len()], &FONTS[..]);
+    }
+
+    #[test]
+    fn op_disp_large_draws_large_sprite() {
+        let mut d = new();
+        d.frame.I = 0x300;
+
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen
+                .expect_draw_large_sprite()
+                .times(1)
+                .return_const(DisplayState::Changed);
+        }
+
+        d.vm.execute(&mut d.frame, OpCode::Disp_Large { x: 1, y: 2 }).unwrap();
+
+        assert_eq!(d.frame.registers[0xF], 1);
+    }
+
+    #[test]
+    fn op_flags_dump_and_load() {
+        let mut d = new();
+        d.frame.registers[..9].copy_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Flags_Dump { x: 0xF }).unwrap();
+        d.frame.registers = [0; REGISTER_COUNT];
+        d.vm.execute(&mut d.frame, OpCode::MEM_Flags_Load { x: 0x3 }).unwrap();
+
+        assert_eq!(&d.frame.registers[..5], &[1, 2, 3, 4, 0]);
+    }
+
+    #[test]
+    fn restored_rpl_flags_are_loaded() {
+        let mut d = with_config(&Config::default(), &vec![0xF1, 0x85]);
+        d.vm.set_rpl_flags([7, 8, 0, 0, 0, 0, 0, 0]);
+
+        d.vm.step().unwrap();
+
+        assert_eq!(&d.vm.get_current_frame().registers[..3], &[7, 8, 0]);
+    }
+
+    #[test]
+    fn op_mem_set_large_sprite_i_points_after_font() {
+        let mut d = new();
+        d.frame.registers[0] = 0x7;
+
+        d.vm.execute(&mut d.frame, OpCode::MEM_Set_Large_Sprite_I { x: 0 }).unwrap();
+
+        let address = d.frame.I as usize;
+        assert_eq!(&d.vm.frames[0].memory[address..address + BIG_FONT_SYMBOL_SIZE], &BIG_FONTS[70..80]);
+    }
+
+    #[test]
+    fn op_flow_exit_keeps_pc() {
+        let mut d = new();
+        d.frame.PC = 0x246;
+
+        d.vm.execute(&mut d.frame, OpCode::Flow_Exit).unwrap();
+
+        assert_eq!(d.frame.PC, 0x246);
+        assert_eq!(d.vm.exit_address, Some(0x246));
+        assert!(d.vm.has_exited());
+    }
+
+    #[test]
+    fn xo_chip_rom_loads_beyond_4k() {
+        let mut rom = vec![0xF0, 0x00, 0x12, 0x34];
+        rom.resize(MEMORY_SIZE + 16, 0xAB);
+
+        let mut d = with_config(&Config::default(), &rom);
+        d.vm.step().unwrap();
+
+        let frame = d.vm.get_current_frame();
+        assert_eq!(frame.memory.len(), XO_CHIP_MEMORY_SIZE);
+        assert_eq!(frame.memory[PC_START as usize + MEMORY_SIZE + 15], 0xAB);
+        assert_eq!(frame.I, 0x1234);
+        assert_eq!(frame.PC, PC_START + 4);
+    }
+
+    #[test_case(Some(1024 * 8), Some(1024 * 8) ; "configured")]
+    #[test_case(Some(1024), None ; "too small")]
+    #[test_case(None, Some(MEMORY_SIZE) ; "platform")]
+    fn new_sizes_memory_from_config(memory_size: Option<usize>, expected: Option<usize>) {
+        let mut config = Config::default();
+        config.memory_size = memory_size;
+
+        let display = Arc::new(Mutex::new(MockDisplay::new()));
+        let input = Arc::new(Mutex::new(MockInput::new()));
+        let (_, rx) = channel::<DebuggerCommand>();
+
+        let vm = Vm::new(
+            &config,
+            &vec![0, 0],
+            display,
+            input,
+            Arc::new(Mutex::new(Audio::new())),
+            Debugger::new(
+                &config,
+                Arc::new(AtomicBool::new(false)),
+                rx,
+                Arc::new(Mutex::new(Breakpoints::new())),
+                Arc::new(Mutex::new(Watchpoints::new(Vec::new()))),
+            ),
+            Arc::new(Mutex::new(Cheats::default())),
+        );
+
+        assert_eq!(vm.ok().map(|x| x.get_current_frame().memory.len()), expected);
+    }
+
+    #[test]
+    fn timers_run_at_60hz_regardless_of_hz() {
+        let mut config = Config::default();
+        config.hz = 30;
+        config.use_rom_database = false;
+
+        let mut d = with_config(&config, &vec![0x12, 0x00]);
+        d.vm.frames[0].delay_timer.set_scaled(10);
+
+        // an instruction every 6th tick, the last one sees all the time which passed
+        for _ in 0..24 {
+            d.vm.tick(TIMER_DURATION_NANO / 2).unwrap();
+        }
+
+        assert_eq!(d.vm.get_current_frame().delay_timer.get_scaled(), 0);
+        assert_eq!(d.vm.get_frame_count(), 12);
+    }
+
+    #[test]
+    fn frame_scheduler_runs_instructions_at_frame_start() {
+        let mut config = Config::default();
+        config.hz = 600;
+        config.use_rom_database = false;
+        config.scheduler = Scheduler::Frame;
+
+        let mut d = with_config(&config, &vec![0x70, 0x01, 0x12, 0x00]);
+
+        d.vm.tick(TIMER_DURATION_NANO / 2).unwrap();
+        assert_eq!(d.vm.get_current_frame().registers[0], 0);
+
+        d.vm.tick(TIMER_DURATION_NANO / 2).unwrap();
+        assert_eq!(d.vm.get_current_frame().registers[0], 5);
+        assert_eq!(d.vm.get_frame_count(), 1);
+    }
+
+    #[test]
+    fn movie_input_is_latched_per_frame() {
+        let mut d = with_config(&Config::default(), &vec![0xE0, 0x9E, 0x12, 0x00, 0x71, 0x01, 0x12, 0x00]);
+        d.input.lock().unwrap().expect_is_pressed().return_const(true);
+
+        d.vm.set_movie_input(MovieInput::playing(vec![0, 1, 1, 0]));
+        for _ in 0..3 {
+            d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        }
+        assert_eq!(d.vm.get_current_frame().registers[1], 1);
+
+        // the keyboard is used again after the last frame of the movie
+        d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        assert!(d.vm.movie_input.is_none());
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 4);
+
+        d.vm.set_movie_input(MovieInput::recording());
+        d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        assert_eq!(d.vm.take_movie_input(), vec![0xFFFF, 0xFFFF]);
+    }
+
+    #[test]
+    fn draw_waits_for_display_with_quirk() {
+        let mut config = Config::default();
+        config.detect_quirks = false;
+        config.quirks.display_wait = true;
+
+        let mut d = with_config(&config, &vec![0xD0, 0x11, 0x70, 0x05]);
+        {
+            let mut screen = d.display.lock().unwrap();
+            screen
+                .expect_draw_sprite()
+                .times(1)
+                .return_const(DisplayState::Unchanged);
+        }
+
+        d.vm.cycle(1000).unwrap();
+        d.vm.cycle(1000).unwrap();
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 2);
+
+        d.vm.cycle(TIMER_DURATION_NANO).unwrap();
+        assert_eq!(d.vm.get_current_frame().PC, PC_START + 4);
+        assert_eq!(d.vm.get_current_frame().registers[0], 5);
+    }
+
+    #[test_case(0x00, PC_START + 6 ; "long instruction")]
+    #[test_case(0x01, PC_START + 4 ; "short instruction")]
+    fn skip_jumps_over_long_instruction(low_byte: u8, expected: u16) {
+        let mut d = new();
+        d.frame.PC = PC_START;
+        d.frame.memory[PC_START as usize + 2..PC_START as usize + 4].copy_from_slice(&[0xF0, low_byte]);
+
+        d.vm.execute(&mut d.frame, OpCode::Cond_Eq_Const { x: 0, nn: 0 }).unwrap();
+
+        assert_eq!(d.frame.PC, expected);
+    }
 }
@@ -0,0 +1,101 @@
+use super::condition::ConditionContext;
+use super::symbols;
+
+// a value printed whenever the debugger halts or steps, e.g. V3, I, DT, [0x300] or [0x300:4] for 4 bytes
+#[derive(Debug, Clone, PartialEq)]
+pub enum Watch {
+    Register(usize),
+    I,
+    Pc,
+    DelayTimer,
+    SoundTimer,
+    Memory { address: usize, len: usize },
+}
+
+// addresses and lengths are 16 bit like the ones of the memory command, so a watch stays cheap to print
+fn parse_number(text: &str) -> Result<usize, String> {
+    match symbols::parse_address(text) {
+        Some(value) => Ok(value as usize),
+        None => Err(format!("{} is not a 16 bit number", text)),
+    }
+}
+
+impl Watch {
+    pub fn parse(text: &str) -> Result<Watch, String> {
+        let text = text.trim();
+        let upper = text.to_uppercase();
+
+        let watch = match upper.as_str() {
+            "I" => Watch::I,
+            "PC" => Watch::Pc,
+            "DT" => Watch::DelayTimer,
+            "ST" => Watch::SoundTimer,
+            _ if upper.starts_with('V') && upper.len() == 2 => Watch::Register(
+                usize::from_str_radix(&upper[1..], 16).map_err(|_| format!("{} is not a register", text))?,
+            ),
+            _ if text.starts_with('[') && text.ends_with(']') => {
+                let inner = &text[1..text.len() - 1];
+                let mut parts = inner.splitn(2, ':');
+                let address = parse_number(parts.next().unwrap_or_default().trim())?;
+                let len = match parts.next() {
+                    Some(len) => parse_number(len.trim())?,
+                    None => 1,
+                };
+
+                Watch::Memory { address, len }
+            }
+            _ => return Err(format!("{} is not a register, I, PC, DT, ST or [address:len]", text)),
+        };
+
+        Ok(watch)
+    }
+
+    // memory outside of the memory is shown as --
+    pub fn format(&self, context: &ConditionContext, memory: &[u8]) -> String {
+        match *self {
+            Watch::Register(x) => format!("{:#04X}", context.registers.get(x).copied().unwrap_or(0)),
+            Watch::I => format!("{:#05X}", context.i),
+            Watch::Pc => format!("{:#05X}", context.pc),
+            Watch::DelayTimer => format!("{}", context.delay_timer),
+            Watch::SoundTimer => format!("{}", context.sound_timer),
+            Watch::Memory { address, len } => (address..address.saturating_add(len))
+                .map(|x| match memory.get(x) {
+                    Some(value) => format!("{:02X}", value),
+                    None => String::from("--"),
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watches_format_the_values() {
+        let mut registers = [0u8; 16];
+        registers[3] = 0x1F;
+        let context = ConditionContext {
+            registers: &registers,
+            i: 0x2A0,
+            pc: 0x202,
+            delay_timer: 60,
+            sound_timer: 0,
+        };
+        let memory = [0x01, 0x02, 0x03];
+
+        let format = |text: &str| Watch::parse(text).unwrap().format(&context, &memory);
+        assert_eq!(format("v3"), "0x1F");
+        assert_eq!(format("I"), "0x2A0");
+        assert_eq!(format("DT"), "60");
+        assert_eq!(format("[1]"), "02");
+        assert_eq!(format("[0x1:4]"), "02 03 -- --");
+
+        assert!(Watch::parse("VG").is_err());
+        assert!(Watch::parse("[0x300").is_err());
+        assert!(Watch::parse("[x:2]").is_err());
+        assert!(Watch::parse("[0x300:0x10000]").is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use strum_macros::Display;
+
+#[derive(Display, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    fn includes(self, access: Access) -> bool {
+        self == Access::ReadWrite || self == access
+    }
+}
+
+// breaks when an instruction reads or writes memory in start..=end
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Watchpoint {
+    pub start: u16,
+    // the same as start for a single address
+    #[serde(default)]
+    pub end: Option<u16>,
+    pub access: Access,
+    // watchpoints of the config do not list it
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+
+    #[serde(skip)]
+    pub hit_count: u64,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Watchpoint {
+    pub fn new(start: u16, end: Option<u16>, access: Access) -> Watchpoint {
+        Watchpoint {
+            start,
+            end,
+            access,
+            enabled: true,
+            hit_count: 0,
+        }
+    }
+
+    fn covers(&self, other: &Watchpoint) -> bool {
+        self.start == other.start && self.end() == other.end() && self.access == other.access
+    }
+
+    fn end(&self) -> u16 {
+        self.end.unwrap_or(self.start).max(self.start)
+    }
+
+    fn overlaps(&self, address: usize, len: usize) -> bool {
+        address <= self.end() as usize && address + len > self.start as usize
+    }
+}
+
+impl fmt::Display for Watchpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.end() == self.start {
+            true => write!(f, "{} {:#05X}", self.access, self.start),
+            false => write!(f, "{} {:#05X}..={:#05X}", self.access, self.start, self.end()),
+        }
+    }
+}
+
+// a memory access which touched a watchpoint
+#[derive(Debug, Clone, PartialEq)]
+pub struct WatchpointHit {
+    pub frame: u64,
+    pub access: Access,
+    pub address: usize,
+    pub len: usize,
+    // address of the instruction which accessed the memory
+    pub pc: u16,
+}
+
+impl fmt::Display for WatchpointHit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "frame {:>6}  {} of {} bytes at {:#05X}  PC {:#05X}",
+            self.frame, self.access, self.len, self.address, self.pc
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Watchpoints {
+    entries: Vec<Watchpoint>,
+    path: Option<PathBuf>,
+}
+
+impl Watchpoints {
+    pub fn new(entries: Vec<Watchpoint>) -> Watchpoints {
+        Watchpoints { entries, path: None }
+    }
+
+    // loads the watchpoints persisted for a ROM, changes are written back to the same file
+    pub fn load(path: PathBuf) -> Watchpoints {
+        let entries = match fs::read_to_string(&path) {
+            Ok(yaml) => match serde_yaml::from_str::<Vec<Watchpoint>>(&yaml) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    warn!("Failed to parse watchpoints {}: {}", path.display(), err);
+                    Vec::new()
+                }
+            },
+            Err(..) => Vec::new(),
+        };
+
+        Watchpoints {
+            entries,
+            path: Some(path),
+        }
+    }
+
+    pub fn entries(&self) -> &[Watchpoint] {
+        &self.entries
+    }
+
+    // like breakpoints an existing watchpoint is kept, it is enabled again
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        match self.entries.iter_mut().find(|x| x.covers(&watchpoint)) {
+            Some(existing) => existing.enabled = true,
+            None => self.entries.push(watchpoint),
+        }
+
+        self.save();
+    }
+
+    pub fn toggle_enabled(&mut self, index: usize) {
+        if let Some(watchpoint) = self.entries.get_mut(index) {
+            watchpoint.enabled = !watchpoint.enabled;
+            self.save();
+        }
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.entries.len() {
+            self.entries.remove(index);
+            self.save();
+        }
+    }
+
+    // removes every watchpoint which covers the address
+    pub fn remove_at(&mut self, address: u16) {
+        self.entries.retain(|x| x.overlaps(address as usize, 1) == false);
+        self.save();
+    }
+
+    // counts the hit of every enabled watchpoint the access touches
+    pub(super) fn hit(&mut self, address: usize, len: usize, access: Access) -> bool {
+        let mut hit = false;
+
+        for watchpoint in self
+            .entries
+            .iter_mut()
+            .filter(|x| x.enabled && x.access.includes(access) && x.overlaps(address, len))
+        {
+            watchpoint.hit_count += 1;
+            hit = true;
+        }
+
+        hit
+    }
+
+    // whether the memory of an enabled write watchpoint differs, reads leave no trace in the memory
+    pub(super) fn written(&self, before: &[u8], after: &[u8]) -> bool {
+        self.entries.iter().filter(|x| x.enabled && x.access.includes(Access::Write)).any(|x| {
+            let range = x.start as usize..(x.end() as usize + 1).min(before.len());
+            before.get(range.clone()) != after.get(range)
+        })
+    }
+
+    fn save(&self) {
+        if let Some(path) = &self.path {
+            if let Err(err) = self.write(path) {
+                warn!("Failed to save watchpoints to {}: {}", path.display(), err);
+            }
+        }
+    }
+
+    fn write(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(path, serde_yaml::to_string(&self.entries)?)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hit_matches_access_and_range() {
+        let mut watchpoints = Watchpoints::new(vec![
+            Watchpoint::new(0x300, Some(0x303), Access::Write),
+            Watchpoint::new(0x400, None, Access::ReadWrite),
+        ]);
+
+        assert!(watchpoints.hit(0x2FE, 3, Access::Write));
+        assert!(watchpoints.hit(0x303, 1, Access::Write));
+        assert!(watchpoints.hit(0x300, 1, Access::Read) == false);
+        assert!(watchpoints.hit(0x304, 2, Access::Write) == false);
+        assert!(watchpoints.hit(0x400, 1, Access::Read));
+        assert!(watchpoints.hit(0x401, 1, Access::Read) == false);
+        assert_eq!(watchpoints.entries()[0].hit_count, 2);
+    }
+
+    #[test]
+    fn disabled_watchpoints_do_not_hit() {
+        let mut watchpoints = Watchpoints::new(vec![Watchpoint::new(0x300, None, Access::Write)]);
+        watchpoints.toggle_enabled(0);
+
+        assert!(watchpoints.hit(0x300, 1, Access::Write) == false);
+        assert_eq!(watchpoints.entries()[0].hit_count, 0);
+
+        watchpoints.add(Watchpoint::new(0x300, Some(0x300), Access::Write));
+        assert_eq!(watchpoints.entries().len(), 1);
+        assert!(watchpoints.hit(0x300, 1, Access::Write));
+    }
+
+    #[test]
+    fn written_compares_write_watchpoints() {
+        let watchpoints = Watchpoints::new(vec![
+            Watchpoint::new(0x300, Some(0x303), Access::Write),
+            Watchpoint::new(0x400, None, Access::Read),
+        ]);
+
+        let before = vec![0u8; 0x1000];
+        let mut after = before.clone();
+        after[0x400] = 1;
+        assert!(watchpoints.written(&before, &after) == false);
+
+        after[0x303] = 1;
+        assert!(watchpoints.written(&before, &after));
+    }
+}